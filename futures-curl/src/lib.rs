@@ -82,6 +82,7 @@ use futures::stream::Stream;
 use futures::{Future, Task, TaskHandle, Poll, promise, Promise, Complete};
 use futures_io::{Ready, IoFuture};
 use futures_mio::{LoopHandle, LoopData, Timeout, Source, ReadinessStream};
+use futures_mio::Trigger;
 use mio::unix::EventedFd;
 
 /// A shared cache for HTTP requests to pool data such as TCP connections
@@ -275,9 +276,11 @@ impl Data {
         }
 
         // Next, if this socket has already been registered, then we just
-        // updated the events that it's waiting for.
+        // updated the events that it's waiting for, and only listen for those
+        // on the event loop.
         if let Some(state) = state.io.get_mut(&socket) {
             debug!("socket already registered: {}", socket);
+            state.stream.set_interest(interest(&events));
             state.want = Some(events);
             return
         }
@@ -291,7 +294,10 @@ impl Data {
         // should be immediately resolve-able because we're guaranteed to be on
         // the event loop.
         debug!("schedule socket {}", socket);
-        let source = Arc::new(Source::new(MioSocket { inner: socket }));
+        let source = Source::with_interest(MioSocket { inner: socket },
+                                           interest(&events),
+                                           Trigger::Edge);
+        let source = Arc::new(source);
         let mut ready = ReadinessStream::new(self.handle.clone(), source);
         let stream = match ready.poll(&mut state.task) {
             Poll::Ok(stream) => stream,
@@ -571,3 +577,16 @@ impl mio::Evented for MioSocket {
         EventedFd(&self.inner).deregister(poll)
     }
 }
+
+/// Returns the readiness that libcurl asked for with `events`.
+fn interest(events: &SocketEvents) -> Ready {
+    if events.input_and_output() {
+        Ready::ReadWrite
+    } else if events.input() {
+        Ready::Read
+    } else if events.output() {
+        Ready::Write
+    } else {
+        Ready::ReadWrite
+    }
+}
@@ -128,6 +128,24 @@ impl<W: WriteTask> WriteTask for BufWriter<W> {
         }
     }
 
+    fn write_vectored(&mut self,
+                      task: &mut Task,
+                      bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let total = bufs.iter().fold(0, |sum, b| sum + b.len());
+        if self.flushing || self.buf.len() + total > self.buf.capacity() {
+            try!(self.flush_buf(task));
+        }
+        if total >= self.buf.capacity() {
+            assert_eq!(self.buf.len(), 0);
+            self.inner.write_vectored(task, bufs)
+        } else {
+            for buf in bufs {
+                self.buf.extend_from_slice(buf);
+            }
+            Ok(total)
+        }
+    }
+
     fn flush(&mut self, task: &mut Task) -> io::Result<()> {
         try!(self.flush_buf(task));
         self.inner.flush(task)
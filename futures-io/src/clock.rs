@@ -0,0 +1,16 @@
+use std::time::{Duration, Instant};
+
+use futures::Future;
+
+/// A source of the current time and of timeouts, used by `Throttled` and
+/// `IoTimeout` to know when to perform more I/O or give up waiting for it.
+///
+/// This is implemented for `futures_mio::LoopHandle`, for example, and can be
+/// implemented on top of a virtual clock for tests.
+pub trait Clock: Send + 'static {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+
+    /// Returns a future which resolves once `dur` has elapsed.
+    fn sleep(&self, dur: Duration) -> Box<Future<Item=(), Error=()>>;
+}
@@ -0,0 +1,226 @@
+use std::io;
+
+use framed::{Decoder, Encoder};
+
+/// A codec for frames delimited by newlines.
+///
+/// Decoded lines are `String`s without the trailing `\n` (or `\r\n`), and
+/// lines which are not valid UTF-8 generate an `InvalidData` error. Encoding a
+/// line appends a `\n` to it.
+pub struct LinesCodec {
+    max_length: usize,
+}
+
+impl LinesCodec {
+    /// Creates a new codec for lines of any length.
+    pub fn new() -> LinesCodec {
+        LinesCodec { max_length: usize::max_value() }
+    }
+
+    /// Creates a new codec which will return an error when decoding a line
+    /// longer than `max_length` bytes.
+    ///
+    /// This is useful to guard against peers sending an endless line and
+    /// forcing unbounded buffering.
+    pub fn with_max_length(max_length: usize) -> LinesCodec {
+        LinesCodec { max_length: max_length }
+    }
+
+    /// Returns the maximum line length when decoding.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    fn line(&self, mut line: Vec<u8>) -> io::Result<String> {
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        String::from_utf8(line).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData,
+                           "line is not valid UTF-8")
+        })
+    }
+}
+
+// Returns the length of `line` without a trailing `\r`.
+fn line_len(line: &[u8]) -> usize {
+    if line.last() == Some(&b'\r') {
+        line.len() - 1
+    } else {
+        line.len()
+    }
+}
+
+fn too_long() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "frame exceeds maximum length")
+}
+
+impl Decoder for LinesCodec {
+    type Item = String;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<String>> {
+        match buf.iter().position(|b| *b == b'\n') {
+            Some(i) => {
+                if line_len(&buf[..i]) > self.max_length {
+                    return Err(too_long())
+                }
+                let line = buf.drain(..i + 1).collect();
+                self.line(line).map(Some)
+            }
+            // The last byte may be the `\r` of a `\r\n` which isn't part of
+            // the line, so allow for one more byte than the maximum.
+            None if buf.len() > self.max_length.saturating_add(1) => {
+                Err(too_long())
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<String>> {
+        match try!(self.decode(buf)) {
+            Some(line) => Ok(Some(line)),
+            None if buf.len() == 0 => Ok(None),
+            None if line_len(buf) > self.max_length => Err(too_long()),
+            None => {
+                let line = buf.drain(..).collect();
+                self.line(line).map(Some)
+            }
+        }
+    }
+}
+
+impl Encoder for LinesCodec {
+    type Item = String;
+    type Error = io::Error;
+
+    fn encode(&mut self, line: String, buf: &mut Vec<u8>) -> io::Result<()> {
+        buf.extend_from_slice(line.as_bytes());
+        buf.push(b'\n');
+        Ok(())
+    }
+}
+
+/// A codec for frames prefixed with their length.
+///
+/// Each frame is preceded by a header containing the length of the frame in
+/// bytes, not including the header itself. By default the header is a 4 byte
+/// big-endian integer and frames are limited to 8MB, both of which can be
+/// configured through the methods on this type.
+///
+/// Frames are decoded to and encoded from `Vec<u8>`.
+pub struct LengthDelimitedCodec {
+    length_field_len: usize,
+    big_endian: bool,
+    max_frame_len: usize,
+}
+
+impl LengthDelimitedCodec {
+    /// Creates a new codec with a 4 byte big-endian length header and a
+    /// maximum frame length of 8MB.
+    pub fn new() -> LengthDelimitedCodec {
+        LengthDelimitedCodec {
+            length_field_len: 4,
+            big_endian: true,
+            max_frame_len: 8 * 1024 * 1024,
+        }
+    }
+
+    /// Sets the number of bytes used to encode the length of a frame.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `len` is 0 or greater than 8.
+    pub fn length_field_length(&mut self, len: usize)
+                               -> &mut LengthDelimitedCodec {
+        assert!(len > 0 && len <= 8, "invalid length field length: {}", len);
+        self.length_field_len = len;
+        self
+    }
+
+    /// Configures the length header to be read and written in big-endian
+    /// (network) byte order, the default.
+    pub fn big_endian(&mut self) -> &mut LengthDelimitedCodec {
+        self.big_endian = true;
+        self
+    }
+
+    /// Configures the length header to be read and written in little-endian
+    /// byte order.
+    pub fn little_endian(&mut self) -> &mut LengthDelimitedCodec {
+        self.big_endian = false;
+        self
+    }
+
+    /// Sets the maximum length of a frame, not including the header.
+    ///
+    /// Decoding a header for a longer frame, or encoding a longer frame,
+    /// results in an `InvalidData` error.
+    pub fn max_frame_length(&mut self, len: usize)
+                            -> &mut LengthDelimitedCodec {
+        self.max_frame_len = len;
+        self
+    }
+
+    fn read_len(&self, header: &[u8]) -> u64 {
+        let mut n = 0u64;
+        if self.big_endian {
+            for b in header {
+                n = (n << 8) | *b as u64;
+            }
+        } else {
+            for b in header.iter().rev() {
+                n = (n << 8) | *b as u64;
+            }
+        }
+        n
+    }
+}
+
+impl Decoder for LengthDelimitedCodec {
+    type Item = Vec<u8>;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+        let hdr = self.length_field_len;
+        if buf.len() < hdr {
+            return Ok(None)
+        }
+        let len = self.read_len(&buf[..hdr]);
+        if len > self.max_frame_len as u64 {
+            return Err(too_long())
+        }
+        let len = len as usize;
+        if buf.len() < hdr + len {
+            let need = hdr + len - buf.len();
+            buf.reserve(need);
+            return Ok(None)
+        }
+        let frame = buf[hdr..hdr + len].to_vec();
+        buf.drain(..hdr + len);
+        Ok(Some(frame))
+    }
+}
+
+impl Encoder for LengthDelimitedCodec {
+    type Item = Vec<u8>;
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: Vec<u8>, buf: &mut Vec<u8>) -> io::Result<()> {
+        let len = frame.len() as u64;
+        let bits = self.length_field_len * 8;
+        if frame.len() > self.max_frame_len || (bits < 64 && len >> bits != 0) {
+            return Err(too_long())
+        }
+        let hdr = self.length_field_len;
+        for i in 0..hdr {
+            let shift = (if self.big_endian { hdr - 1 - i } else { i }) * 8;
+            buf.push((len >> shift) as u8);
+        }
+        buf.extend_from_slice(&frame);
+        Ok(())
+    }
+}
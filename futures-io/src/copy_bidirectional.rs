@@ -0,0 +1,160 @@
+use std::io;
+
+use futures::{Future, Poll, Task};
+use futures::stream::Stream;
+
+use {IoFuture, ReadTask, WriteTask, Ready, ShutdownWrite};
+use {split, ReadHalf, WriteHalf};
+
+/// Creates a future which copies data in both directions between two I/O
+/// objects, such as the two connections of a proxy.
+///
+/// Both objects are split into their read and write halves with `split`, and
+/// then data is pumped from `a` to `b`
+/// and from `b` to `a` concurrently. When one object hits EOF, the write half
+/// of the other is shut down once all data read has been written to it, so
+/// the "half close" is seen by the other end of that connection as well.
+///
+/// The returned future resolves once both directions have hit EOF, yielding
+/// the number of bytes copied from `a` to `b` and from `b` to `a`,
+/// respectively. Any error in either direction causes the future to resolve to
+/// that error, and both objects are dropped.
+pub fn copy_bidirectional<A, B>(a: A, b: B) -> Box<IoFuture<(u64, u64)>>
+    where A: io::Read + io::Write + Stream<Item=Ready, Error=io::Error>,
+          A: ShutdownWrite,
+          B: io::Read + io::Write + Stream<Item=Ready, Error=io::Error>,
+          B: ShutdownWrite,
+{
+    let (a_read, a_write) = split(a);
+    let (b_read, b_write) = split(b);
+    let a_to_b = Half::new(a_read, b_write);
+    let b_to_a = Half::new(b_read, a_write);
+    a_to_b.join(b_to_a).boxed()
+}
+
+/// One direction of a `copy_bidirectional`, like `Copy` except that the
+/// write half is shut down once everything has been copied.
+struct Half<R, W> {
+    reader: ReadHalf<R>,
+    read_ready: bool,
+    read_done: bool,
+    writer: WriteHalf<W>,
+    write_ready: bool,
+    pos: usize,
+    cap: usize,
+    amt: u64,
+    buf: Box<[u8]>,
+}
+
+impl<R, W> Half<R, W> {
+    fn new(reader: ReadHalf<R>, writer: WriteHalf<W>) -> Half<R, W> {
+        Half {
+            reader: reader,
+            read_ready: true,
+            read_done: false,
+            writer: writer,
+            write_ready: true,
+            pos: 0,
+            cap: 0,
+            amt: 0,
+            buf: Box::new([0; 8 * 1024]),
+        }
+    }
+}
+
+fn zero_write() -> io::Error {
+    io::Error::new(io::ErrorKind::WriteZero, "zero-length write")
+}
+
+impl<R, W> Future for Half<R, W>
+    where R: io::Read + io::Write + Stream<Item=Ready, Error=io::Error>,
+          W: io::Read + io::Write + Stream<Item=Ready, Error=io::Error>,
+          W: ShutdownWrite,
+{
+    type Item = u64;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<u64, io::Error> {
+        loop {
+            if !self.read_done && self.pos == self.cap {
+                if !self.read_ready {
+                    match try_poll!(self.reader.poll(task)) {
+                        Ok(Some(ref r)) if r.is_read() => self.read_ready = true,
+                        Ok(_) => return Poll::NotReady,
+                        Err(e) => return Poll::Err(e),
+                    }
+                }
+                match self.reader.read(task, &mut self.buf) {
+                    Ok(0) => {
+                        debug!("copy_bidirectional half at eof");
+                        self.read_done = true;
+                    }
+                    Ok(i) => {
+                        self.pos = 0;
+                        self.cap = i;
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        self.read_ready = false;
+                        return Poll::NotReady
+                    }
+                    Err(e) => return Poll::Err(e),
+                }
+            }
+
+            while self.pos < self.cap || self.read_done {
+                if !self.write_ready {
+                    match try_poll!(self.writer.poll(task)) {
+                        Ok(Some(ref r)) if r.is_write() => self.write_ready = true,
+                        Ok(_) => return Poll::NotReady,
+                        Err(e) => return Poll::Err(e),
+                    }
+                }
+                if self.pos == self.cap {
+                    // Everything has been read and written, so flush the
+                    // writer and then propagate the EOF we saw by shutting
+                    // down its write half.
+                    match self.writer.flush(task) {
+                        Ok(()) => {}
+                        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            self.write_ready = false;
+                            return Poll::NotReady
+                        }
+                        Err(e) => return Poll::Err(e),
+                    }
+                    match self.writer.shutdown_write() {
+                        // If the other end is already entirely gone then
+                        // there's nobody to signal EOF to.
+                        Ok(()) => {}
+                        Err(ref e) if e.kind() == io::ErrorKind::NotConnected => {}
+                        Err(e) => return Poll::Err(e),
+                    }
+                    return Poll::Ok(self.amt)
+                }
+                match self.writer.write(task, &self.buf[self.pos..self.cap]) {
+                    Ok(0) => return Poll::Err(zero_write()),
+                    Ok(i) => {
+                        self.pos += i;
+                        self.amt += i as u64;
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        self.write_ready = false;
+                        return Poll::NotReady
+                    }
+                    Err(e) => return Poll::Err(e),
+                }
+            }
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        if self.read_ready && self.write_ready {
+            task.notify();
+        }
+        if !self.read_ready && !self.read_done {
+            self.reader.schedule(task);
+        }
+        if !self.write_ready {
+            self.writer.schedule(task);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::mem;
+use std::net::Shutdown;
+use std::sync::{Arc, Mutex};
+
+use futures::{Poll, Task, TaskHandle};
+use futures::stream::Stream;
+
+use {Ready, ShutdownWrite};
+
+/// One end of an in-memory duplex pipe.
+///
+/// Created by the `duplex` function, each `DuplexStream` implements `Read`,
+/// `Write`, and `Stream<Item=Ready, Error=io::Error>`, and all bytes written to
+/// one end can be read from the other.
+///
+/// Like sockets registered with an event loop, readiness notifications have
+/// "edge" semantics. A `Read` notification is delivered once data arrives (or
+/// the other end is closed), and a `Write` notification is delivered once room
+/// is freed up in the pipe, but no further notification is delivered until the
+/// object has been read or written to the point of returning `WouldBlock`.
+pub struct DuplexStream {
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+}
+
+// One direction of a duplex pipe, shared between the end writing into it and
+// the end reading out of it.
+struct Pipe {
+    buf: VecDeque<u8>,
+    cap: usize,
+
+    // Whether the reading end or the writing end have been closed.
+    read_closed: bool,
+    write_closed: bool,
+
+    // Pending edge notifications for each end of this pipe, as well as the
+    // tasks waiting on those notifications.
+    read_ready: bool,
+    write_ready: bool,
+    reader: Option<TaskHandle>,
+    writer: Option<TaskHandle>,
+}
+
+/// Creates a new pair of connected in-memory I/O objects.
+///
+/// Each direction of the pipe buffers at most `capacity` bytes. Once that many
+/// bytes are buffered writes will return `WouldBlock` until the other end
+/// reads some of the data.
+///
+/// Dropping one end (or shutting down its write half) causes the other end to
+/// read EOF once all buffered data is read, and writes to an end whose peer
+/// has been dropped return a `BrokenPipe` error.
+///
+/// # Panics
+///
+/// This function will panic if `capacity` is 0.
+pub fn duplex(capacity: usize) -> (DuplexStream, DuplexStream) {
+    assert!(capacity > 0, "duplex capacity must be nonzero");
+    let a = Arc::new(Mutex::new(Pipe::new(capacity)));
+    let b = Arc::new(Mutex::new(Pipe::new(capacity)));
+    (DuplexStream { read: a.clone(), write: b.clone() },
+     DuplexStream { read: b, write: a })
+}
+
+impl Pipe {
+    fn new(cap: usize) -> Pipe {
+        Pipe {
+            buf: VecDeque::new(),
+            cap: cap,
+            read_closed: false,
+            write_closed: false,
+            read_ready: false,
+            write_ready: true,
+            reader: None,
+            writer: None,
+        }
+    }
+
+    fn wake_reader(&mut self) -> Option<TaskHandle> {
+        self.read_ready = true;
+        self.reader.take()
+    }
+
+    fn wake_writer(&mut self) -> Option<TaskHandle> {
+        self.write_ready = true;
+        self.writer.take()
+    }
+}
+
+fn notify(handle: Option<TaskHandle>) {
+    if let Some(handle) = handle {
+        handle.notify();
+    }
+}
+
+impl DuplexStream {
+    /// Shuts down the read, write, or both halves of this end of the pipe.
+    ///
+    /// Shutting down the write half causes the other end to see EOF once it
+    /// has read all buffered data, and shutting down the read half causes
+    /// writes from the other end to fail.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        if how != Shutdown::Write {
+            self.close_read();
+        }
+        if how != Shutdown::Read {
+            self.close_write();
+        }
+        Ok(())
+    }
+
+    fn close_read(&self) {
+        let to_wake = {
+            let mut pipe = self.read.lock().unwrap();
+            pipe.read_closed = true;
+            pipe.buf.clear();
+            pipe.wake_writer()
+        };
+        notify(to_wake);
+    }
+
+    fn close_write(&self) {
+        let to_wake = {
+            let mut pipe = self.write.lock().unwrap();
+            pipe.write_closed = true;
+            pipe.wake_reader()
+        };
+        notify(to_wake);
+    }
+}
+
+impl Read for DuplexStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_vectored(&mut [IoSliceMut::new(buf)])
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        let (n, to_wake) = {
+            let mut pipe = self.read.lock().unwrap();
+            let len = bufs.iter().fold(0, |sum, b| sum + b.len());
+            if pipe.buf.len() == 0 {
+                if pipe.write_closed || pipe.read_closed || len == 0 {
+                    return Ok(0)
+                }
+                return Err(io::Error::new(io::ErrorKind::WouldBlock,
+                                          "duplex pipe is empty"))
+            }
+            let mut n = 0;
+            for buf in bufs.iter_mut() {
+                let amt = cmp::min(buf.len(), pipe.buf.len());
+                for (slot, byte) in buf.iter_mut().zip(pipe.buf.drain(..amt)) {
+                    *slot = byte;
+                }
+                n += amt;
+            }
+            (n, pipe.wake_writer())
+        };
+        notify(to_wake);
+        Ok(n)
+    }
+}
+
+impl Write for DuplexStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_vectored(&[IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        let (n, to_wake) = {
+            let mut pipe = self.write.lock().unwrap();
+            if pipe.read_closed || pipe.write_closed {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe,
+                                          "duplex pipe is closed"))
+            }
+            if bufs.iter().all(|b| b.len() == 0) {
+                return Ok(0)
+            }
+            if pipe.buf.len() == pipe.cap {
+                return Err(io::Error::new(io::ErrorKind::WouldBlock,
+                                          "duplex pipe is full"))
+            }
+            let mut n = 0;
+            for buf in bufs {
+                let amt = cmp::min(buf.len(), pipe.cap - pipe.buf.len());
+                pipe.buf.extend(buf[..amt].iter().cloned());
+                n += amt;
+            }
+            (n, pipe.wake_reader())
+        };
+        notify(to_wake);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Stream for DuplexStream {
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, _task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        let read = {
+            let mut pipe = self.read.lock().unwrap();
+            mem::replace(&mut pipe.read_ready, false)
+        };
+        let write = {
+            let mut pipe = self.write.lock().unwrap();
+            mem::replace(&mut pipe.write_ready, false)
+        };
+        match (read, write) {
+            (true, true) => Poll::Ok(Some(Ready::ReadWrite)),
+            (true, false) => Poll::Ok(Some(Ready::Read)),
+            (false, true) => Poll::Ok(Some(Ready::Write)),
+            (false, false) => Poll::NotReady,
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        let read = {
+            let mut pipe = self.read.lock().unwrap();
+            pipe.reader = Some(task.handle().clone());
+            pipe.read_ready
+        };
+        let write = {
+            let mut pipe = self.write.lock().unwrap();
+            pipe.writer = Some(task.handle().clone());
+            pipe.write_ready
+        };
+        if read || write {
+            task.notify();
+        }
+    }
+}
+
+impl ShutdownWrite for DuplexStream {
+    fn shutdown_write(&mut self) -> io::Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+}
+
+impl Drop for DuplexStream {
+    fn drop(&mut self) {
+        self.close_read();
+        self.close_write();
+    }
+}
@@ -0,0 +1,573 @@
+//! Compression and decompression of I/O objects with gzip, zlib, or raw
+//! deflate.
+//!
+//! The `FlateReader` and `FlateWriter` types in this module wrap any object
+//! implementing `Read` (or `Write`) as well as `Stream<Item=Ready>`, similar to
+//! `BufReader` and `BufWriter`, and transparently decompress data read or
+//! compress data written. Readiness notifications of the underlying object are
+//! passed through, and a `WouldBlock` error from the underlying object never
+//! loses any data, so these can be used with all the combinators in this
+//! crate.
+//!
+//! This module is only available when the `flate2` feature of this crate is
+//! enabled.
+
+use std::cmp;
+use std::io::{self, Read, Write};
+
+use flate2::{Compress, Crc, Decompress, FlushCompress, FlushDecompress, Status};
+use futures::{Future, Poll, Task};
+use futures::stream::Stream;
+
+use Ready;
+
+pub use flate2::Compression;
+
+const BUF_SIZE: usize = 8 * 1024;
+
+// The fixed header we emit for gzip streams: no flags, no modification time,
+// no extra flags, and an "unknown" operating system.
+const GZIP_HEADER: [u8; 10] = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+
+const FHCRC: u8 = 1 << 1;
+const FEXTRA: u8 = 1 << 2;
+const FNAME: u8 = 1 << 3;
+const FCOMMENT: u8 = 1 << 4;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Format {
+    Gzip,
+    Zlib,
+    Deflate,
+}
+
+/// A reader which decompresses all data read from an underlying I/O object.
+///
+/// Created through the `gzip`, `zlib`, or `deflate` constructors depending on
+/// the format of the compressed data. Once the end of the compressed stream has
+/// been reached this reader returns EOF. Data is read from the underlying
+/// object in large chunks, so some of the data following the compressed stream
+/// may have been read already, and it is discarded along with this reader.
+pub struct FlateReader<R> {
+    inner: R,
+    format: Format,
+    state: ReadState,
+    decompress: Decompress,
+    crc: Crc,
+    buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+    eof: bool,
+    // Whether the last call to the decompressor filled all of the output
+    // space it was given, in which case it may still have more to give us
+    // without reading anything more from the underlying object.
+    pending: bool,
+    // Bytes of a gzip header or trailer which have been read so far.
+    partial: Vec<u8>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum ReadState {
+    Header,
+    Body,
+    Trailer,
+    Done,
+}
+
+/// A writer which compresses all data before writing it to an underlying I/O
+/// object.
+///
+/// Created through the `gzip`, `zlib`, or `deflate` constructors depending on
+/// the desired format of the compressed data.
+///
+/// Compressed data is buffered internally, so like `BufWriter` this object
+/// must be flushed for all data written so far to reach the underlying object.
+/// Once all data has been written the `finish` method must be used to write
+/// out the end of the compressed stream.
+pub struct FlateWriter<W> {
+    inner: W,
+    format: Format,
+    compress: Compress,
+    crc: Crc,
+    buf: Vec<u8>,
+    header_done: bool,
+    finished: bool,
+}
+
+/// A future which finishes the compressed stream of a `FlateWriter`.
+///
+/// Created by the `FlateWriter::finish` method, resolves to the underlying I/O
+/// object once the rest of the compressed stream has been written and flushed.
+pub struct Finish<W> {
+    w: Option<FlateWriter<W>>,
+    first: bool,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "compressed stream truncated")
+}
+
+fn unfinished() -> io::Error {
+    io::Error::new(io::ErrorKind::WriteZero,
+                   "stream ended before compressed stream was finished")
+}
+
+// Attempts to parse a gzip header from the front of `data`, returning its
+// length if it's all there.
+fn parse_gzip_header(data: &[u8]) -> io::Result<Option<usize>> {
+    if data.len() < 10 {
+        return Ok(None)
+    }
+    if data[0] != 0x1f || data[1] != 0x8b {
+        return Err(invalid("invalid gzip header"))
+    }
+    if data[2] != 8 {
+        return Err(invalid("unsupported gzip compression method"))
+    }
+    let flags = data[3];
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        if data.len() < pos + 2 {
+            return Ok(None)
+        }
+        pos += 2 + (data[pos] as usize | (data[pos + 1] as usize) << 8);
+    }
+    for &flag in [FNAME, FCOMMENT].iter() {
+        if flags & flag != 0 {
+            match data.get(pos..).and_then(|d| d.iter().position(|b| *b == 0)) {
+                Some(i) => pos += i + 1,
+                None => return Ok(None),
+            }
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+    if data.len() < pos {
+        Ok(None)
+    } else {
+        Ok(Some(pos))
+    }
+}
+
+impl<R> FlateReader<R> {
+    /// Creates a new reader which decompresses a gzip stream read from
+    /// `inner`.
+    pub fn gzip(inner: R) -> FlateReader<R> {
+        FlateReader::new(inner, Format::Gzip)
+    }
+
+    /// Creates a new reader which decompresses a zlib stream read from
+    /// `inner`.
+    ///
+    /// This is the format used by the "deflate" content encoding of HTTP.
+    pub fn zlib(inner: R) -> FlateReader<R> {
+        FlateReader::new(inner, Format::Zlib)
+    }
+
+    /// Creates a new reader which decompresses a raw deflate stream, without
+    /// any header or trailer, read from `inner`.
+    pub fn deflate(inner: R) -> FlateReader<R> {
+        FlateReader::new(inner, Format::Deflate)
+    }
+
+    fn new(inner: R, format: Format) -> FlateReader<R> {
+        FlateReader {
+            inner: inner,
+            format: format,
+            state: if format == Format::Gzip {
+                ReadState::Header
+            } else {
+                ReadState::Body
+            },
+            decompress: Decompress::new(format == Format::Zlib),
+            crc: Crc::new(),
+            buf: vec![0; BUF_SIZE].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+            eof: false,
+            pending: false,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Gets a shared reference to the underlying I/O object.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying I/O object.
+    ///
+    /// Note that care must be taken to not tamper with the I/O stream itself
+    /// as the compressed data may otherwise get corrupted.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consumes this reader, returning the underlying I/O object.
+    ///
+    /// Note that any data that has been read from the underlying object but
+    /// not yet decompressed is discarded.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> FlateReader<R> {
+    // Refills our buffer of compressed data if it's empty, returning whether
+    // there's any data available.
+    fn fill(&mut self) -> io::Result<bool> {
+        if self.pos == self.cap && !self.eof {
+            let n = try!(self.inner.read(&mut self.buf));
+            self.pos = 0;
+            self.cap = n;
+            self.eof = n == 0;
+        }
+        Ok(self.pos < self.cap)
+    }
+
+    // Moves up to `amt` bytes of our buffer into `self.partial`.
+    fn take_partial(&mut self, amt: usize) -> io::Result<()> {
+        if !try!(self.fill()) {
+            return Err(truncated())
+        }
+        let n = cmp::min(amt, self.cap - self.pos);
+        self.partial.extend_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(())
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        loop {
+            match try!(parse_gzip_header(&self.partial)) {
+                Some(len) => {
+                    // We may have taken too much, so hand back what's left
+                    // over. All of it came out of our buffer, which is
+                    // otherwise empty at this point.
+                    let extra = self.partial.len() - len;
+                    self.buf[..extra].copy_from_slice(&self.partial[len..]);
+                    self.pos = 0;
+                    self.cap = extra;
+                    self.partial.truncate(0);
+                    self.state = ReadState::Body;
+                    return Ok(())
+                }
+                None => try!(self.take_partial(BUF_SIZE)),
+            }
+        }
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        while self.partial.len() < 8 {
+            let needed = 8 - self.partial.len();
+            try!(self.take_partial(needed));
+        }
+        let crc = self.partial[..4].iter().rev()
+                      .fold(0, |sum, b| (sum << 8) | *b as u32);
+        let len = self.partial[4..].iter().rev()
+                      .fold(0, |sum, b| (sum << 8) | *b as u32);
+        if crc != self.crc.sum() || len != self.crc.amount() {
+            return Err(invalid("corrupt gzip stream does not have a \
+                                matching checksum"))
+        }
+        self.partial.truncate(0);
+        self.state = ReadState::Done;
+        Ok(())
+    }
+
+    fn read_body(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let before_in = self.decompress.total_in();
+            let before_out = self.decompress.total_out();
+            let flush = if self.eof {
+                FlushDecompress::Finish
+            } else {
+                FlushDecompress::None
+            };
+            let status = try!(self.decompress
+                                  .decompress(&self.buf[self.pos..self.cap],
+                                              buf, flush)
+                                  .map_err(|e| invalid(&e.to_string())));
+            let consumed = (self.decompress.total_in() - before_in) as usize;
+            let produced = (self.decompress.total_out() - before_out) as usize;
+            self.pos += consumed;
+            self.crc.update(&buf[..produced]);
+            self.pending = produced == buf.len();
+
+            if status == Status::StreamEnd {
+                self.state = if self.format == Format::Gzip {
+                    ReadState::Trailer
+                } else {
+                    ReadState::Done
+                };
+                return Ok(produced)
+            }
+            if produced > 0 || buf.len() == 0 {
+                return Ok(produced)
+            }
+
+            // Only once the decompressor has nothing left to give us do we
+            // go back to the underlying object, so if it returns `WouldBlock`
+            // we're guaranteed to have nothing buffered.
+            if self.pos == self.cap && !try!(self.fill()) {
+                return Err(truncated())
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for FlateReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.state {
+                ReadState::Header => try!(self.read_header()),
+                ReadState::Body => {
+                    let n = try!(self.read_body(buf));
+                    if n > 0 || buf.len() == 0 || self.state == ReadState::Body {
+                        return Ok(n)
+                    }
+                }
+                ReadState::Trailer => try!(self.read_trailer()),
+                ReadState::Done => return Ok(0),
+            }
+        }
+    }
+}
+
+impl<R> Stream for FlateReader<R>
+    where R: Stream<Item=Ready, Error=io::Error>,
+{
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        if self.can_read_buffered() {
+            Poll::Ok(Some(Ready::Read))
+        } else {
+            self.inner.poll(task)
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        if self.can_read_buffered() {
+            task.notify()
+        } else {
+            self.inner.schedule(task)
+        }
+    }
+}
+
+impl<R> FlateReader<R> {
+    // Returns whether a read may make progress without the underlying object
+    // becoming readable again.
+    fn can_read_buffered(&self) -> bool {
+        self.pos < self.cap || self.pending || self.eof ||
+            self.state == ReadState::Done
+    }
+}
+
+impl<W> FlateWriter<W> {
+    /// Creates a new writer which writes a gzip stream of all data written to
+    /// it to `inner`.
+    pub fn gzip(inner: W, level: Compression) -> FlateWriter<W> {
+        FlateWriter::new(inner, level, Format::Gzip)
+    }
+
+    /// Creates a new writer which writes a zlib stream of all data written to
+    /// it to `inner`.
+    ///
+    /// This is the format used by the "deflate" content encoding of HTTP.
+    pub fn zlib(inner: W, level: Compression) -> FlateWriter<W> {
+        FlateWriter::new(inner, level, Format::Zlib)
+    }
+
+    /// Creates a new writer which writes a raw deflate stream, without any
+    /// header or trailer, of all data written to it to `inner`.
+    pub fn deflate(inner: W, level: Compression) -> FlateWriter<W> {
+        FlateWriter::new(inner, level, Format::Deflate)
+    }
+
+    fn new(inner: W, level: Compression, format: Format) -> FlateWriter<W> {
+        FlateWriter {
+            inner: inner,
+            format: format,
+            compress: Compress::new(level, format == Format::Zlib),
+            crc: Crc::new(),
+            buf: Vec::with_capacity(BUF_SIZE),
+            header_done: format != Format::Gzip,
+            finished: false,
+        }
+    }
+
+    /// Gets a shared reference to the underlying I/O object.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying I/O object.
+    ///
+    /// Note that care must be taken to not tamper with the I/O stream itself
+    /// as the compressed data may otherwise get corrupted.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Creates a future which will finish the compressed stream, writing all
+    /// remaining data to the underlying object and flushing it.
+    ///
+    /// The returned future resolves to the underlying I/O object. No more data
+    /// can be written once this has been called.
+    pub fn finish(self) -> Finish<W> {
+        Finish {
+            w: Some(self),
+            first: true,
+        }
+    }
+}
+
+impl<W: Write> FlateWriter<W> {
+    // Writes out all compressed data that's been buffered.
+    fn dump(&mut self) -> io::Result<()> {
+        while self.buf.len() > 0 {
+            let n = try!(self.inner.write(&self.buf));
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::WriteZero,
+                                          "failed to write compressed data"))
+            }
+            self.buf.drain(..n);
+        }
+        Ok(())
+    }
+
+    // Runs the compressor, returning how many bytes of `data` were consumed
+    // and whether the compressed stream is now finished.
+    fn compress(&mut self, data: &[u8], flush: FlushCompress)
+                -> io::Result<(usize, bool)> {
+        if !self.header_done {
+            self.buf.extend_from_slice(&GZIP_HEADER);
+            self.header_done = true;
+        }
+        if self.buf.capacity() - self.buf.len() < BUF_SIZE {
+            self.buf.reserve(BUF_SIZE);
+        }
+        let before = self.compress.total_in();
+        let status = try!(self.compress.compress_vec(data, &mut self.buf, flush)
+                              .map_err(|e| invalid(&e.to_string())));
+        let consumed = (self.compress.total_in() - before) as usize;
+        self.crc.update(&data[..consumed]);
+        Ok((consumed, status == Status::StreamEnd))
+    }
+
+    // Finishes the compressed stream, returning `WouldBlock` if the
+    // underlying object can't take all of the data yet.
+    fn try_finish(&mut self) -> io::Result<()> {
+        while !self.finished {
+            try!(self.dump());
+            let (_, done) = try!(self.compress(&[], FlushCompress::Finish));
+            if done {
+                if self.format == Format::Gzip {
+                    let (crc, amt) = (self.crc.sum(), self.crc.amount());
+                    for i in 0..4 {
+                        self.buf.push((crc >> (i * 8)) as u8);
+                    }
+                    for i in 0..4 {
+                        self.buf.push((amt >> (i * 8)) as u8);
+                    }
+                }
+                self.finished = true;
+            }
+        }
+        try!(self.dump());
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for FlateWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        assert!(!self.finished, "write to a finished FlateWriter");
+
+        // Make sure that nothing is consumed if the underlying object isn't
+        // ready for more data.
+        try!(self.dump());
+        if data.len() == 0 {
+            return Ok(0)
+        }
+        let (n, _) = try!(self.compress(data, FlushCompress::None));
+        if n == 0 {
+            // There's always room in our buffer for the compressor, so this
+            // shouldn't happen, but retrying wouldn't get us anywhere.
+            return Err(io::Error::new(io::ErrorKind::Other,
+                                      "compressor made no progress"))
+        }
+        // Opportunistically write out what we've got. The data has already
+        // been consumed, so if the underlying object isn't ready we'll try
+        // again on the next write or flush, but any other error is returned.
+        match self.dump() {
+            Ok(()) => Ok(n),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(n),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.finished {
+            try!(self.dump());
+            try!(self.compress(&[], FlushCompress::Sync));
+        }
+        try!(self.dump());
+        self.inner.flush()
+    }
+}
+
+impl<W> Stream for FlateWriter<W>
+    where W: Stream<Item=Ready, Error=io::Error>,
+{
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        self.inner.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.inner.schedule(task)
+    }
+}
+
+impl<W> Future for Finish<W>
+    where W: Write + Stream<Item=Ready, Error=io::Error>,
+{
+    type Item = W;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<W, io::Error> {
+        if self.first {
+            self.first = false;
+        } else {
+            match try_poll!(self.w.as_mut().unwrap().poll(task)) {
+                Ok(Some(ref r)) if r.is_write() => {}
+                Ok(Some(_)) => return Poll::NotReady,
+                Ok(None) => return Poll::Err(unfinished()),
+                Err(e) => return Poll::Err(e),
+            }
+        }
+        match self.w.as_mut().unwrap().try_finish() {
+            Ok(()) => Poll::Ok(self.w.take().unwrap().inner),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Poll::NotReady
+            }
+            Err(e) => Poll::Err(e),
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        match self.w {
+            Some(ref mut w) => w.schedule(task),
+            None => task.notify(),
+        }
+    }
+}
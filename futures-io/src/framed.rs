@@ -0,0 +1,343 @@
+use std::io;
+
+use futures::{Future, Poll, Task};
+use futures::stream::{Stream, Fuse};
+
+use {ReadTask, WriteTask};
+
+/// Decoding of frames from a buffer of bytes read from an I/O object.
+///
+/// Implementations of this trait are used by `Framed` to turn a stream of
+/// bytes into a stream of frames.
+pub trait Decoder: Send + 'static {
+    /// The type of frames decoded.
+    type Item: Send + 'static;
+
+    /// The type of errors that can happen when decoding, which also includes
+    /// errors from the underlying I/O object.
+    type Error: From<io::Error> + Send + 'static;
+
+    /// Attempts to decode a frame from the front of `buf`.
+    ///
+    /// If a full frame is available the decoder should remove the bytes of
+    /// that frame from `buf` and return it. Otherwise `Ok(None)` should be
+    /// returned, in which case more bytes will be read and this method will be
+    /// called again.
+    fn decode(&mut self, buf: &mut Vec<u8>)
+              -> Result<Option<Self::Item>, Self::Error>;
+
+    /// Attempts to decode a frame after the underlying I/O object has hit EOF.
+    ///
+    /// This is called repeatedly until it returns `Ok(None)`, after which the
+    /// stream of frames is finished. The default implementation defers to
+    /// `decode`, returning an error if bytes are left over at the end of the
+    /// stream.
+    fn decode_eof(&mut self, buf: &mut Vec<u8>)
+                  -> Result<Option<Self::Item>, Self::Error> {
+        match try!(self.decode(buf)) {
+            Some(frame) => Ok(Some(frame)),
+            None if buf.len() == 0 => Ok(None),
+            None => {
+                Err(io::Error::new(io::ErrorKind::Other,
+                                   "bytes remaining on stream").into())
+            }
+        }
+    }
+}
+
+/// Encoding of frames into a buffer of bytes to get written to an I/O object.
+///
+/// Implementations of this trait are used by `Framed` to write frames.
+pub trait Encoder: Send + 'static {
+    /// The type of frames encoded.
+    type Item: Send + 'static;
+
+    /// The type of errors that can happen when encoding, which also includes
+    /// errors from the underlying I/O object.
+    type Error: From<io::Error> + Send + 'static;
+
+    /// Encodes `item` by appending its bytes to `buf`.
+    fn encode(&mut self, item: Self::Item, buf: &mut Vec<u8>)
+              -> Result<(), Self::Error>;
+}
+
+/// An I/O object paired with a codec to read and write frames rather than
+/// bytes.
+///
+/// If the codec implements `Decoder` and the I/O object implements `ReadTask`,
+/// then a `Framed` is a `Stream` of decoded frames. If the codec implements
+/// `Encoder` and the I/O object implements `WriteTask` then frames can be
+/// written through `start_send` and `poll_complete`, or a whole stream of them
+/// can be written through `send_all`.
+///
+/// Both reads and writes are buffered internally with unbounded buffers.
+pub struct Framed<T, C> {
+    io: T,
+    codec: C,
+
+    rd: Vec<u8>,
+    read_ready: bool,
+    need_decode: bool,
+    eof: bool,
+
+    wr: Vec<u8>,
+    write_ready: bool,
+}
+
+/// A future which writes all frames of a stream to a `Framed`.
+///
+/// Created by the `Framed::send_all` method, resolves to the `Framed` once all
+/// frames have been written and flushed.
+pub struct SendAll<T, C, S> {
+    framed: Option<Framed<T, C>>,
+    items: Fuse<S>,
+}
+
+const READ_SIZE: usize = 2048;
+
+impl<T, C> Framed<T, C> {
+    /// Creates a new `Framed` reading and writing frames with `codec` on the
+    /// I/O object `io`.
+    pub fn new(io: T, codec: C) -> Framed<T, C> {
+        Framed {
+            io: io,
+            codec: codec,
+            rd: Vec::with_capacity(READ_SIZE),
+            read_ready: true,
+            need_decode: false,
+            eof: false,
+            wr: Vec::with_capacity(READ_SIZE),
+            write_ready: true,
+        }
+    }
+
+    /// Gets a shared reference to the underlying I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Gets a mutable reference to the underlying I/O object.
+    ///
+    /// Note that care must be taken to not tamper with the I/O stream itself
+    /// as frames may otherwise get corrupted.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Gets a shared reference to the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Gets a mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Consumes this `Framed`, returning the underlying I/O object.
+    ///
+    /// Note that any buffered data, read or not yet written, is discarded.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T, C> Stream for Framed<T, C>
+    where T: ReadTask,
+          C: Decoder,
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<C::Item>, C::Error> {
+        loop {
+            if self.need_decode {
+                let frame = if self.eof {
+                    self.codec.decode_eof(&mut self.rd)
+                } else {
+                    self.codec.decode(&mut self.rd)
+                };
+                match frame {
+                    Ok(Some(frame)) => return Poll::Ok(Some(frame)),
+                    Ok(None) => self.need_decode = false,
+                    Err(e) => return Poll::Err(e),
+                }
+            }
+
+            if self.eof {
+                return Poll::Ok(None)
+            }
+
+            if !self.read_ready {
+                match try_poll!(self.io.poll(task)) {
+                    Ok(Some(ref r)) if r.is_read() => self.read_ready = true,
+                    Ok(Some(_)) => return Poll::NotReady,
+                    Ok(None) => {
+                        self.eof = true;
+                        self.need_decode = true;
+                        continue
+                    }
+                    Err(e) => return Poll::Err(e.into()),
+                }
+            }
+
+            let len = self.rd.len();
+            self.rd.resize(len + READ_SIZE, 0);
+            let res = self.io.read(task, &mut self.rd[len..]);
+            match res {
+                Ok(n) => {
+                    trace!("framed read {} bytes", n);
+                    self.rd.truncate(len + n);
+                    self.eof = n == 0;
+                    self.need_decode = true;
+                }
+                Err(e) => {
+                    self.rd.truncate(len);
+                    if e.kind() == io::ErrorKind::WouldBlock {
+                        self.read_ready = false;
+                        return Poll::NotReady
+                    }
+                    return Poll::Err(e.into())
+                }
+            }
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        if self.need_decode || self.eof || self.read_ready {
+            task.notify()
+        } else {
+            self.io.schedule(task)
+        }
+    }
+}
+
+impl<T, C> Framed<T, C>
+    where T: WriteTask,
+          C: Encoder,
+{
+    /// Encodes `item` into the internal write buffer.
+    ///
+    /// The frame isn't written to the underlying I/O object until
+    /// `poll_complete` is called.
+    pub fn start_send(&mut self, item: C::Item) -> Result<(), C::Error> {
+        self.codec.encode(item, &mut self.wr)
+    }
+
+    /// Attempts to write all buffered frames to the underlying I/O object and
+    /// then flush it.
+    ///
+    /// Returns `Poll::NotReady` if the I/O object isn't ready to accept all of
+    /// the data yet.
+    pub fn poll_complete(&mut self, task: &mut Task) -> Poll<(), C::Error> {
+        while self.wr.len() > 0 {
+            if !self.write_ready {
+                match try_poll!(self.io.poll(task)) {
+                    Ok(Some(ref r)) if r.is_write() => self.write_ready = true,
+                    Ok(Some(_)) => return Poll::NotReady,
+                    Ok(None) => return Poll::Err(zero_write().into()),
+                    Err(e) => return Poll::Err(e.into()),
+                }
+            }
+            match self.io.write(task, &self.wr) {
+                Ok(0) => return Poll::Err(zero_write().into()),
+                Ok(n) => {
+                    trace!("framed wrote {} bytes", n);
+                    self.wr.drain(..n);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.write_ready = false;
+                    return Poll::NotReady
+                }
+                Err(e) => return Poll::Err(e.into()),
+            }
+        }
+        match self.io.flush(task) {
+            Ok(()) => Poll::Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.write_ready = false;
+                Poll::NotReady
+            }
+            Err(e) => Poll::Err(e.into()),
+        }
+    }
+
+    /// Creates a future which will encode and write all frames of `items`.
+    ///
+    /// The returned future resolves back to this `Framed` once the stream
+    /// `items` has finished and everything has been written and flushed. Any
+    /// error, either from the stream or from writing, will be yielded by the
+    /// future.
+    pub fn send_all<S>(self, items: S) -> SendAll<T, C, S>
+        where S: Stream<Item=C::Item>,
+              S::Error: From<C::Error>,
+    {
+        SendAll {
+            framed: Some(self),
+            items: items.fuse(),
+        }
+    }
+
+    fn schedule_write(&mut self, task: &mut Task) {
+        if self.write_ready {
+            task.notify()
+        } else {
+            self.io.schedule(task)
+        }
+    }
+}
+
+fn zero_write() -> io::Error {
+    io::Error::new(io::ErrorKind::WriteZero, "failed to write frame")
+}
+
+impl<T, C, S> Future for SendAll<T, C, S>
+    where T: WriteTask,
+          C: Encoder,
+          S: Stream<Item=C::Item>,
+          S::Error: From<C::Error>,
+{
+    type Item = Framed<T, C>;
+    type Error = S::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Framed<T, C>, S::Error> {
+        {
+            let framed = self.framed.as_mut().expect("cannot poll SendAll twice");
+            loop {
+                match self.items.poll(task) {
+                    Poll::Ok(Some(item)) => {
+                        if let Err(e) = framed.start_send(item) {
+                            return Poll::Err(e.into())
+                        }
+                    }
+                    Poll::Ok(None) |
+                    Poll::NotReady => break,
+                    Poll::Err(e) => return Poll::Err(e),
+                }
+            }
+
+            match framed.poll_complete(task) {
+                Poll::Ok(()) => {}
+                Poll::NotReady => return Poll::NotReady,
+                Poll::Err(e) => return Poll::Err(e.into()),
+            }
+            if !self.items.is_done() {
+                return Poll::NotReady
+            }
+        }
+        Poll::Ok(self.framed.take().unwrap())
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        let framed = match self.framed {
+            Some(ref mut framed) => framed,
+            None => return task.notify(),
+        };
+        if framed.wr.len() > 0 {
+            framed.schedule_write(task);
+        }
+        if !self.items.is_done() {
+            self.items.schedule(task);
+        }
+    }
+}
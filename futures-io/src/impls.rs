@@ -17,6 +17,12 @@ impl<R: ?Sized> ReadTask for R
                    buf: &mut Vec<u8>) -> io::Result<usize> {
         io::Read::read_to_end(self, buf)
     }
+
+    fn read_vectored(&mut self,
+                     _task: &mut Task,
+                     bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        io::Read::read_vectored(self, bufs)
+    }
 }
 
 impl<W: ?Sized> WriteTask for W
@@ -26,6 +32,12 @@ impl<W: ?Sized> WriteTask for W
         io::Write::write(self, buf)
     }
 
+    fn write_vectored(&mut self,
+                      _task: &mut Task,
+                      bufs: &[io::IoSlice]) -> io::Result<usize> {
+        io::Write::write_vectored(self, bufs)
+    }
+
     fn flush(&mut self, _task: &mut Task) -> io::Result<()> {
         io::Write::flush(self)
     }
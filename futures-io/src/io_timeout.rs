@@ -0,0 +1,194 @@
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use futures::{Future, Poll, Task};
+use futures::stream::Stream;
+
+use {Clock, Ready, ShutdownWrite};
+
+/// An I/O object which fails reads and writes with `TimedOut` once an
+/// underlying object stops making progress.
+///
+/// Two limits can be configured. The idle timeout is the longest time allowed
+/// between two successful reads or writes, and the deadline is a point in
+/// time after which no more I/O is allowed at all. Once either has passed,
+/// any read or write which would otherwise return `WouldBlock` instead
+/// returns an error of kind `io::ErrorKind::TimedOut`.
+///
+/// This object also acts as a stream of readiness, and readiness is reported
+/// when a limit passes so that futures such as `ReadExact` or `Copy` which
+/// are waiting on it get a chance to see the error.
+pub struct IoTimeout<T> {
+    inner: T,
+    clock: Box<Clock>,
+    idle: Option<Duration>,
+    deadline: Option<Instant>,
+    last: Instant,
+    sleep: Option<Box<Future<Item=(), Error=()>>>,
+}
+
+impl<T> IoTimeout<T> {
+    /// Creates a new object with no limits on `inner`, using `clock` to keep
+    /// track of time.
+    pub fn new<C>(inner: T, clock: C) -> IoTimeout<T>
+        where C: Clock,
+    {
+        let now = clock.now();
+        IoTimeout {
+            inner: inner,
+            clock: Box::new(clock),
+            idle: None,
+            deadline: None,
+            last: now,
+            sleep: None,
+        }
+    }
+
+    /// Sets the longest amount of time that may pass without any bytes being
+    /// read or written.
+    ///
+    /// The timer starts from the last successful read or write, or from the
+    /// creation of this object if there hasn't been one yet. A value of
+    /// `None` disables the idle timeout.
+    pub fn set_idle_timeout(&mut self, dur: Option<Duration>) {
+        self.idle = dur;
+        self.sleep = None;
+    }
+
+    /// Sets the point in time after which reads and writes will fail,
+    /// regardless of whether progress is being made.
+    ///
+    /// A value of `None` disables the deadline.
+    pub fn set_deadline(&mut self, at: Option<Instant>) {
+        self.deadline = at;
+        self.sleep = None;
+    }
+
+    /// Returns the idle timeout, if any.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle
+    }
+
+    /// Returns the deadline, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Gets a shared reference to the underlying I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying I/O object.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes this object, returning the underlying I/O object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    // Returns the point in time at which the next limit passes, if any.
+    fn expires(&self) -> Option<Instant> {
+        let idle = self.idle.map(|dur| self.last + dur);
+        match (idle, self.deadline) {
+            (Some(a), Some(b)) => Some(if a < b {a} else {b}),
+            (a, b) => a.or(b),
+        }
+    }
+
+    // Filters the result of an I/O operation, recording progress and turning
+    // `WouldBlock` into `TimedOut` once a limit has passed.
+    fn check(&mut self, res: io::Result<usize>) -> io::Result<usize> {
+        match res {
+            Ok(n) => {
+                if n > 0 {
+                    self.last = self.clock.now();
+                    self.sleep = None;
+                }
+                Ok(n)
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                let at = match self.expires() {
+                    Some(at) => at,
+                    None => return res,
+                };
+                let now = self.clock.now();
+                if at <= now {
+                    return Err(timed_out())
+                }
+                if self.sleep.is_none() {
+                    self.sleep = Some(self.clock.sleep(at - now));
+                }
+                res
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "I/O operation timed out")
+}
+
+impl<T: Read> Read for IoTimeout<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let res = self.inner.read(buf);
+        self.check(res)
+    }
+}
+
+impl<T: Write> Write for IoTimeout<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let res = self.inner.write(buf);
+        self.check(res)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.inner.flush() {
+            Ok(()) => Ok(()),
+            Err(e) => self.check(Err(e)).map(|_| ()),
+        }
+    }
+}
+
+impl<T> Stream for IoTimeout<T>
+    where T: Stream<Item=Ready, Error=io::Error>,
+{
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        // When the timer fires we say that we're ready in both directions so
+        // whoever's waiting on us will retry their operation and get the
+        // `TimedOut` error.
+        let fired = match self.sleep {
+            Some(ref mut sleep) => {
+                match sleep.poll(task) {
+                    Poll::NotReady => false,
+                    Poll::Ok(()) | Poll::Err(()) => true,
+                }
+            }
+            None => false,
+        };
+        if fired {
+            self.sleep = None;
+            return Poll::Ok(Some(Ready::ReadWrite))
+        }
+        self.inner.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        if let Some(ref mut sleep) = self.sleep {
+            sleep.schedule(task);
+        }
+        self.inner.schedule(task)
+    }
+}
+
+impl<T: ShutdownWrite> ShutdownWrite for IoTimeout<T> {
+    fn shutdown_write(&mut self) -> io::Result<()> {
+        self.inner.shutdown_write()
+    }
+}
@@ -38,6 +38,8 @@
 extern crate futures;
 #[macro_use]
 extern crate log;
+#[cfg(feature = "flate2")]
+extern crate flate2;
 
 use std::io;
 use std::ops::BitOr;
@@ -67,36 +69,67 @@ pub type IoStream<T> = Stream<Item=T, Error=io::Error>;
 
 mod impls;
 
+pub mod mock;
+#[cfg(feature = "flate2")]
+pub mod flate;
+
 mod buf_reader;
 mod buf_writer;
 mod chain;
+mod clock;
+mod codec;
 mod copy;
+mod copy_bidirectional;
+mod duplex;
 mod empty;
 mod flush;
+mod framed;
+mod io_timeout;
+mod lines;
+mod metered;
 mod read_exact;
+mod read_line;
 mod read_to_end;
+mod read_until;
 mod ready_tracker;
 mod repeat;
 mod sink;
+mod split;
 mod take;
 mod task;
+mod throttled;
 mod window;
 mod write_all;
+mod write_all_vectored;
 pub use buf_reader::BufReader;
 pub use buf_writer::BufWriter;
 pub use chain::{chain, Chain};
+pub use clock::Clock;
+pub use codec::{LinesCodec, LengthDelimitedCodec};
 pub use copy::{copy, Copy};
+pub use copy_bidirectional::copy_bidirectional;
+pub use duplex::{duplex, DuplexStream};
 pub use empty::{empty, Empty};
 pub use flush::{flush, Flush};
+pub use framed::{Decoder, Encoder, Framed, SendAll};
+pub use io_timeout::IoTimeout;
+pub use lines::{lines, Lines};
+pub use metered::{Meter, Metered};
 pub use read_exact::{read_exact, ReadExact};
+pub use read_line::{read_line, ReadLine};
 pub use read_to_end::{read_to_end, ReadToEnd};
+pub use read_until::{read_until, ReadUntil};
 pub use ready_tracker::ReadyTracker;
 pub use repeat::{repeat, Repeat};
 pub use sink::{sink, Sink};
+pub use split::{split, ReadHalf, ReuniteError, WriteHalf};
 pub use take::{take, Take};
+#[allow(deprecated)]
 pub use task::{TaskIo, TaskIoRead, TaskIoWrite};
+pub use throttled::Throttled;
 pub use window::Window;
 pub use write_all::{write_all, WriteAll};
+pub use write_all_vectored::{write_all_vectored, WriteAllVectored};
 
 /// Readiness notifications that a stream can deliver.
 ///
@@ -141,6 +174,25 @@ pub trait ReadTask: Stream<Item=Ready, Error=io::Error> {
     fn read_to_end(&mut self,
                    task: &mut Task,
                    buf: &mut Vec<u8>) -> io::Result<usize>;
+
+    /// Like `read`, except that it reads into a slice of buffers, optionally
+    /// using `task` as a source of storage to draw from.
+    ///
+    /// Data is copied to fill each buffer in order, with the final buffer
+    /// written to possibly being only partially filled. The default
+    /// implementation calls `read` with the first nonempty buffer provided.
+    ///
+    /// Otherwise behaves the same as [`Read::read_vectored`][stdreadv].
+    ///
+    /// [stdreadv]: https://doc.rust-lang.org/std/io/trait.Read.html#method.read_vectored
+    fn read_vectored(&mut self,
+                     task: &mut Task,
+                     bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        match bufs.iter_mut().find(|b| b.len() > 0) {
+            Some(buf) => self.read(task, buf),
+            None => self.read(task, &mut []),
+        }
+    }
 }
 
 /// A trait representing buffered streams that can be read within the context of
@@ -191,6 +243,25 @@ pub trait WriteTask: Stream<Item=Ready, Error=io::Error> {
     /// [stdwrite]: https://doc.rust-lang.org/std/io/trait.Write.html#tymethod.write
     fn write(&mut self, task: &mut Task, buf: &[u8]) -> io::Result<usize>;
 
+    /// Like `write`, except that it writes from a slice of buffers, optionally
+    /// using `task` as a source of storage to draw from.
+    ///
+    /// Data is copied from each buffer in order, with the final buffer read
+    /// from possibly being only partially consumed. The default implementation
+    /// calls `write` with the first nonempty buffer provided.
+    ///
+    /// Otherwise behaves the same as [`Write::write_vectored`][stdwritev].
+    ///
+    /// [stdwritev]: https://doc.rust-lang.org/std/io/trait.Write.html#method.write_vectored
+    fn write_vectored(&mut self,
+                      task: &mut Task,
+                      bufs: &[io::IoSlice]) -> io::Result<usize> {
+        match bufs.iter().find(|b| b.len() > 0) {
+            Some(buf) => self.write(task, buf),
+            None => self.write(task, &[]),
+        }
+    }
+
     /// Flushes any internal buffers of this object, optionally using a `task`
     /// as a source of storage to draw from.
     ///
@@ -200,6 +271,20 @@ pub trait WriteTask: Stream<Item=Ready, Error=io::Error> {
     fn flush(&mut self, task: &mut Task) -> io::Result<()>;
 }
 
+/// A trait for I/O objects whose write half can be shut down on its own.
+///
+/// Shutting down the write half of an object like a TCP stream signals EOF to
+/// the other end while still allowing data to be read. This is used by
+/// combinators such as `copy_bidirectional` to propagate a "half close" from
+/// one connection to another.
+pub trait ShutdownWrite {
+    /// Shuts down the write half of this object.
+    ///
+    /// Any further writes will fail, and the other end will read EOF once it
+    /// has read all data written previously.
+    fn shutdown_write(&mut self) -> io::Result<()>;
+}
+
 impl Ready {
     /// Returns whether this readiness notification indicates that an object is
     /// readable.
@@ -0,0 +1,86 @@
+use std::io;
+use std::mem;
+
+use futures::{Poll, Task};
+use futures::stream::Stream;
+
+use BufReadTask;
+use read_line::invalid_utf8;
+use read_until::read_until_internal;
+
+/// A stream over the lines of a buffered I/O object.
+///
+/// Created by the `lines` function.
+pub struct Lines<A> {
+    a: A,
+    buf: Vec<u8>,
+    read_ready: bool,
+}
+
+/// Creates a new stream which will yield each line of text read from the I/O
+/// object `A`.
+///
+/// Each line yielded will not have a newline byte (the 0xA byte) or CRLF
+/// (0xD, 0xA bytes) at the end. The final line of the stream is yielded even
+/// if it doesn't end in a newline, and the stream finishes once EOF is
+/// reached.
+///
+/// If a line is not valid UTF-8 then an error of kind `InvalidData` is
+/// yielded.
+pub fn lines<A>(a: A) -> Lines<A>
+    where A: BufReadTask,
+{
+    Lines {
+        a: a,
+        buf: Vec::new(),
+        read_ready: true,
+    }
+}
+
+impl<A> Lines<A> {
+    /// Consumes this stream, returning the underlying I/O object.
+    ///
+    /// Note that any partially read line is discarded.
+    pub fn into_inner(self) -> A {
+        self.a
+    }
+}
+
+impl<A> Stream for Lines<A>
+    where A: BufReadTask,
+{
+    type Item = String;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<String>, io::Error> {
+        match try_poll!(read_until_internal(&mut self.a, task, b'\n',
+                                            &mut self.buf,
+                                            &mut self.read_ready)) {
+            Ok(()) => {}
+            Err(e) => return Poll::Err(e),
+        }
+        if self.buf.len() == 0 {
+            return Poll::Ok(None)
+        }
+
+        let mut line = mem::replace(&mut self.buf, Vec::new());
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        match String::from_utf8(line) {
+            Ok(line) => Poll::Ok(Some(line)),
+            Err(_) => Poll::Err(invalid_utf8()),
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        if self.read_ready {
+            task.notify()
+        } else {
+            self.a.schedule(task)
+        }
+    }
+}
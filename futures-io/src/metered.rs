@@ -0,0 +1,145 @@
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::{Poll, Task};
+use futures::stream::Stream;
+
+use {Ready, ShutdownWrite};
+
+/// An I/O object which counts the bytes read from and written to an
+/// underlying object.
+///
+/// The counts are kept in a `Meter`, which can be shared between many objects
+/// (for example all connections of one user) and read from any thread.
+pub struct Metered<T> {
+    inner: T,
+    meter: Meter,
+}
+
+/// A shared handle to the byte counts of one or more `Metered` objects.
+///
+/// The counts are 64 bits on all platforms, so they don't wrap around on
+/// 32-bit platforms after 4GB.
+#[derive(Clone)]
+pub struct Meter {
+    inner: Arc<Counts>,
+}
+
+struct Counts {
+    read: AtomicU64,
+    written: AtomicU64,
+}
+
+impl<T> Metered<T> {
+    /// Creates a new object counting bytes transferred through `inner` with a
+    /// new `Meter`.
+    pub fn new(inner: T) -> Metered<T> {
+        Metered::with_meter(inner, Meter::new())
+    }
+
+    /// Creates a new object counting bytes transferred through `inner` in the
+    /// `meter` provided.
+    pub fn with_meter(inner: T, meter: Meter) -> Metered<T> {
+        Metered {
+            inner: inner,
+            meter: meter,
+        }
+    }
+
+    /// Returns the meter that this object is counting bytes in.
+    pub fn meter(&self) -> &Meter {
+        &self.meter
+    }
+
+    /// Gets a shared reference to the underlying I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying I/O object.
+    ///
+    /// Note that bytes transferred directly through the underlying object
+    /// aren't counted.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes this object, returning the underlying I/O object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl Meter {
+    /// Creates a new meter with both counts at zero.
+    pub fn new() -> Meter {
+        Meter {
+            inner: Arc::new(Counts {
+                read: AtomicU64::new(0),
+                written: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Returns the total number of bytes read so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.inner.read.load(Ordering::SeqCst)
+    }
+
+    /// Returns the total number of bytes written so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.inner.written.load(Ordering::SeqCst)
+    }
+
+    /// Resets both counts to zero, returning the number of bytes read and
+    /// written, respectively, before the reset.
+    ///
+    /// The two counts are reset one after the other, so bytes transferred
+    /// concurrently with this call may be counted in either period.
+    pub fn reset(&self) -> (u64, u64) {
+        (self.inner.read.swap(0, Ordering::SeqCst),
+         self.inner.written.swap(0, Ordering::SeqCst))
+    }
+}
+
+impl<T: Read> Read for Metered<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.inner.read(buf));
+        self.meter.inner.read.fetch_add(n as u64, Ordering::SeqCst);
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Metered<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = try!(self.inner.write(buf));
+        self.meter.inner.written.fetch_add(n as u64, Ordering::SeqCst);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<T> Stream for Metered<T>
+    where T: Stream<Item=Ready, Error=io::Error>,
+{
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        self.inner.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.inner.schedule(task)
+    }
+}
+
+impl<T: ShutdownWrite> ShutdownWrite for Metered<T> {
+    fn shutdown_write(&mut self) -> io::Result<()> {
+        self.inner.shutdown_write()
+    }
+}
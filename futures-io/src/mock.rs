@@ -0,0 +1,249 @@
+//! A scripted I/O object for testing protocol implementations.
+//!
+//! The `Mock` type in this module implements `Read`, `Write`, and
+//! `Stream<Item=Ready, Error=io::Error>` by following a script of actions that
+//! is configured up front through a `Builder`. This allows exercising code such
+//! as `read_exact`, `write_all`, or an entire protocol handshake without
+//! opening any sockets.
+//!
+//! The script is a sequence of reads (data handed out by `read`), writes (data
+//! that `write` is expected to be called with), injected errors, and "wait"
+//! points at which the object returns `WouldBlock` until its stream of
+//! readiness is polled again.
+
+use std::collections::VecDeque;
+use std::cmp;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::thread;
+
+use futures::{Poll, Task};
+use futures::stream::Stream;
+
+use Ready;
+
+/// A builder for `Mock` I/O objects.
+///
+/// Each method appends an action to the script, and the actions are performed
+/// in the order they were added.
+pub struct Builder {
+    actions: VecDeque<Action>,
+}
+
+/// A scripted I/O object, created through a `Builder`.
+///
+/// Reads are served from the data configured with `Builder::read` and writes
+/// are checked against the data configured with `Builder::write`, panicking if
+/// the bytes written don't match. Once the script has run out, reads return
+/// EOF and writes panic.
+///
+/// # Panics
+///
+/// A `Mock` will panic when dropped if not all of its script has been
+/// consumed, unless the thread is already panicking.
+///
+/// If a read or write is attempted while the script is expecting the other
+/// direction, the object is never ready again, so the code under test stalls
+/// rather than spinning forever.
+pub struct Mock {
+    actions: VecDeque<Action>,
+    // The direction of the last call which returned `WouldBlock` because the
+    // script was expecting the other direction.
+    blocked: Option<Ready>,
+}
+
+enum Action {
+    Read(Vec<u8>),
+    Write(Vec<u8>),
+    Wait,
+    ReadError(io::Error),
+    WriteError(io::Error),
+}
+
+impl Builder {
+    /// Creates a new builder with an empty script.
+    pub fn new() -> Builder {
+        Builder { actions: VecDeque::new() }
+    }
+
+    /// Appends a read of `data` to the script.
+    ///
+    /// The data may be handed out over multiple calls to `read` if the buffers
+    /// provided are smaller than `data`.
+    pub fn read(&mut self, data: &[u8]) -> &mut Builder {
+        self.actions.push_back(Action::Read(data.to_vec()));
+        self
+    }
+
+    /// Appends an expected write of `data` to the script.
+    ///
+    /// The data may be written over multiple calls to `write`, but each call
+    /// must write a prefix of the remaining expected data.
+    pub fn write(&mut self, data: &[u8]) -> &mut Builder {
+        self.actions.push_back(Action::Write(data.to_vec()));
+        self
+    }
+
+    /// Appends a point in the script at which both reads and writes will
+    /// return `WouldBlock`.
+    ///
+    /// The object becomes ready again the next time its stream of readiness is
+    /// polled.
+    pub fn wait(&mut self) -> &mut Builder {
+        self.actions.push_back(Action::Wait);
+        self
+    }
+
+    /// Appends an error to be returned from the next call to `read`.
+    pub fn read_error(&mut self, err: io::Error) -> &mut Builder {
+        self.actions.push_back(Action::ReadError(err));
+        self
+    }
+
+    /// Appends an error to be returned from the next call to `write`.
+    pub fn write_error(&mut self, err: io::Error) -> &mut Builder {
+        self.actions.push_back(Action::WriteError(err));
+        self
+    }
+
+    /// Creates a `Mock` I/O object following the script configured so far.
+    ///
+    /// The script of this builder is reset afterwards.
+    pub fn build(&mut self) -> Mock {
+        Mock {
+            actions: mem::replace(&mut self.actions, VecDeque::new()),
+            blocked: None,
+        }
+    }
+}
+
+fn would_block() -> io::Error {
+    io::Error::new(io::ErrorKind::WouldBlock, "mock would block")
+}
+
+impl Mock {
+    /// Returns whether the entire script of this object has been consumed.
+    pub fn is_done(&self) -> bool {
+        self.actions.iter().all(|a| {
+            match *a {
+                Action::Wait => true,
+                _ => false,
+            }
+        })
+    }
+}
+
+impl Mock {
+    // Returns whether the next action is for the other direction than the
+    // last call which was blocked, in which case no amount of waiting will
+    // let that call make progress.
+    fn mismatched(&self) -> bool {
+        let dir = match self.blocked {
+            Some(dir) => dir,
+            None => return false,
+        };
+        match self.actions.front() {
+            Some(&Action::Read(..)) |
+            Some(&Action::ReadError(..)) => !dir.is_read(),
+            Some(&Action::Write(..)) |
+            Some(&Action::WriteError(..)) => !dir.is_write(),
+            _ => false,
+        }
+    }
+}
+
+impl Read for Mock {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.blocked = None;
+        match self.actions.pop_front() {
+            Some(Action::Read(mut data)) => {
+                let n = cmp::min(buf.len(), data.len());
+                buf[..n].copy_from_slice(&data[..n]);
+                data.drain(..n);
+                if data.len() > 0 {
+                    self.actions.push_front(Action::Read(data));
+                }
+                Ok(n)
+            }
+            Some(Action::ReadError(e)) => Err(e),
+            Some(Action::Wait) => {
+                self.actions.push_front(Action::Wait);
+                Err(would_block())
+            }
+            Some(other) => {
+                self.actions.push_front(other);
+                self.blocked = Some(Ready::Read);
+                Err(would_block())
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+impl Write for Mock {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.blocked = None;
+        match self.actions.pop_front() {
+            Some(Action::Write(mut data)) => {
+                let n = cmp::min(buf.len(), data.len());
+                assert!(buf[..n] == data[..n],
+                        "mock expected a write of {:?} but got {:?}",
+                        &data[..n], &buf[..n]);
+                data.drain(..n);
+                if data.len() > 0 {
+                    self.actions.push_front(Action::Write(data));
+                }
+                Ok(n)
+            }
+            Some(Action::WriteError(e)) => Err(e),
+            Some(Action::Wait) => {
+                self.actions.push_front(Action::Wait);
+                Err(would_block())
+            }
+            Some(other) => {
+                self.actions.push_front(other);
+                self.blocked = Some(Ready::Write);
+                Err(would_block())
+            }
+            None => panic!("unexpected write of {:?} to mock", buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Stream for Mock {
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, _task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        // Getting polled for readiness is what unblocks a wait point
+        while let Some(&Action::Wait) = self.actions.front() {
+            self.actions.pop_front();
+        }
+        if self.mismatched() {
+            return Poll::NotReady
+        }
+        match self.actions.front() {
+            Some(&Action::Write(..)) |
+            Some(&Action::WriteError(..)) => Poll::Ok(Some(Ready::Write)),
+            _ => Poll::Ok(Some(Ready::Read)),
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        if !self.mismatched() {
+            task.notify()
+        }
+    }
+}
+
+impl Drop for Mock {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            assert!(self.is_done(), "mock dropped before its script finished");
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::io;
+use std::mem;
+use std::str;
+
+use futures::{Poll, Task, Future};
+
+use BufReadTask;
+use read_until::read_until_internal;
+
+/// A future which can be used to read a line of text from a buffered stream.
+///
+/// Created by the `read_line` function.
+pub struct ReadLine<A> {
+    state: State<A>,
+}
+
+enum State<A> {
+    Reading {
+        a: A,
+        buf: Vec<u8>,
+        start: usize,
+        read_ready: bool,
+    },
+    Empty,
+}
+
+/// Creates a future which will read all bytes from the I/O object `A` until a
+/// newline (the 0xA byte) is reached, appending them to the string provided.
+///
+/// The newline is included in the appended data, unless EOF was reached
+/// before it was found. The returned future will resolve to both the I/O
+/// stream as well as the string once the read operation is completed.
+///
+/// If the data read is not valid UTF-8 then an error of kind `InvalidData` is
+/// returned. In the case of an error the string and the object will be
+/// discarded, with the error yielded.
+pub fn read_line<A>(a: A, buf: String) -> ReadLine<A>
+    where A: BufReadTask,
+{
+    let buf = buf.into_bytes();
+    ReadLine {
+        state: State::Reading {
+            a: a,
+            start: buf.len(),
+            buf: buf,
+            read_ready: true,
+        },
+    }
+}
+
+pub fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData,
+                   "stream did not contain valid UTF-8")
+}
+
+impl<A> Future for ReadLine<A>
+    where A: BufReadTask,
+{
+    type Item = (A, String);
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<(A, String), io::Error> {
+        match self.state {
+            State::Reading { ref mut a, ref mut buf, start, ref mut read_ready } => {
+                if let Err(e) = try_poll!(read_until_internal(a, task, b'\n',
+                                                              buf, read_ready)) {
+                    return Poll::Err(e)
+                }
+                if str::from_utf8(&buf[start..]).is_err() {
+                    return Poll::Err(invalid_utf8())
+                }
+            }
+            State::Empty => panic!("poll a ReadLine after it's done"),
+        }
+
+        match mem::replace(&mut self.state, State::Empty) {
+            State::Reading { a, buf, .. } => {
+                // We validated the new bytes above, and the rest came from a
+                // `String` to begin with.
+                Poll::Ok((a, unsafe { String::from_utf8_unchecked(buf) }))
+            }
+            State::Empty => panic!(),
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        match self.state {
+            State::Reading { ref mut a, .. } => a.schedule(task),
+            State::Empty => task.notify(),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::io;
+use std::mem;
+
+use futures::{Poll, Task, Future};
+
+use BufReadTask;
+
+/// A future which can be used to read bytes from a buffered stream until a
+/// delimiter is found.
+///
+/// Created by the `read_until` function.
+pub struct ReadUntil<A> {
+    state: State<A>,
+}
+
+enum State<A> {
+    Reading {
+        a: A,
+        byte: u8,
+        buf: Vec<u8>,
+        read_ready: bool,
+    },
+    Empty,
+}
+
+/// Creates a future which will read all the bytes associated with the I/O
+/// object `A` into the buffer provided until the delimiter `byte` is reached.
+///
+/// The delimiter is included in the bytes appended to `buf`, unless EOF was
+/// reached before it was found. The returned future will resolve to both the
+/// I/O stream as well as the buffer once the read operation is completed.
+///
+/// In the case of an error the buffer and the object will be discarded, with
+/// the error yielded.
+pub fn read_until<A>(a: A, byte: u8, buf: Vec<u8>) -> ReadUntil<A>
+    where A: BufReadTask,
+{
+    ReadUntil {
+        state: State::Reading {
+            a: a,
+            byte: byte,
+            buf: buf,
+            read_ready: true,
+        },
+    }
+}
+
+/// Appends bytes from `a` to `buf` until `byte` or EOF is hit.
+///
+/// The `read_ready` flag tracks whether `a` may have data available, and is
+/// cleared whenever a read returns `WouldBlock`. Once cleared, `a` is polled
+/// for readiness before attempting to read again as its notifications may be
+/// "edge" based.
+pub fn read_until_internal<A>(a: &mut A,
+                              task: &mut Task,
+                              byte: u8,
+                              buf: &mut Vec<u8>,
+                              read_ready: &mut bool) -> Poll<(), io::Error>
+    where A: BufReadTask,
+{
+    if !*read_ready {
+        match try_poll!(a.poll(task)) {
+            Ok(Some(ref r)) if r.is_read() => *read_ready = true,
+            Ok(Some(_)) => return Poll::NotReady,
+            Ok(None) => return Poll::Ok(()),
+            Err(e) => return Poll::Err(e),
+        }
+    }
+
+    loop {
+        let (done, used) = {
+            let available = match a.fill_buf(task) {
+                Ok(buf) => buf,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    *read_ready = false;
+                    return Poll::NotReady
+                }
+                Err(e) => return Poll::Err(e),
+            };
+            match available.iter().position(|b| *b == byte) {
+                Some(i) => {
+                    buf.extend_from_slice(&available[..i + 1]);
+                    (true, i + 1)
+                }
+                None => {
+                    buf.extend_from_slice(available);
+                    (available.len() == 0, available.len())
+                }
+            }
+        };
+        a.consume(task, used);
+        if done {
+            return Poll::Ok(())
+        }
+    }
+}
+
+impl<A> Future for ReadUntil<A>
+    where A: BufReadTask,
+{
+    type Item = (A, Vec<u8>);
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<(A, Vec<u8>), io::Error> {
+        match self.state {
+            State::Reading { ref mut a, byte, ref mut buf, ref mut read_ready } => {
+                if let Err(e) = try_poll!(read_until_internal(a, task, byte,
+                                                              buf, read_ready)) {
+                    return Poll::Err(e)
+                }
+            }
+            State::Empty => panic!("poll a ReadUntil after it's done"),
+        }
+
+        match mem::replace(&mut self.state, State::Empty) {
+            State::Reading { a, buf, .. } => Poll::Ok((a, buf)),
+            State::Empty => panic!(),
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        match self.state {
+            State::Reading { ref mut a, .. } => a.schedule(task),
+            State::Empty => task.notify(),
+        }
+    }
+}
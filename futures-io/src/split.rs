@@ -0,0 +1,297 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use futures::{Poll, Task, TaskHandle};
+use futures::stream::Stream;
+
+use {Ready, ShutdownWrite};
+
+/// Splits an I/O object into its read and write halves.
+///
+/// Unlike `TaskIo`, the halves returned here aren't tied to any one task.
+/// Both halves are `Send` and can be moved into separate futures running on
+/// different tasks (or even different threads), for example to have one task
+/// reading from a connection while another task writes messages it receives
+/// from a channel.
+///
+/// The object is shared between the two halves with a lock, and readiness
+/// notifications from the object are routed to whichever half they're for.
+/// The original object can be recovered with `ReadHalf::reunite`.
+pub fn split<T>(t: T) -> (ReadHalf<T>, WriteHalf<T>)
+    where T: io::Read + io::Write + Stream<Item=Ready, Error=io::Error>,
+{
+    let inner = Arc::new(Mutex::new(Inner {
+        object: t,
+        ready: None,
+        reader: None,
+        writer: None,
+    }));
+    (ReadHalf { inner: inner.clone() }, WriteHalf { inner: inner })
+}
+
+/// The readable half of an I/O object returned from `split`.
+///
+/// This implements `io::Read` and is a stream of read readiness, so it can be
+/// used with all the same combinators as the original object.
+pub struct ReadHalf<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+/// The writable half of an I/O object returned from `split`.
+///
+/// This implements `io::Write` and is a stream of write readiness, so it can
+/// be used with all the same combinators as the original object.
+pub struct WriteHalf<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+/// Error indicating that a `ReadHalf<T>` and `WriteHalf<T>` were not from the
+/// same object when attempting to reunite them.
+///
+/// Both halves are given back in the error.
+pub struct ReuniteError<T>(pub ReadHalf<T>, pub WriteHalf<T>);
+
+struct Inner<T> {
+    object: T,
+    // Readiness which has been seen on the object but not yet consumed by
+    // the half it's for.
+    ready: Option<Ready>,
+    // Tasks currently blocked on each half.
+    reader: Option<TaskHandle>,
+    writer: Option<TaskHandle>,
+}
+
+impl<T> Inner<T>
+    where T: Stream<Item=Ready, Error=io::Error>,
+{
+    // Along with the result this returns the other half's task if it needs
+    // waking, which the caller must only notify once the lock is released:
+    // notifying may run that task inline, and it'll want the lock too.
+    fn poll(&mut self, task: &mut Task, dir: Ready)
+            -> (Poll<Option<Ready>, io::Error>, Option<TaskHandle>) {
+        // We're getting polled, so we're no longer blocked.
+        if dir.is_read() {
+            self.reader = None;
+        } else {
+            self.writer = None;
+        }
+
+        let mut ready = self.ready.take();
+        if !has(ready, dir) {
+            match self.object.poll(task) {
+                Poll::Ok(Some(r)) => ready = Some(ready.map_or(r, |c| c | r)),
+                Poll::Ok(None) => return (Poll::Ok(None), None),
+                Poll::NotReady => {}
+                Poll::Err(e) => return (Poll::Err(e), None),
+            }
+        }
+
+        let (ours, theirs) = match (ready, dir) {
+            (None, _) => (false, None),
+            (Some(Ready::ReadWrite), Ready::Read) => (true, Some(Ready::Write)),
+            (Some(Ready::ReadWrite), _) => (true, Some(Ready::Read)),
+            (Some(r), d) if r.is_read() == d.is_read() => (true, None),
+            (Some(r), _) => (false, Some(r)),
+        };
+        self.ready = theirs;
+
+        // If the other half is blocked then it needs to hear about this. It's
+        // also woken up if we consumed some readiness, as the object may have
+        // only been set up to notify us and the other half needs to register
+        // itself again.
+        let other = if theirs.is_some() || ours {
+            if dir.is_read() {
+                self.writer.take()
+            } else {
+                self.reader.take()
+            }
+        } else {
+            None
+        };
+
+        if ours {
+            (Poll::Ok(Some(dir)), other)
+        } else {
+            (Poll::NotReady, other)
+        }
+    }
+
+    // Returns whether readiness is already buffered for `dir`, in which case
+    // the caller should notify `task` after releasing the lock.
+    fn schedule(&mut self, task: &mut Task, dir: Ready) -> bool {
+        if has(self.ready, dir) {
+            return true
+        }
+        if dir.is_read() {
+            self.reader = Some(task.handle().clone());
+        } else {
+            self.writer = Some(task.handle().clone());
+        }
+        self.object.schedule(task);
+        false
+    }
+}
+
+fn has(ready: Option<Ready>, dir: Ready) -> bool {
+    match ready {
+        Some(r) => if dir.is_read() {r.is_read()} else {r.is_write()},
+        None => false,
+    }
+}
+
+fn notify(handle: Option<TaskHandle>) {
+    if let Some(handle) = handle {
+        handle.notify();
+    }
+}
+
+fn lock<T>(inner: &Mutex<Inner<T>>) -> MutexGuard<Inner<T>> {
+    match inner.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+impl<T> ReadHalf<T> {
+    /// Attempts to put the two halves of a split object back together,
+    /// returning the original object.
+    ///
+    /// This fails with a `ReuniteError` if `other` is not the other half of
+    /// the object that this half came from.
+    pub fn reunite(self, other: WriteHalf<T>) -> Result<T, ReuniteError<T>> {
+        if !Arc::ptr_eq(&self.inner, &other.inner) {
+            return Err(ReuniteError(self, other))
+        }
+        let inner = self.inner.clone();
+        drop(self);
+        drop(other);
+        let inner = match Arc::try_unwrap(inner) {
+            Ok(inner) => inner,
+            Err(_) => panic!("split halves shared more than twice"),
+        };
+        match inner.into_inner() {
+            Ok(inner) => Ok(inner.object),
+            Err(poisoned) => Ok(poisoned.into_inner().object),
+        }
+    }
+
+    /// Returns whether `other` is the other half of the object this half
+    /// came from.
+    pub fn is_pair_of(&self, other: &WriteHalf<T>) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl<T> WriteHalf<T> {
+    /// Returns whether `other` is the other half of the object this half
+    /// came from.
+    pub fn is_pair_of(&self, other: &ReadHalf<T>) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl<T: io::Read> io::Read for ReadHalf<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        lock(&self.inner).object.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        lock(&self.inner).object.read_vectored(bufs)
+    }
+}
+
+impl<T: io::Write> io::Write for WriteHalf<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        lock(&self.inner).object.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        lock(&self.inner).object.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        lock(&self.inner).object.flush()
+    }
+}
+
+impl<T> Stream for ReadHalf<T>
+    where T: Stream<Item=Ready, Error=io::Error>,
+{
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        let (res, other) = lock(&self.inner).poll(task, Ready::Read);
+        notify(other);
+        res
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        let ready = lock(&self.inner).schedule(task, Ready::Read);
+        if ready {
+            task.notify();
+        }
+    }
+}
+
+impl<T> Stream for WriteHalf<T>
+    where T: Stream<Item=Ready, Error=io::Error>,
+{
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        let (res, other) = lock(&self.inner).poll(task, Ready::Write);
+        notify(other);
+        res
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        let ready = lock(&self.inner).schedule(task, Ready::Write);
+        if ready {
+            task.notify();
+        }
+    }
+}
+
+impl<T: ShutdownWrite> ShutdownWrite for WriteHalf<T> {
+    fn shutdown_write(&mut self) -> io::Result<()> {
+        lock(&self.inner).object.shutdown_write()
+    }
+}
+
+// When one half goes away the other half may have been relying on it to pass
+// along its notifications, so wake it up to register itself with the object.
+impl<T> Drop for ReadHalf<T> {
+    fn drop(&mut self) {
+        let task = lock(&self.inner).writer.take();
+        notify(task);
+    }
+}
+
+impl<T> Drop for WriteHalf<T> {
+    fn drop(&mut self) {
+        let task = lock(&self.inner).reader.take();
+        notify(task);
+    }
+}
+
+impl<T> fmt::Debug for ReuniteError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ReuniteError").field(&"...").finish()
+    }
+}
+
+impl<T> fmt::Display for ReuniteError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl<T> Error for ReuniteError<T> {
+    fn description(&self) -> &str {
+        "tried to reunite halves that are not from the same object"
+    }
+}
@@ -1,10 +1,12 @@
+#![allow(deprecated)]
+
 use std::cell::RefCell;
 use std::io;
 
 use futures::{Future, Task, TaskData, Poll, store, Store};
 use futures::stream::Stream;
 
-use {WriteTask, ReadTask, Ready};
+use {WriteTask, ReadTask, Ready, ShutdownWrite};
 
 /// Abstraction that allows inserting an I/O object into task-local storage,
 /// returning a handle that can be split.
@@ -17,6 +19,12 @@ use {WriteTask, ReadTask, Ready};
 /// Note that it is important that the future returned from `TaskIo::new`, when
 /// polled, will pin the yielded `TaskIo<T>` object to that specific task. Any
 /// attempt to read or write the object on other tasks will result in a panic.
+/// To use the two halves of an object from different tasks, use `split`
+/// instead.
+///
+/// This type is deprecated in favor of `split`, whose halves aren't tied to a
+/// task.
+#[deprecated(since = "0.1.0", note = "use `split` instead")]
 pub struct TaskIo<T> {
     handle: TaskData<RefCell<Option<State<T>>>>,
 }
@@ -31,6 +39,7 @@ pub struct TaskIoNew<T> {
 ///
 /// This handle implements the `ReadTask` trait and can be used to split up an
 /// I/O object into two distinct halves.
+#[deprecated(since = "0.1.0", note = "use `split` instead")]
 pub struct TaskIoRead<T> {
     handle: TaskData<RefCell<Option<State<T>>>>,
 }
@@ -39,6 +48,7 @@ pub struct TaskIoRead<T> {
 ///
 /// This handle implements the `WriteTask` trait and can be used to split up an
 /// I/O object into two distinct halves.
+#[deprecated(since = "0.1.0", note = "use `split` instead")]
 pub struct TaskIoWrite<T> {
     handle: TaskData<RefCell<Option<State<T>>>>,
 }
@@ -214,6 +224,13 @@ impl<T> ReadTask for TaskIo<T>
         let mut io = TaskIoTake::new(task, &self.handle);
         io.state().object.read_to_end(buf)
     }
+
+    fn read_vectored(&mut self,
+                     task: &mut Task,
+                     bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        let mut io = TaskIoTake::new(task, &self.handle);
+        io.state().object.read_vectored(bufs)
+    }
 }
 
 impl<T> WriteTask for TaskIo<T>
@@ -224,6 +241,13 @@ impl<T> WriteTask for TaskIo<T>
         io.state().object.write(buf)
     }
 
+    fn write_vectored(&mut self,
+                      task: &mut Task,
+                      bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let mut io = TaskIoTake::new(task, &self.handle);
+        io.state().object.write_vectored(bufs)
+    }
+
     fn flush(&mut self, task: &mut Task) -> io::Result<()> {
         let mut io = TaskIoTake::new(task, &self.handle);
         io.state().object.flush()
@@ -259,6 +283,13 @@ impl<T> ReadTask for TaskIoRead<T>
         let mut io = TaskIoTake::new(task, &self.handle);
         io.state().object.read_to_end(buf)
     }
+
+    fn read_vectored(&mut self,
+                     task: &mut Task,
+                     bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        let mut io = TaskIoTake::new(task, &self.handle);
+        io.state().object.read_vectored(bufs)
+    }
 }
 
 impl<T> Stream for TaskIoWrite<T>
@@ -284,8 +315,28 @@ impl<T> WriteTask for TaskIoWrite<T>
         io.state().object.write(buf)
     }
 
+    fn write_vectored(&mut self,
+                      task: &mut Task,
+                      bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let mut io = TaskIoTake::new(task, &self.handle);
+        io.state().object.write_vectored(bufs)
+    }
+
     fn flush(&mut self, task: &mut Task) -> io::Result<()> {
         let mut io = TaskIoTake::new(task, &self.handle);
         io.state().object.flush()
     }
 }
+
+impl<T> TaskIoWrite<T>
+    where T: ShutdownWrite + Send + 'static,
+{
+    /// Shuts down the write half of the underlying I/O object.
+    ///
+    /// This is the same as `ShutdownWrite::shutdown_write`, except that it
+    /// must be called from within the task the object was inserted into.
+    pub fn shutdown_write(&mut self, task: &mut Task) -> io::Result<()> {
+        let mut io = TaskIoTake::new(task, &self.handle);
+        io.state().object.shutdown_write()
+    }
+}
@@ -0,0 +1,269 @@
+use std::cmp;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use futures::{Future, Poll, Task};
+use futures::stream::Stream;
+
+use {Clock, Ready, ShutdownWrite};
+
+/// An I/O object which limits the rate at which bytes are read from and
+/// written to an underlying object.
+///
+/// Both reads and writes are limited to a number of bytes per second with a
+/// token bucket. Once the tokens for a direction have run out, operations in
+/// that direction return `WouldBlock` and readiness for it is delivered once
+/// enough tokens have been refilled.
+pub struct Throttled<T> {
+    inner: T,
+    clock: Box<Clock>,
+    read: Bucket,
+    write: Bucket,
+}
+
+struct Bucket {
+    rate: u64,
+    capacity: u64,
+    tokens: u64,
+    last: Instant,
+    sleep: Option<Box<Future<Item=(), Error=()>>>,
+}
+
+impl<T> Throttled<T> {
+    /// Creates a new object which reads and writes at most `bytes_per_sec`
+    /// bytes per second each from `inner`.
+    ///
+    /// Up to one second's worth of bytes may be transferred in a burst.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `bytes_per_sec` is 0.
+    pub fn new<C>(inner: T, bytes_per_sec: u64, clock: C) -> Throttled<T>
+        where C: Clock,
+    {
+        assert!(bytes_per_sec > 0, "rate must be nonzero");
+        let now = clock.now();
+        Throttled {
+            inner: inner,
+            clock: Box::new(clock),
+            read: Bucket::new(bytes_per_sec, now),
+            write: Bucket::new(bytes_per_sec, now),
+        }
+    }
+
+    /// Sets the limit for reads, in bytes per second.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `bytes_per_sec` is 0.
+    pub fn set_read_rate(&mut self, bytes_per_sec: u64) {
+        assert!(bytes_per_sec > 0, "rate must be nonzero");
+        let now = self.clock.now();
+        self.read.set_rate(bytes_per_sec, now);
+    }
+
+    /// Sets the limit for writes, in bytes per second.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `bytes_per_sec` is 0.
+    pub fn set_write_rate(&mut self, bytes_per_sec: u64) {
+        assert!(bytes_per_sec > 0, "rate must be nonzero");
+        let now = self.clock.now();
+        self.write.set_rate(bytes_per_sec, now);
+    }
+
+    /// Gets a shared reference to the underlying I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying I/O object.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes this object, returning the underlying I/O object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+fn throttled() -> io::Error {
+    io::Error::new(io::ErrorKind::WouldBlock, "rate limit exceeded")
+}
+
+impl Bucket {
+    fn new(rate: u64, now: Instant) -> Bucket {
+        Bucket {
+            rate: rate,
+            capacity: rate,
+            tokens: rate,
+            last: now,
+            sleep: None,
+        }
+    }
+
+    fn set_rate(&mut self, rate: u64, now: Instant) {
+        self.refill(now);
+        self.rate = rate;
+        self.capacity = rate;
+        self.tokens = cmp::min(self.tokens, rate);
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now <= self.last {
+            return
+        }
+        let elapsed = now - self.last;
+        let nanos = elapsed.as_secs() * 1_000_000_000 +
+                    elapsed.subsec_nanos() as u64;
+        let new = nanos.saturating_mul(self.rate) / 1_000_000_000;
+        if self.tokens + new >= self.capacity {
+            self.tokens = self.capacity;
+            self.last = now;
+        } else {
+            // Only move forward by the time it took to earn the tokens we
+            // added, so fractional tokens aren't lost.
+            let earned = nanos_to_duration(new * 1_000_000_000 / self.rate);
+            self.tokens += new;
+            self.last = self.last + earned;
+        }
+    }
+
+    // Takes up to `want` tokens, returning how many were taken. If no tokens
+    // are available then a wake up is scheduled with `clock`.
+    fn take(&mut self, want: usize, clock: &Clock) -> usize {
+        if self.sleep.is_some() {
+            return 0
+        }
+        self.refill(clock.now());
+        if self.tokens == 0 {
+            let need = cmp::min(want as u64, self.capacity);
+            let nanos = (need * 1_000_000_000 + self.rate - 1) / self.rate;
+            self.sleep = Some(clock.sleep(nanos_to_duration(nanos)));
+            return 0
+        }
+        cmp::min(want as u64, self.tokens) as usize
+    }
+
+    // Removes the tokens for `amt` bytes which have been transferred.
+    fn consume(&mut self, amt: usize) {
+        self.tokens -= amt as u64;
+    }
+
+    // Polls the pending wake up, if any, returning whether it has fired.
+    fn poll_sleep(&mut self, task: &mut Task) -> bool {
+        let done = match self.sleep {
+            Some(ref mut sleep) => {
+                match sleep.poll(task) {
+                    Poll::NotReady => false,
+                    Poll::Ok(()) | Poll::Err(()) => true,
+                }
+            }
+            None => false,
+        };
+        if done {
+            self.sleep = None;
+        }
+        done
+    }
+}
+
+fn nanos_to_duration(nanos: u64) -> Duration {
+    Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+}
+
+impl<T: Read> Read for Throttled<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.len() == 0 {
+            return self.inner.read(buf)
+        }
+        let amt = self.read.take(buf.len(), &*self.clock);
+        if amt == 0 {
+            return Err(throttled())
+        }
+        let n = try!(self.inner.read(&mut buf[..amt]));
+        self.read.consume(n);
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Throttled<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() == 0 {
+            return self.inner.write(buf)
+        }
+        let amt = self.write.take(buf.len(), &*self.clock);
+        if amt == 0 {
+            return Err(throttled())
+        }
+        let n = try!(self.inner.write(&buf[..amt]));
+        self.write.consume(n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<T> Stream for Throttled<T>
+    where T: Stream<Item=Ready, Error=io::Error>,
+{
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        // Once a wake up fires we report readiness for that direction, even
+        // though the underlying object may not actually be ready, as the
+        // notification from the underlying object may have been swallowed
+        // while we were waiting.
+        let mut ready = None;
+        if self.read.poll_sleep(task) {
+            ready = Some(Ready::Read);
+        }
+        if self.write.poll_sleep(task) {
+            ready = Some(ready.map_or(Ready::Write, |r| r | Ready::Write));
+        }
+
+        let inner = match self.inner.poll(task) {
+            Poll::Ok(Some(r)) => {
+                // Drop the notifications for any direction that's still
+                // throttled, they'll be delivered once the wake up fires.
+                match (r.is_read() && self.read.sleep.is_none(),
+                       r.is_write() && self.write.sleep.is_none()) {
+                    (true, true) => Some(Ready::ReadWrite),
+                    (true, false) => Some(Ready::Read),
+                    (false, true) => Some(Ready::Write),
+                    (false, false) => None,
+                }
+            }
+            Poll::Ok(None) => return Poll::Ok(None),
+            Poll::NotReady => None,
+            Poll::Err(e) => return Poll::Err(e),
+        };
+
+        match (ready, inner) {
+            (Some(a), Some(b)) => Poll::Ok(Some(a | b)),
+            (Some(r), None) | (None, Some(r)) => Poll::Ok(Some(r)),
+            (None, None) => Poll::NotReady,
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        if let Some(ref mut sleep) = self.read.sleep {
+            sleep.schedule(task);
+        }
+        if let Some(ref mut sleep) = self.write.sleep {
+            sleep.schedule(task);
+        }
+        self.inner.schedule(task)
+    }
+}
+
+impl<T: ShutdownWrite> ShutdownWrite for Throttled<T> {
+    fn shutdown_write(&mut self) -> io::Result<()> {
+        self.inner.shutdown_write()
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::{self, IoSlice};
+use std::mem;
+
+use futures::{Poll, Task, Future};
+
+use WriteTask;
+
+/// A future used to write the entire contents of a list of buffers to a
+/// stream.
+///
+/// This is created by the `write_all_vectored` top-level method.
+pub struct WriteAllVectored<A, T> {
+    state: State<A, T>,
+}
+
+enum State<A, T> {
+    Writing {
+        a: A,
+        bufs: Vec<T>,
+        idx: usize,
+        pos: usize,
+        first: bool,
+    },
+    Empty,
+}
+
+// The maximum number of buffers handed to a single `write_vectored` call,
+// comfortably below the `IOV_MAX` of the platforms we support.
+const MAX_BUFS: usize = 64;
+
+/// Creates a future that will write the entire contents of each buffer in
+/// `bufs`, in order, to the stream `a` provided.
+///
+/// Writes are issued through `WriteTask::write_vectored` so I/O objects which
+/// support it can write many buffers (such as a header and a body) with one
+/// system call, without first copying them into one contiguous buffer.
+///
+/// The returned future will not return until all the data has been written,
+/// and the future will resolve to the stream as well as the buffers (for reuse
+/// if needed).
+///
+/// Any error which happens during writing will cause both the stream and the
+/// buffers to get destroyed.
+pub fn write_all_vectored<A, T>(a: A, bufs: Vec<T>) -> WriteAllVectored<A, T>
+    where A: WriteTask,
+          T: AsRef<[u8]> + Send + 'static,
+{
+    WriteAllVectored {
+        state: State::Writing {
+            a: a,
+            bufs: bufs,
+            idx: 0,
+            pos: 0,
+            first: true,
+        },
+    }
+}
+
+fn zero_write() -> io::Error {
+    io::Error::new(io::ErrorKind::WriteZero, "zero-length write")
+}
+
+impl<A, T> Future for WriteAllVectored<A, T>
+    where A: WriteTask,
+          T: AsRef<[u8]> + Send + 'static,
+{
+    type Item = (A, Vec<T>);
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<(A, Vec<T>), io::Error> {
+        match self.state {
+            State::Writing { ref mut a, ref bufs, ref mut idx, ref mut pos,
+                             ref mut first } => {
+                if !*first {
+                    match try_poll!(a.poll(task)) {
+                        Ok(Some(r)) if r.is_write() => {}
+                        Ok(_) => return Poll::NotReady,
+                        Err(e) => return Poll::Err(e),
+                    }
+                }
+                *first = false;
+
+                loop {
+                    // Skip over everything that's been written (as well as
+                    // any empty buffers) to find what's left.
+                    while *idx < bufs.len() && *pos == bufs[*idx].as_ref().len() {
+                        *idx += 1;
+                        *pos = 0;
+                    }
+                    if *idx == bufs.len() {
+                        break
+                    }
+
+                    let res = {
+                        let mut slices = Vec::with_capacity(MAX_BUFS);
+                        slices.push(IoSlice::new(&bufs[*idx].as_ref()[*pos..]));
+                        for buf in bufs[*idx + 1..].iter().take(MAX_BUFS - 1) {
+                            slices.push(IoSlice::new(buf.as_ref()));
+                        }
+                        a.write_vectored(task, &slices)
+                    };
+                    let mut n = match res {
+                        Ok(0) => return Poll::Err(zero_write()),
+                        Ok(n) => n,
+                        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            return Poll::NotReady
+                        }
+                        Err(e) => return Poll::Err(e),
+                    };
+                    while n > 0 {
+                        let remaining = bufs[*idx].as_ref().len() - *pos;
+                        if n < remaining {
+                            *pos += n;
+                            break
+                        }
+                        n -= remaining;
+                        *idx += 1;
+                        *pos = 0;
+                    }
+                }
+            }
+            State::Empty => panic!("poll a WriteAllVectored after it's done"),
+        }
+
+        match mem::replace(&mut self.state, State::Empty) {
+            State::Writing { a, bufs, .. } => Poll::Ok((a, bufs)),
+            State::Empty => panic!(),
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        match self.state {
+            State::Writing { ref mut a, .. } => a.schedule(task),
+            State::Empty => task.notify(),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+extern crate futures;
+extern crate futures_io;
+
+use std::net::Shutdown;
+use std::sync::mpsc::channel;
+
+use futures::Future;
+use futures::executor::Deterministic;
+use futures_io::{copy_bidirectional, duplex, read_exact, read_to_end, write_all};
+
+#[test]
+fn half_close() {
+    let (client, proxy_a) = duplex(4);
+    let (proxy_b, server) = duplex(4);
+    let exec = Deterministic::new();
+
+    // The server reads the request, responds, and then waits for the client's
+    // half close to make its way through the proxy.
+    let server = read_exact(server, [0; 10]).and_then(|(server, req)| {
+        assert_eq!(&req, b"ping, ping");
+        write_all(server, b"pong!")
+    }).and_then(|(server, _)| {
+        read_to_end(server, Vec::new())
+    });
+    exec.spawn(server.map(|rest| assert_eq!(rest, b""))
+                     .map_err(|e| panic!("{}", e)));
+
+    // The client only sees EOF once the server has gone away.
+    let (tx, rx) = channel();
+    let client = write_all(client, b"ping, ping").and_then(|(client, _)| {
+        client.shutdown(Shutdown::Write).unwrap();
+        read_to_end(client, Vec::new())
+    });
+    exec.spawn(client.map(move |res| tx.send(res).unwrap())
+                     .map_err(|e| panic!("{}", e)));
+
+    let amts = exec.run(copy_bidirectional(proxy_a, proxy_b)).unwrap().unwrap();
+    assert_eq!(amts, (10, 5));
+    exec.run_until_stalled();
+    assert_eq!(rx.try_recv().unwrap(), b"pong!");
+}
@@ -0,0 +1,52 @@
+extern crate futures;
+extern crate futures_io;
+
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+
+use futures::Future;
+use futures::executor::Deterministic;
+use futures_io::{duplex, read_exact, read_to_end, write_all};
+
+#[test]
+fn smoke() {
+    let (mut a, mut b) = duplex(16);
+    assert_eq!(a.write(b"hello").unwrap(), 5);
+    let mut buf = [0; 16];
+    assert_eq!(b.read(&mut buf).unwrap(), 5);
+    assert_eq!(&buf[..5], b"hello");
+    assert_eq!(b.read(&mut buf).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+
+    assert_eq!(b.write(b"world").unwrap(), 5);
+    assert_eq!(a.read(&mut buf).unwrap(), 5);
+    assert_eq!(&buf[..5], b"world");
+}
+
+#[test]
+fn back_pressure() {
+    let (a, b) = duplex(4);
+    let exec = Deterministic::new();
+    let data = (0..100).map(|i| i as u8).collect::<Vec<_>>();
+    let expected = data.clone();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    exec.spawn(write_all(a, data).map(|_| ()).map_err(|e| panic!("{}", e)));
+    exec.spawn(read_exact(b, vec![0; 100]).map(move |(_, buf)| {
+        tx.send(buf).unwrap();
+    }).map_err(|e| panic!("{}", e)));
+    exec.run_until_stalled();
+    assert_eq!(rx.try_recv().unwrap(), expected);
+}
+
+#[test]
+fn eof_and_broken_pipe() {
+    let (mut a, b) = duplex(16);
+    assert_eq!(a.write(b"abc").unwrap(), 3);
+    a.shutdown(Shutdown::Write).unwrap();
+    assert_eq!(a.write(b"d").unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+
+    let exec = Deterministic::new();
+    let res = exec.run(read_to_end(b, Vec::new()));
+    assert_eq!(res.unwrap().unwrap(), b"abc");
+    assert_eq!(a.write(b"d").unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+}
@@ -0,0 +1,148 @@
+#![cfg(feature = "flate2")]
+
+extern crate flate2;
+extern crate futures;
+extern crate futures_io;
+
+use std::io::{self, Read, Write};
+
+use futures::{Future, Poll, Task};
+use futures::stream::Stream;
+use futures::executor::Deterministic;
+use futures_io::{duplex, read_exact, read_to_end, write_all, Ready};
+use futures_io::flate::{Compression, FlateReader, FlateWriter};
+use futures_io::mock::Builder;
+
+fn data() -> Vec<u8> {
+    (0..64 * 1024).map(|i| (i % 251) as u8 ^ (i / 1000) as u8).collect()
+}
+
+#[test]
+fn gzip_roundtrip() {
+    // A small pipe means both ends will see plenty of `WouldBlock`s.
+    let (a, b) = duplex(7);
+    let exec = Deterministic::new();
+
+    let writer = FlateWriter::gzip(a, Compression::default());
+    let write = write_all(writer, data()).and_then(|(w, _)| w.finish());
+    exec.spawn(write.map(|_| ()).map_err(|e| panic!("{}", e)));
+
+    let read = read_to_end(FlateReader::gzip(b), Vec::new());
+    let res = exec.run(read).unwrap().unwrap();
+    assert!(res == data());
+}
+
+#[test]
+fn zlib_roundtrip() {
+    let (a, b) = duplex(1024);
+    let exec = Deterministic::new();
+
+    let writer = FlateWriter::zlib(a, Compression::fast());
+    let write = write_all(writer, data()).and_then(|(w, _)| w.finish());
+    exec.spawn(write.map(|_| ()).map_err(|e| panic!("{}", e)));
+
+    let read = read_to_end(FlateReader::zlib(b), Vec::new());
+    let res = exec.run(read).unwrap().unwrap();
+    assert!(res == data());
+}
+
+#[test]
+fn gzip_interop() {
+    // Decode something encoded by flate2, with a file name in the header, in
+    // pieces split up by wait points.
+    let mut e = flate2::GzBuilder::new()
+        .filename("foo.txt")
+        .write(Vec::new(), Compression::default());
+    e.write_all(&data()).unwrap();
+    let compressed = e.finish().unwrap();
+
+    let mut builder = Builder::new();
+    for chunk in compressed.chunks(5) {
+        builder.read(chunk).wait();
+    }
+    let exec = Deterministic::new();
+    let read = read_to_end(FlateReader::gzip(builder.build()), Vec::new());
+    assert!(exec.run(read).unwrap().unwrap() == data());
+
+    // And encode something that flate2 can decode.
+    let (a, b) = duplex(100);
+    let writer = FlateWriter::gzip(a, Compression::best());
+    let write = write_all(writer, data()).and_then(|(w, _)| w.finish());
+    exec.spawn(write.map(|_| ()).map_err(|e| panic!("{}", e)));
+    let compressed = exec.run(read_to_end(b, Vec::new())).unwrap().unwrap();
+
+    let mut d = flate2::read::GzDecoder::new(&compressed[..]);
+    let mut out = Vec::new();
+    d.read_to_end(&mut out).unwrap();
+    assert!(out == data());
+}
+
+#[test]
+fn gzip_corrupt() {
+    let mut e = flate2::write::GzEncoder::new(Vec::new(), Compression::default());
+    e.write_all(b"hello").unwrap();
+    let mut compressed = e.finish().unwrap();
+    let len = compressed.len();
+    compressed[len - 8] ^= 1;
+
+    let mock = Builder::new().read(&compressed).build();
+    let exec = Deterministic::new();
+    let read = read_to_end(FlateReader::gzip(mock), Vec::new());
+    assert!(exec.run(read).unwrap().is_err());
+}
+
+#[test]
+fn ready_with_buffered_output() {
+    let mut e = flate2::write::DeflateEncoder::new(Vec::new(),
+                                                   Compression::default());
+    e.write_all(&[0; 64 * 1024]).unwrap();
+    let compressed = e.finish().unwrap();
+
+    // All of the compressed data is read in one go, and then the underlying
+    // object is only ready for writing, so only the decompressor knows
+    // there's more to read.
+    let mock = Builder::new().read(&compressed).write(b"x").build();
+    let exec = Deterministic::new();
+    let read = read_exact(FlateReader::deflate(mock), vec![0; 64 * 1024 - 1]);
+    let read = read.and_then(|(r, _)| r.into_future().map_err(|e| e.0));
+    let (ready, r) = exec.run(read).unwrap().unwrap();
+    assert!(ready.unwrap().is_read());
+    r.into_inner().write_all(b"x").unwrap();
+}
+
+// An object which never accepts any data and whose readiness stream has
+// ended, like a socket which was closed.
+struct Closed;
+
+impl Write for Closed {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::WouldBlock, "closed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Stream for Closed {
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, _task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        Poll::Ok(None)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        task.notify()
+    }
+}
+
+#[test]
+fn finish_on_closed_stream() {
+    let exec = Deterministic::new();
+    let finish = FlateWriter::deflate(Closed, Compression::default()).finish();
+    match exec.run(finish).unwrap() {
+        Ok(_) => panic!("finished on a closed stream"),
+        Err(e) => assert_eq!(e.kind(), io::ErrorKind::WriteZero),
+    }
+}
@@ -0,0 +1,100 @@
+extern crate futures;
+extern crate futures_io;
+
+use std::io;
+
+use futures::Future;
+use futures::executor::Deterministic;
+use futures::stream::{self, Stream};
+use futures_io::{duplex, Framed, LinesCodec, LengthDelimitedCodec};
+use futures_io::mock::Builder;
+
+#[test]
+fn lines() {
+    let mock = Builder::new()
+        .read(b"hello\r\nwor")
+        .wait()
+        .read(b"ld\n\nlast")
+        .build();
+    let exec = Deterministic::new();
+    let lines = Framed::new(mock, LinesCodec::new()).collect();
+    let lines = exec.run(lines).unwrap().unwrap();
+    assert_eq!(lines, ["hello", "world", "", "last"]);
+}
+
+#[test]
+fn lines_invalid() {
+    let mock = Builder::new().read(b"\xff\n").build();
+    let exec = Deterministic::new();
+    let res = exec.run(Framed::new(mock, LinesCodec::new()).collect());
+    assert_eq!(res.unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let mock = Builder::new().read(b"abcdef").build();
+    let res = exec.run(Framed::new(mock, LinesCodec::with_max_length(4)).collect());
+    assert_eq!(res.unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn lines_max_length_crlf() {
+    // A line of exactly the maximum length, split just before its `\n`.
+    let mock = Builder::new()
+        .read(b"abcd\r")
+        .wait()
+        .read(b"\nefgh")
+        .build();
+    let exec = Deterministic::new();
+    let lines = Framed::new(mock, LinesCodec::with_max_length(4)).collect();
+    let lines = exec.run(lines).unwrap().unwrap();
+    assert_eq!(lines, ["abcd", "efgh"]);
+}
+
+#[test]
+fn send_lines() {
+    let mock = Builder::new().write(b"a\nbc\n").build();
+    let exec = Deterministic::new();
+    let items = vec![Ok("a".to_string()), Ok("bc".to_string())];
+    let items = stream::iter(items.into_iter()).map_err(|e: io::Error| e);
+    let res = exec.run(Framed::new(mock, LinesCodec::new()).send_all(items));
+    assert!(res.unwrap().unwrap().get_ref().is_done());
+}
+
+#[test]
+fn length_delimited_roundtrip() {
+    let (a, b) = duplex(3);
+    let exec = Deterministic::new();
+
+    let mut codec = LengthDelimitedCodec::new();
+    codec.length_field_length(2).little_endian();
+    let frames = vec![Ok(b"hello".to_vec()), Ok(Vec::new()), Ok(vec![7; 300])];
+    let frames = stream::iter(frames.into_iter()).map_err(|e: io::Error| e);
+    exec.spawn(Framed::new(a, codec).send_all(frames).map(|_| ())
+                                    .map_err(|e| panic!("{}", e)));
+
+    let mut codec = LengthDelimitedCodec::new();
+    codec.length_field_length(2).little_endian();
+    let res = exec.run(Framed::new(b, codec).collect()).unwrap().unwrap();
+    assert_eq!(res, [b"hello".to_vec(), Vec::new(), vec![7; 300]]);
+}
+
+#[test]
+fn length_delimited_header() {
+    let mock = Builder::new()
+        .read(&[0, 0, 0, 3, 1, 2, 3, 0, 0])
+        .build();
+    let exec = Deterministic::new();
+    let res = exec.run(Framed::new(mock, LengthDelimitedCodec::new()).collect());
+    assert_eq!(res.unwrap().unwrap_err().kind(), io::ErrorKind::Other);
+
+    let mut codec = LengthDelimitedCodec::new();
+    codec.max_frame_length(2);
+    let mock = Builder::new().read(&[0, 0, 0, 3]).build();
+    let res = exec.run(Framed::new(mock, codec).collect());
+    assert_eq!(res.unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let mut codec = LengthDelimitedCodec::new();
+    codec.length_field_length(1);
+    let mock = Builder::new().build();
+    let mut framed = Framed::new(mock, codec);
+    assert_eq!(framed.start_send(vec![0; 256]).unwrap_err().kind(),
+               io::ErrorKind::InvalidData);
+}
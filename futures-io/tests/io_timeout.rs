@@ -0,0 +1,66 @@
+extern crate futures;
+extern crate futures_io;
+
+use std::io::{self, Write};
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+
+use futures::Future;
+use futures::executor::Deterministic;
+use futures_io::{duplex, read_exact, write_all, IoTimeout};
+
+mod support;
+use support::*;
+
+#[test]
+fn idle_timeout() {
+    let (a, mut b) = duplex(1024);
+    let exec = Deterministic::new();
+    let mut a = IoTimeout::new(a, clock(&exec));
+    a.set_idle_timeout(Some(Duration::from_secs(1)));
+
+    let (tx, rx) = channel();
+    exec.spawn(read_exact(a, [0; 10]).then(move |res| {
+        tx.send(res.map(|_| ())).unwrap();
+        Ok::<(), ()>(())
+    }));
+    exec.run_until_stalled();
+
+    // Making some progress resets the timer.
+    exec.advance(Duration::from_millis(500));
+    b.write_all(&[1; 5]).unwrap();
+    exec.run_until_stalled();
+    exec.advance(Duration::from_millis(900));
+    assert!(rx.try_recv().is_err());
+
+    exec.advance(Duration::from_millis(100));
+    let err = rx.try_recv().unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn deadline() {
+    let (a, mut b) = duplex(4);
+    let exec = Deterministic::new();
+    let start = Instant::now();
+    let mut a = IoTimeout::new(a, VirtualClock::new(&exec, start));
+    a.set_deadline(Some(start + Duration::from_secs(2)));
+
+    // Progress is being made the whole time, but not fast enough.
+    let (tx, rx) = channel();
+    exec.spawn(write_all(a, vec![0; 100]).then(move |res| {
+        tx.send(res.map(|_| ())).unwrap();
+        Ok::<(), ()>(())
+    }));
+    let mut buf = [0; 4];
+    for _ in 0..3 {
+        exec.advance(Duration::from_millis(500));
+        assert_eq!(io::Read::read(&mut b, &mut buf).unwrap(), 4);
+        exec.run_until_stalled();
+        assert!(rx.try_recv().is_err());
+    }
+
+    exec.advance(Duration::from_millis(500));
+    let err = rx.try_recv().unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
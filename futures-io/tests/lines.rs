@@ -0,0 +1,73 @@
+extern crate futures;
+extern crate futures_io;
+
+use std::io;
+
+use futures::executor::Deterministic;
+use futures::stream::Stream;
+use futures_io::{lines, read_line, read_until, BufReader};
+use futures_io::mock::Builder;
+
+#[test]
+fn read_until_delimiter() {
+    let mock = Builder::new()
+        .read(b"ab")
+        .wait()
+        .read(b"c,de,")
+        .read(b"f")
+        .build();
+    let exec = Deterministic::new();
+    let reader = BufReader::with_capacity(2, mock);
+
+    let (reader, buf) = exec.run(read_until(reader, b',', Vec::new()))
+                            .unwrap().unwrap();
+    assert_eq!(buf, b"abc,");
+    let (reader, buf) = exec.run(read_until(reader, b',', buf))
+                            .unwrap().unwrap();
+    assert_eq!(buf, b"abc,de,");
+    let (reader, buf) = exec.run(read_until(reader, b',', Vec::new()))
+                            .unwrap().unwrap();
+    assert_eq!(buf, b"f");
+    let (_, buf) = exec.run(read_until(reader, b',', Vec::new()))
+                       .unwrap().unwrap();
+    assert_eq!(buf, b"");
+}
+
+#[test]
+fn read_line_utf8() {
+    let mock = Builder::new()
+        .read("héllo\nwörld".as_bytes())
+        .wait()
+        .read(b"\n\xff\n")
+        .build();
+    let exec = Deterministic::new();
+    let reader = BufReader::with_capacity(3, mock);
+
+    let (reader, line) = exec.run(read_line(reader, String::new()))
+                             .unwrap().unwrap();
+    assert_eq!(line, "héllo\n");
+    let (reader, line) = exec.run(read_line(reader, line))
+                             .unwrap().unwrap();
+    assert_eq!(line, "héllo\nwörld\n");
+    let res = exec.run(read_line(reader, String::new())).unwrap();
+    match res {
+        Ok(_) => panic!("invalid UTF-8 was accepted"),
+        Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+    }
+}
+
+#[test]
+fn lines_stream() {
+    let mock = Builder::new()
+        .read(b"one\r\ntw")
+        .wait()
+        .read(b"o\n\nthree")
+        .build();
+    let exec = Deterministic::new();
+    let res = exec.run(lines(BufReader::with_capacity(4, mock)).collect());
+    assert_eq!(res.unwrap().unwrap(), ["one", "two", "", "three"]);
+
+    let mock = Builder::new().read(b"ok\n\xc3\x28\n").build();
+    let res = exec.run(lines(BufReader::new(mock)).collect()).unwrap();
+    assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
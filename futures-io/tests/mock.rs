@@ -0,0 +1,83 @@
+extern crate futures;
+extern crate futures_io;
+
+use std::io;
+
+use futures::Future;
+use futures::executor::Deterministic;
+use futures_io::{read_exact, write_all};
+use futures_io::mock::Builder;
+
+fn err<T>(res: Option<Result<T, io::Error>>) -> io::ErrorKind {
+    match res {
+        Some(Err(e)) => e.kind(),
+        Some(Ok(_)) => panic!("expected an error"),
+        None => panic!("future didn't complete"),
+    }
+}
+
+#[test]
+fn read_exact_across_waits() {
+    let mock = Builder::new()
+        .read(b"hel")
+        .wait()
+        .read(b"lo")
+        .build();
+    let exec = Deterministic::new();
+    let res = exec.run(read_exact(mock, [0; 5]));
+    let (mock, buf) = res.unwrap().unwrap();
+    assert_eq!(&buf, b"hello");
+    assert!(mock.is_done());
+
+    let res = exec.run(read_exact(Builder::new().build(), [0; 1]));
+    assert_eq!(err(res), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn write_all_checks_data() {
+    let mock = Builder::new()
+        .write(b"ping")
+        .wait()
+        .write(b"pong")
+        .read(b"ok")
+        .build();
+    let exec = Deterministic::new();
+    let res = exec.run(write_all(mock, b"pingpong").and_then(|(mock, _)| {
+        read_exact(mock, [0; 2])
+    }));
+    let (_mock, buf) = res.unwrap().unwrap();
+    assert_eq!(&buf, b"ok");
+}
+
+#[test]
+fn injected_errors() {
+    let mock = Builder::new()
+        .read_error(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+        .build();
+    let exec = Deterministic::new();
+    let res = exec.run(read_exact(mock, [0; 1]));
+    assert_eq!(err(res), io::ErrorKind::ConnectionReset);
+
+    let mock = Builder::new()
+        .write(b"a")
+        .write_error(io::Error::new(io::ErrorKind::BrokenPipe, "pipe"))
+        .build();
+    let res = exec.run(write_all(mock, b"ab"));
+    assert_eq!(err(res), io::ErrorKind::BrokenPipe);
+}
+
+#[test]
+#[should_panic]
+fn unexpected_write() {
+    let mock = Builder::new().write(b"abc").build();
+    let exec = Deterministic::new();
+    exec.run(write_all(mock, b"abd"));
+}
+
+#[test]
+fn wrong_direction_stalls() {
+    let mock = Builder::new().write(b"ping").build();
+    let exec = Deterministic::new();
+    let res = exec.run(read_exact(mock, [0; 4]));
+    assert!(res.is_none());
+}
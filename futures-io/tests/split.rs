@@ -0,0 +1,94 @@
+extern crate futures;
+extern crate futures_io;
+
+use std::io::{Read, Write};
+use std::sync::mpsc::channel;
+
+use futures::Future;
+use futures::executor::Deterministic;
+use futures_io::{duplex, read_exact, split, write_all, DuplexStream};
+
+#[test]
+fn halves_on_different_tasks() {
+    let (a, b) = duplex(4);
+    let exec = Deterministic::new();
+    let (a_read, a_write) = split(a);
+    let (b_read, b_write) = split(b);
+
+    // Each half is driven by its own task, and each write is bigger than the
+    // pipe so every task has to wait for readiness from the others.
+    let (tx, rx) = channel();
+    let tx2 = tx.clone();
+    exec.spawn(write_all(a_write, b"hello world").map(|_| ())
+                                                 .map_err(|e| panic!("{}", e)));
+    exec.spawn(write_all(b_write, b"dlrow olleh").map(|_| ())
+                                                 .map_err(|e| panic!("{}", e)));
+    exec.spawn(read_exact(a_read, [0; 11]).map(move |(_, buf)| {
+        tx.send(buf).unwrap()
+    }).map_err(|e| panic!("{}", e)));
+    exec.spawn(read_exact(b_read, [0; 11]).map(move |(_, buf)| {
+        tx2.send(buf).unwrap()
+    }).map_err(|e| panic!("{}", e)));
+    exec.run_until_stalled();
+
+    let mut got = vec![rx.recv().unwrap(), rx.recv().unwrap()];
+    got.sort();
+    assert_eq!(got, vec![*b"dlrow olleh", *b"hello world"]);
+}
+
+#[test]
+fn halves_on_parked_tasks_default_executor() {
+    let (a, mut b) = duplex(4);
+    let (a_read, a_write) = split(a);
+
+    // Park a writer and then a reader on their own tasks. The default
+    // executor runs a task inline when it's notified, so each half ends up
+    // waking the other from inside its own poll.
+    let (tx, rx) = channel();
+    let tx2 = tx.clone();
+    write_all(a_write, b"hello world").map(move |_| {
+        tx.send(None).unwrap()
+    }).map_err(|e| panic!("{}", e)).forget();
+    read_exact(a_read, [0; 5]).map(move |(_, buf)| {
+        tx2.send(Some(buf)).unwrap()
+    }).map_err(|e| panic!("{}", e)).forget();
+    assert!(rx.try_recv().is_err());
+
+    let mut got = Vec::new();
+    let mut buf = [0; 4];
+    while got.len() < 11 {
+        let n = b.read(&mut buf).unwrap();
+        got.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(got, b"hello world");
+    assert_eq!(rx.recv().unwrap(), None);
+
+    b.write_all(b"dlrow").unwrap();
+    assert_eq!(rx.recv().unwrap(), Some(*b"dlrow"));
+}
+
+#[test]
+fn halves_are_send() {
+    fn assert_send<T: Send>(_: &T) {}
+    let (a, _b) = duplex(4);
+    let (read, write) = split(a);
+    assert_send(&read);
+    assert_send(&write);
+}
+
+#[test]
+fn reunite() {
+    let (a, b) = duplex(4);
+    let (a_read, a_write) = split(a);
+    let (b_read, b_write) = split(b);
+    assert!(a_read.is_pair_of(&a_write));
+    assert!(!a_read.is_pair_of(&b_write));
+
+    let (a_read, b_write) = match a_read.reunite(b_write) {
+        Ok(_) => panic!("reunited halves of different objects"),
+        Err(e) => (e.0, e.1),
+    };
+    let a: DuplexStream = a_read.reunite(a_write).unwrap();
+    let b: DuplexStream = b_read.reunite(b_write).unwrap();
+    drop((a, b));
+}
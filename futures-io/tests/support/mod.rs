@@ -0,0 +1,34 @@
+#![allow(dead_code)]
+
+use std::time::{Duration, Instant};
+
+use futures::Future;
+use futures::executor::Deterministic;
+use futures_io::Clock;
+
+/// A `Clock` which follows the virtual time of a `Deterministic` executor.
+pub struct VirtualClock {
+    exec: Deterministic,
+    start: Instant,
+}
+
+impl VirtualClock {
+    /// Creates a clock whose virtual time starts at `start`.
+    pub fn new(exec: &Deterministic, start: Instant) -> VirtualClock {
+        VirtualClock { exec: exec.clone(), start: start }
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.start + self.exec.now()
+    }
+
+    fn sleep(&self, dur: Duration) -> Box<Future<Item=(), Error=()>> {
+        self.exec.timeout(dur).boxed()
+    }
+}
+
+pub fn clock(exec: &Deterministic) -> VirtualClock {
+    VirtualClock::new(exec, Instant::now())
+}
@@ -0,0 +1,86 @@
+extern crate futures;
+extern crate futures_io;
+
+use std::io::{self, Read, Write};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use futures::Future;
+use futures::executor::Deterministic;
+use futures_io::{duplex, read_exact, write_all, Meter, Metered, Throttled};
+use futures_io::mock::Builder;
+
+mod support;
+use support::*;
+
+fn drain(r: &mut Read) -> usize {
+    let mut buf = [0; 1024];
+    match r.read(&mut buf) {
+        Ok(n) => n,
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => 0,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+#[test]
+fn throttled_write() {
+    let (a, mut b) = duplex(1024);
+    let exec = Deterministic::new();
+    let a = Throttled::new(a, 100, clock(&exec));
+
+    let (tx, rx) = channel();
+    exec.spawn(write_all(a, vec![0; 250]).map(move |_| tx.send(()).unwrap())
+                                         .map_err(|e| panic!("{}", e)));
+    exec.run_until_stalled();
+    assert_eq!(drain(&mut b), 100);
+
+    exec.advance(Duration::from_millis(500));
+    assert_eq!(drain(&mut b), 0);
+    exec.advance(Duration::from_millis(500));
+    assert_eq!(drain(&mut b), 100);
+    assert!(rx.try_recv().is_err());
+
+    exec.advance(Duration::from_millis(500));
+    assert_eq!(drain(&mut b), 50);
+    rx.try_recv().unwrap();
+}
+
+#[test]
+fn throttled_read() {
+    let (a, mut b) = duplex(1024);
+    b.write_all(&[1; 30]).unwrap();
+    let exec = Deterministic::new();
+    let mut a = Throttled::new(a, 10, clock(&exec));
+    a.set_write_rate(1);
+
+    let (tx, rx) = channel();
+    exec.spawn(read_exact(a, [0; 30]).map(move |(_, buf)| {
+        tx.send(buf.to_vec()).unwrap()
+    }).map_err(|e| panic!("{}", e)));
+    exec.run_until_stalled();
+    exec.advance(Duration::from_millis(1000));
+    assert!(rx.try_recv().is_err());
+    exec.advance(Duration::from_millis(900));
+    assert!(rx.try_recv().is_err());
+    exec.advance(Duration::from_millis(100));
+    assert_eq!(rx.try_recv().unwrap(), vec![1; 30]);
+}
+
+#[test]
+fn metered() {
+    let meter = Meter::new();
+    let exec = Deterministic::new();
+
+    let mock = Builder::new().read(b"hello").write(b"world!").build();
+    let mock = Metered::with_meter(mock, meter.clone());
+    let (mock, _) = exec.run(read_exact(mock, [0; 5])).unwrap().unwrap();
+    let (mock, _) = exec.run(write_all(mock, b"world!")).unwrap().unwrap();
+    assert_eq!(mock.meter().bytes_read(), 5);
+    assert_eq!(mock.meter().bytes_written(), 6);
+
+    let mock = Builder::new().write(b"!").build();
+    let mock = Metered::with_meter(mock, meter.clone());
+    exec.run(write_all(mock, b"!")).unwrap().unwrap();
+    assert_eq!(meter.reset(), (5, 7));
+    assert_eq!(meter.bytes_written(), 0);
+}
@@ -0,0 +1,56 @@
+extern crate futures;
+extern crate futures_io;
+
+use std::io::{IoSlice, IoSliceMut, Read, Write};
+
+use futures::Future;
+use futures::executor::Deterministic;
+use futures_io::{duplex, read_to_end, write_all_vectored};
+use futures_io::mock::Builder;
+
+#[test]
+fn write_all_vectored_duplex() {
+    let (a, b) = duplex(5);
+    let exec = Deterministic::new();
+
+    let bufs = vec![b"head".to_vec(), Vec::new(), b"er\r\n".to_vec(),
+                    b"body".to_vec()];
+    let write = write_all_vectored(a, bufs).map(|(a, bufs)| {
+        assert_eq!(bufs.len(), 4);
+        drop(a);
+    });
+    exec.spawn(write.map_err(|e| panic!("{}", e)));
+    let data = exec.run(read_to_end(b, Vec::new())).unwrap().unwrap();
+    assert_eq!(data, b"header\r\nbody");
+}
+
+#[test]
+fn write_all_vectored_fallback() {
+    // The mock doesn't support vectored writes, so each buffer is written
+    // separately.
+    let mock = Builder::new()
+        .write(b"ab")
+        .wait()
+        .write(b"cd")
+        .build();
+    let exec = Deterministic::new();
+    let bufs = vec![&b"ab"[..], &b"cd"[..]];
+    let (mock, _) = exec.run(write_all_vectored(mock, bufs)).unwrap().unwrap();
+    assert!(mock.is_done());
+}
+
+#[test]
+fn duplex_vectored() {
+    let (mut a, mut b) = duplex(4);
+    let n = a.write_vectored(&[IoSlice::new(b"ab"), IoSlice::new(b"cde")])
+             .unwrap();
+    assert_eq!(n, 4);
+
+    let mut x = [0; 1];
+    let mut y = [0; 8];
+    let n = b.read_vectored(&mut [IoSliceMut::new(&mut x),
+                                  IoSliceMut::new(&mut y)]).unwrap();
+    assert_eq!(n, 4);
+    assert_eq!(&x, b"a");
+    assert_eq!(&y[..3], b"bcd");
+}
@@ -0,0 +1,103 @@
+use std::io;
+use std::marker;
+use std::sync::Arc;
+
+use futures_io::{Decoder, Encoder};
+
+pub trait Parse: Sized + Send + 'static {
+    type Parser: Default + Send + 'static;
+    type Error: Send + 'static + From<io::Error>;
+
+    fn parse(parser: &mut Self::Parser,
+             buf: &Arc<Vec<u8>>,
+             offset: usize)
+             -> Option<Result<(Self, usize), Self::Error>>;
+}
+
+pub trait Serialize: Send + 'static {
+    fn serialize(&self, buf: &mut Vec<u8>);
+}
+
+/// Adapter from the `Parse` and `Serialize` traits to a codec used with
+/// `futures_io::Framed`.
+pub struct HttpCodec<Req: Parse, Resp> {
+    parser: Req::Parser,
+    buf: Arc<Vec<u8>>,
+
+    // how far into the buffer have we parsed? note: we drain lazily
+    pos: usize,
+
+    _marker: marker::PhantomData<fn() -> (Req, Resp)>,
+}
+
+impl<Req: Parse, Resp> HttpCodec<Req, Resp> {
+    pub fn new() -> HttpCodec<Req, Resp> {
+        HttpCodec {
+            parser: Default::default(),
+            buf: Arc::new(Vec::with_capacity(2048)),
+            pos: 0,
+            _marker: marker::PhantomData,
+        }
+    }
+
+    // Makes sure we've got mutable access to our buffer, dropping everything
+    // that's already been parsed.
+    fn drain_parsed(&mut self) {
+        // Fast path if we can get mutable access to our own current buffer.
+        let mut drained = false;
+        if let Some(buf) = Arc::get_mut(&mut self.buf) {
+            buf.drain(..self.pos);
+            drained = true;
+        }
+
+        // If we couldn't get access above then we give ourself a new buffer
+        // here.
+        if !drained {
+            let mut v = Vec::with_capacity(2048);
+            v.extend_from_slice(&self.buf[self.pos..]);
+            self.buf = Arc::new(v);
+        }
+        self.pos = 0;
+    }
+}
+
+impl<Req, Resp> Decoder for HttpCodec<Req, Resp>
+    where Req: Parse,
+          Resp: Send + 'static,
+{
+    type Item = Req;
+    type Error = Req::Error;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Result<Option<Req>, Req::Error> {
+        // Parsed requests may hold on to the buffer they were parsed from, so
+        // we keep our own buffer which requests can share, moving newly read
+        // data over into it.
+        if buf.len() > 0 {
+            self.drain_parsed();
+            Arc::get_mut(&mut self.buf).unwrap().extend_from_slice(buf);
+            buf.clear();
+        }
+
+        match Req::parse(&mut self.parser, &self.buf, self.pos) {
+            Some(Ok((req, n))) => {
+                self.pos += n;
+                Ok(Some(req))
+            }
+            Some(Err(e)) => Err(e),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<Req, Resp> Encoder for HttpCodec<Req, Resp>
+    where Req: Parse,
+          Resp: Serialize,
+{
+    type Item = Resp;
+    type Error = io::Error;
+
+    fn encode(&mut self, resp: Resp, buf: &mut Vec<u8>) -> io::Result<()> {
+        resp.serialize(buf);
+        Ok(())
+    }
+}
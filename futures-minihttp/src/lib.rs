@@ -1,24 +1,21 @@
-#[macro_use]
 extern crate futures_io;
 extern crate futures_mio;
 extern crate futures_tls;
-extern crate net2;
-#[macro_use]
 extern crate futures;
 extern crate httparse;
 extern crate time;
-#[macro_use]
 extern crate log;
 
 use std::io::{self, Read, Write};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::thread;
+use std::time::Duration;
 
 use futures::{Future, Task, Poll};
 use futures::stream::Stream;
-use futures_io::{TaskIo, Ready, IoFuture};
-use futures_mio::{Loop, LoopHandle, TcpStream, TcpListener};
+use futures_io::{split, Ready, IoFuture, IoTimeout, Framed};
+use futures_mio::{Loop, LoopBuilder, LoopHandle, TcpBuilder, TcpStream};
+use futures_mio::TcpListener;
 use futures_tls::{ServerContext, TlsStream};
 
 mod request;
@@ -27,9 +24,9 @@ pub use self::request::{Request, RequestHeaders};
 mod response;
 pub use self::response::Response;
 
-mod io2;
-pub use io2::{Parse, Serialize};
-use io2::{ParseStream, StreamWriter};
+mod codec;
+pub use codec::{Parse, Serialize};
+use codec::HttpCodec;
 
 mod date;
 
@@ -59,11 +56,13 @@ pub struct Server {
     addr: SocketAddr,
     workers: u32,
     tls: Option<Box<Fn() -> io::Result<ServerContext> + Send + Sync>>,
+    idle_timeout: Option<Duration>,
 }
 
 struct ServerData<S> {
     service: S,
     tls: Option<Box<Fn() -> io::Result<ServerContext> + Send + Sync>>,
+    idle_timeout: Option<Duration>,
 }
 
 impl Server {
@@ -72,6 +71,7 @@ impl Server {
             addr: *addr,
             workers: 1,
             tls: None,
+            idle_timeout: None,
         }
     }
 
@@ -89,6 +89,13 @@ impl Server {
         self
     }
 
+    /// Closes connections which go longer than `dur` without sending or
+    /// receiving any bytes.
+    pub fn idle_timeout(&mut self, dur: Duration) -> &mut Server {
+        self.idle_timeout = Some(dur);
+        self
+    }
+
     pub fn serve<Req, Resp, S>(&mut self, s: S) -> io::Result<()>
         where Req: Parse,
               Resp: Serialize,
@@ -98,71 +105,75 @@ impl Server {
         let data = Arc::new(ServerData {
             service: s,
             tls: self.tls.take(),
+            idle_timeout: self.idle_timeout,
         });
 
-        let threads = (0..self.workers - 1).map(|i| {
-            let (addr, workers) = (self.addr, self.workers);
-            let data = data.clone();
-            thread::Builder::new().name(format!("worker{}", i)).spawn(move || {
-                let mut lp = Loop::new().unwrap();
-                let listener = listener(&addr, workers, lp.handle());
-                lp.run(listener.and_then(move |l| {
-                    l.incoming().for_each(move |(stream, _)| {
-                        handle(stream, data.clone());
-                        Ok(()) // TODO: error handling
-                    })
-                }))
-            }).unwrap()
-        }).collect::<Vec<_>>();
-
-        let mut lp = Loop::new().unwrap();
-        let listener = listener(&self.addr, self.workers, lp.handle());
-        lp.run(listener.and_then(move |l| {
-            l.incoming().for_each(move |(stream, _)| {
-                handle(stream, data.clone());
-                Ok(()) // TODO: error handling
-            })
-        })).unwrap();
-
-        for thread in threads {
-            thread.join().unwrap().unwrap();
+        let mut threads = Vec::new();
+        let mut done = Vec::new();
+        for i in 0..self.workers - 1 {
+            let name = format!("worker{}", i);
+            let (lp, thread) = try!(LoopBuilder::new().name(&name).spawn_thread());
+            let accept = accept::<Req, Resp, S>(&self.addr, self.workers,
+                                                lp.clone(), data.clone());
+            done.push(lp.spawn(accept).then(|res| {
+                match res {
+                    Ok(res) => res,
+                    Err(_) => Err(io::Error::new(io::ErrorKind::Other,
+                                                 "worker loop went away")),
+                }
+            }));
+            threads.push((lp, thread));
         }
 
-        Ok(())
+        let mut lp = try!(Loop::new());
+        let accept = accept::<Req, Resp, S>(&self.addr, self.workers,
+                                            lp.handle(), data);
+        let res = lp.run(accept.join(futures::collect(done)));
+
+        for (lp, thread) in threads {
+            lp.shutdown();
+            thread.join().unwrap();
+        }
+
+        res.map(|_| ())
     }
 }
 
+fn accept<Req, Resp, S>(addr: &SocketAddr,
+                        workers: u32,
+                        lp: LoopHandle,
+                        data: Arc<ServerData<S>>) -> Box<IoFuture<()>>
+    where Req: Parse,
+          Resp: Serialize,
+          S: Service<Req, Resp>,
+          <S::Fut as Future>::Error: From<Req::Error> + From<io::Error>,
+{
+    listener(addr, workers, lp.clone()).and_then(move |l| {
+        l.incoming().for_each(move |(stream, _)| {
+            handle(stream, lp.clone(), data.clone());
+            Ok(()) // TODO: error handling
+        })
+    }).boxed()
+}
+
 fn listener(addr: &SocketAddr,
             workers: u32,
             handle: LoopHandle) -> Box<IoFuture<TcpListener>> {
-    let listener = (|| {
-        let listener = try!(net2::TcpBuilder::new_v4());
-        try!(configure_tcp(workers, &listener));
-        try!(listener.reuse_address(true));
-        try!(listener.bind(addr));
-        listener.listen(1024)
-    })();
-
-    match listener {
-        Ok(l) => TcpListener::from_listener(l, addr, handle),
-        Err(e) => futures::failed(e).boxed()
-    }
+    let mut builder = handle.tcp_builder();
+    builder.set_reuse_address(true).set_backlog(1024);
+    configure_tcp(workers, &mut builder);
+    builder.listen(addr)
 }
 
 #[cfg(unix)]
-fn configure_tcp(workers: u32, tcp: &net2::TcpBuilder) -> io::Result<()> {
-    use net2::unix::*;
-
+fn configure_tcp(workers: u32, tcp: &mut TcpBuilder) {
     if workers > 1 {
-        try!(tcp.reuse_port(true));
+        tcp.set_reuse_port(true);
     }
-
-    Ok(())
 }
 
 #[cfg(windows)]
-fn configure_tcp(workers: u32, _tcp: &net2::TcpBuilder) -> io::Result<()> {
-    Ok(())
+fn configure_tcp(_workers: u32, _tcp: &mut TcpBuilder) {
 }
 
 trait IoStream: Read + Write + Stream<Item=Ready, Error=io::Error> {}
@@ -171,12 +182,17 @@ impl<T: ?Sized> IoStream for T
     where T: Read + Write + Stream<Item=Ready, Error=io::Error>
 {}
 
-fn handle<Req, Resp, S>(stream: TcpStream, data: Arc<ServerData<S>>)
+fn handle<Req, Resp, S>(stream: TcpStream,
+                        lp: LoopHandle,
+                        data: Arc<ServerData<S>>)
     where Req: Parse,
           Resp: Serialize,
           S: Service<Req, Resp>,
           <S::Fut as Future>::Error: From<Req::Error> + From<io::Error>,
 {
+    let mut stream = IoTimeout::new(stream, lp.clone());
+    stream.set_idle_timeout(data.idle_timeout);
+
     let io = match data.tls {
         Some(ref tls) => {
             Either::A(tls().unwrap().handshake(stream).map(|b| {
@@ -188,17 +204,20 @@ fn handle<Req, Resp, S>(stream: TcpStream, data: Arc<ServerData<S>>)
             Either::B(futures::finished(stream))
         }
     };
-    let io = io.and_then(|io| TaskIo::new(io)).map_err(From::from).and_then(|io| {
-        let (reader, writer) = io.split();
+    let io = io.map_err(From::from).and_then(|io| {
+        let (reader, writer) = split(io);
 
-        let input = ParseStream::new(reader).map_err(From::from);
+        let input = Framed::new(reader, HttpCodec::<Req, Resp>::new());
+        let input = input.map_err(From::from);
         let responses = input.and_then(move |req| data.service.process(req));
-        StreamWriter::new(writer, responses)
+        Framed::new(writer, HttpCodec::<Req, Resp>::new()).send_all(responses)
     });
 
-    // Crucially use `.forget()` here instead of returning the future, allows
-    // processing multiple separate connections concurrently.
-    io.forget();
+    // Crucially spawn the future here instead of returning it, which allows
+    // processing multiple separate connections concurrently. Spawning it onto
+    // the loop, rather than using `.forget()`, also lets a graceful shutdown
+    // of the loop wait for the connection to finish.
+    drop(lp.spawn(io));
 }
 
 /// Temporary adapter for a read/write stream which is either TLS or not.
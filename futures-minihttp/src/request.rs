@@ -5,7 +5,7 @@ use std::sync::Arc;
 
 use httparse;
 
-use io2::Parse;
+use codec::Parse;
 
 pub struct Request {
     method: Slice,
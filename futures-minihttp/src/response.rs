@@ -1,6 +1,6 @@
 use std::fmt::{self, Write};
 
-use io2::Serialize;
+use codec::Serialize;
 
 pub struct Response {
     headers: Vec<(String, String)>,
@@ -0,0 +1,55 @@
+#![feature(test)]
+
+extern crate futures;
+extern crate futures_mio;
+extern crate test;
+
+use std::time::{Duration, Instant};
+
+use futures::Future;
+use futures_mio::{Loop, LoopBuilder, TimeoutToken};
+
+const CONNECTIONS: u64 = 1_000_000;
+
+// Creates a loop with an idle timeout outstanding for each of a million
+// connections, spread out over a minute starting a minute from now.
+fn loaded_loop() -> (Loop, Vec<TimeoutToken>) {
+    let mut lp = LoopBuilder::new()
+        .timer_tick(Duration::from_millis(1))
+        .build()
+        .unwrap();
+    let handle = lp.handle();
+    let now = Instant::now();
+    let timeouts = (0..CONNECTIONS).map(move |i| {
+        let idle = Duration::from_secs(60) + Duration::from_millis(i % 60_000);
+        handle.add_timeout(now + idle)
+    });
+    let tokens = lp.run(futures::collect(timeouts)).unwrap();
+    (lp, tokens)
+}
+
+#[bench]
+fn add_cancel_1m_outstanding(b: &mut test::Bencher) {
+    let (mut lp, _tokens) = loaded_loop();
+    let handle = lp.handle();
+    b.iter(|| {
+        let handle2 = handle.clone();
+        let at = Instant::now() + Duration::from_secs(30);
+        lp.run(handle.add_timeout(at).map(move |t| {
+            handle2.cancel_timeout(&t)
+        })).unwrap();
+    });
+}
+
+#[bench]
+fn reset_1m_outstanding(b: &mut test::Bencher) {
+    let (mut lp, tokens) = loaded_loop();
+    let handle = lp.handle();
+    let mut i = 0;
+    b.iter(|| {
+        let at = Instant::now() + Duration::from_secs(90);
+        handle.reset_timeout(&tokens[i % tokens.len()], at);
+        i += 1;
+        lp.run(futures::finished::<(), ()>(())).unwrap();
+    });
+}
@@ -8,7 +8,7 @@ use std::env;
 use std::net::SocketAddr;
 
 use futures::Future;
-use futures_io::{copy, TaskIo};
+use futures_io::{copy, split};
 use futures::stream::Stream;
 
 fn main() {
@@ -26,18 +26,14 @@ fn main() {
         println!("Listening on: {}", addr);
 
         // Pull out the stream of incoming connections and then for each new
-        // one spin up a new task copying data. We put the `socket` into a
-        // `TaskIo` structure which then allows us to `split` it into the read
-        // and write halves of the socket.
+        // one spin up a new task copying data. We `split` the `socket` into
+        // its read and write halves.
         //
         // Finally we use the `io::copy` future to copy all data from the
         // reading half onto the writing half.
         socket.incoming().for_each(|(socket, addr)| {
-            let io = TaskIo::new(socket);
-            let pair = io.map(|io| io.split());
-            let amt = pair.and_then(|(reader, writer)| {
-                copy(reader, writer)
-            });
+            let (reader, writer) = split(socket);
+            let amt = copy(reader, writer);
 
             // Once all that is done we print out how much we wrote, and then
             // critically we *forget* this future which allows it to run
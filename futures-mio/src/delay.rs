@@ -0,0 +1,93 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::{Future, Task, Poll};
+use futures_io::IoFuture;
+
+use LoopHandle;
+use event_loop::TimeoutToken;
+
+/// A future which completes at a deadline that can be changed after it's
+/// been created.
+///
+/// This is like `Timeout`, except that the `reset` method can move the
+/// deadline, either earlier or later, and even after the delay has already
+/// completed. Resetting a delay reuses its slot in the event loop's timer
+/// rather than allocating a new one, so it's cheap to do often, for example
+/// to push back an idle timer every time some data arrives.
+///
+/// Delays are created through the `LoopHandle::delay` or
+/// `LoopHandle::delay_at` methods.
+pub struct Delay {
+    at: Instant,
+    token: TimeoutToken,
+    handle: LoopHandle,
+}
+
+impl LoopHandle {
+    /// Creates a new delay which will complete at `dur` time into the future.
+    ///
+    /// This function will return a future that will resolve to the actual
+    /// delay object, once it's been registered with the event loop.
+    pub fn delay(self, dur: Duration) -> Box<IoFuture<Delay>> {
+        self.delay_at(Instant::now() + dur)
+    }
+
+    /// Creates a new delay which will complete at the time specified by `at`.
+    ///
+    /// This function will return a future that will resolve to the actual
+    /// delay object, once it's been registered with the event loop.
+    pub fn delay_at(self, at: Instant) -> Box<IoFuture<Delay>> {
+        self.add_timeout(at).map(move |token| {
+            Delay {
+                at: at,
+                token: token,
+                handle: self,
+            }
+        }).boxed()
+    }
+}
+
+impl Delay {
+    /// Returns the instant at which this delay completes.
+    pub fn deadline(&self) -> Instant {
+        self.at
+    }
+
+    /// Changes the deadline of this delay to `at`.
+    ///
+    /// If the delay has already completed then it will complete again once
+    /// `at` is reached, so it can be polled again.
+    pub fn reset(&mut self, at: Instant) {
+        self.at = at;
+        self.handle.reset_timeout(&self.token, at);
+    }
+
+    /// Returns whether the deadline of this delay has passed.
+    pub fn is_elapsed(&self) -> bool {
+        self.at <= Instant::now()
+    }
+}
+
+impl Future for Delay {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self, _task: &mut Task) -> Poll<(), io::Error> {
+        if self.is_elapsed() {
+            Poll::Ok(())
+        } else {
+            Poll::NotReady
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.handle.update_timeout(&self.token, task);
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        self.handle.cancel_timeout(&self.token);
+    }
+}
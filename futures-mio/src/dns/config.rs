@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+/// Configuration for a `Resolver`: which name servers it asks, how patiently,
+/// and which names it already knows the addresses of.
+///
+/// `ResolverConfig::system` reads the configuration the system's own resolver
+/// uses, and `ResolverConfig::new` starts from an empty one.
+#[derive(Clone, Debug)]
+pub struct ResolverConfig {
+    nameservers: Vec<SocketAddr>,
+    hosts: HashMap<String, Vec<IpAddr>>,
+    timeout: Duration,
+    attempts: usize,
+    // Whether names are looked up with the operating system's resolver
+    // rather than by asking name servers ourselves.
+    os_resolver: bool,
+}
+
+impl ResolverConfig {
+    /// Creates a new configuration without any name servers or hosts.
+    ///
+    /// Queries time out after five seconds and each name server is tried
+    /// twice, the same defaults as for `/etc/resolv.conf`.
+    pub fn new() -> ResolverConfig {
+        ResolverConfig {
+            nameservers: Vec::new(),
+            hosts: HashMap::new(),
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+            os_resolver: false,
+        }
+    }
+
+    /// Reads the configuration from `/etc/resolv.conf` and `/etc/hosts`.
+    ///
+    /// The `nameserver` lines and the `timeout` and `attempts` options of
+    /// `resolv.conf` are used, and the rest of it is ignored. If it doesn't
+    /// list any name servers then the one on the local machine is used, and a
+    /// missing file of either kind is treated as an empty one.
+    ///
+    /// On platforms other than Unix there are no such files to read, so the
+    /// returned configuration instead has names looked up with the operating
+    /// system's resolver, on a separate thread as that blocks. Addresses
+    /// looked up that way aren't cached.
+    pub fn system() -> io::Result<ResolverConfig> {
+        let mut config = ResolverConfig::new();
+        if !cfg!(unix) {
+            config.os_resolver = true;
+            return Ok(config)
+        }
+        config.read_resolv_conf(&try!(read_optional("/etc/resolv.conf")));
+        config.read_hosts(&try!(read_optional("/etc/hosts")));
+        if config.nameservers.is_empty() {
+            config.nameservers.push("127.0.0.1:53".parse().unwrap());
+        }
+        Ok(config)
+    }
+
+    /// Adds a name server to ask, after any that were already added.
+    pub fn nameserver(&mut self, addr: SocketAddr) -> &mut ResolverConfig {
+        self.nameservers.push(addr);
+        self
+    }
+
+    /// Adds an address for `name`, which is then returned for it without
+    /// asking any name servers, like an entry in `/etc/hosts`.
+    pub fn host(&mut self, name: &str, addr: IpAddr) -> &mut ResolverConfig {
+        let addrs = self.hosts.entry(normalize(name)).or_insert(Vec::new());
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+        self
+    }
+
+    /// Sets how long to wait for a response from a name server before trying
+    /// the next one.
+    pub fn set_timeout(&mut self, dur: Duration) -> &mut ResolverConfig {
+        self.timeout = dur;
+        self
+    }
+
+    /// Sets how many times each name server is tried before a lookup fails.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `attempts` is zero.
+    pub fn set_attempts(&mut self, attempts: usize) -> &mut ResolverConfig {
+        assert!(attempts > 0, "must make at least one attempt");
+        self.attempts = attempts;
+        self
+    }
+
+    /// Returns the name servers to ask, in order.
+    pub fn nameservers(&self) -> &[SocketAddr] {
+        &self.nameservers
+    }
+
+    /// Returns the addresses known for `name` without asking a name server.
+    pub fn hosts(&self, name: &str) -> Option<&[IpAddr]> {
+        self.hosts.get(&normalize(name)).map(|a| &a[..])
+    }
+
+    /// Returns how long to wait for a response from a name server.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns how many times each name server is tried.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Returns whether names are looked up with the operating system's
+    /// resolver instead of the configured name servers.
+    pub fn os_resolver(&self) -> bool {
+        self.os_resolver
+    }
+
+    fn read_resolv_conf(&mut self, contents: &str) {
+        for line in contents.lines() {
+            let line = line.split(|c| c == '#' || c == ';').next().unwrap();
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("nameserver") => {
+                    let addr = words.next().and_then(|w| w.parse().ok());
+                    if let Some(addr) = addr {
+                        self.nameservers.push(SocketAddr::new(addr, 53));
+                    }
+                }
+                Some("options") => {
+                    for option in words {
+                        let mut parts = option.splitn(2, ':');
+                        let name = parts.next().unwrap();
+                        let value = parts.next().and_then(|v| v.parse().ok());
+                        match (name, value) {
+                            ("timeout", Some(n)) => {
+                                self.timeout = Duration::from_secs(n as u64);
+                            }
+                            ("attempts", Some(n)) if n > 0 => {
+                                self.attempts = n;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn read_hosts(&mut self, contents: &str) {
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap();
+            let mut words = line.split_whitespace();
+            let addr = match words.next().and_then(|w| w.parse().ok()) {
+                Some(addr) => addr,
+                None => continue,
+            };
+            for name in words {
+                self.host(name, addr);
+            }
+        }
+    }
+}
+
+/// Returns `name` in the form it's looked up by, ignoring case and any
+/// trailing dot.
+pub fn normalize(name: &str) -> String {
+    name.trim_right_matches('.').to_lowercase()
+}
+
+fn read_optional<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut contents = String::new();
+    match File::open(path) {
+        Ok(mut f) => try!(f.read_to_string(&mut contents)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(contents)
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    use super::ResolverConfig;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn resolv_conf() {
+        let mut config = ResolverConfig::new();
+        config.read_resolv_conf("
+# comment
+domain example.com
+nameserver 10.0.0.1
+nameserver ::1 ; trailing comment
+nameserver not-an-address
+options ndots:2 timeout:3 attempts:4
+");
+        assert_eq!(config.nameservers(),
+                   &["10.0.0.1:53".parse().unwrap(),
+                     "[::1]:53".parse().unwrap()][..]);
+        assert_eq!(config.timeout(), Duration::from_secs(3));
+        assert_eq!(config.attempts(), 4);
+    }
+
+    #[test]
+    fn hosts() {
+        let mut config = ResolverConfig::new();
+        config.read_hosts("
+127.0.0.1 localhost
+::1       localhost ip6-localhost # comment
+10.0.0.2  Example.com.
+# 10.0.0.3 commented.example.com
+");
+        assert_eq!(config.hosts("localhost"),
+                   Some(&[ip("127.0.0.1"), ip("::1")][..]));
+        assert_eq!(config.hosts("ip6-localhost"), Some(&[ip("::1")][..]));
+        assert_eq!(config.hosts("example.COM"), Some(&[ip("10.0.0.2")][..]));
+        assert_eq!(config.hosts("commented.example.com"), None);
+    }
+}
@@ -0,0 +1,246 @@
+//! Encoding of DNS queries and decoding of the responses to them, as
+//! described in RFC 1035.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_AAAA: u16 = 28;
+const TYPE_CNAME: u16 = 5;
+const CLASS_IN: u16 = 1;
+
+const HEADER_LEN: usize = 12;
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+
+pub const RCODE_NO_ERROR: u16 = 0;
+pub const RCODE_NAME_ERROR: u16 = 3;
+
+/// The parts of a response to a query that the resolver cares about.
+pub struct Answer {
+    pub rcode: u16,
+    pub truncated: bool,
+    pub addrs: Vec<IpAddr>,
+    // The smallest TTL of the records in `addrs`, in seconds.
+    pub ttl: u32,
+}
+
+/// Encodes a recursive query with the given `id` for records of type `qtype`
+/// belonging to `name`.
+pub fn query(id: u16, name: &str, qtype: u16) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    push_u16(&mut buf, id);
+    push_u16(&mut buf, FLAG_RECURSION_DESIRED);
+    push_u16(&mut buf, 1); // questions
+    push_u16(&mut buf, 0); // answers
+    push_u16(&mut buf, 0); // authority records
+    push_u16(&mut buf, 0); // additional records
+
+    let name = name.trim_right_matches('.');
+    if name.len() > 253 {
+        return Err(invalid_name(name))
+    }
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid_name(name))
+        }
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    push_u16(&mut buf, qtype);
+    push_u16(&mut buf, CLASS_IN);
+    Ok(buf)
+}
+
+/// Decodes a response to the query with the given `id` for records of type
+/// `qtype` belonging to `name`, which is expected to be normalized.
+///
+/// Returns `None` if `buf` isn't a response to that query at all, such as a
+/// stray response to some earlier query or one whose question doesn't echo
+/// ours.
+///
+/// Only addresses belonging to `name`, or to a name it's an alias of through
+/// the CNAME records in the response, are returned. Anything else a server
+/// includes is ignored rather than trusted.
+pub fn parse(buf: &[u8], id: u16, name: &str, qtype: u16)
+             -> io::Result<Option<Answer>> {
+    if buf.len() < HEADER_LEN {
+        return Err(malformed())
+    }
+    let flags = read_u16(buf, 2);
+    if read_u16(buf, 0) != id || flags & FLAG_RESPONSE == 0 {
+        return Ok(None)
+    }
+    let questions = read_u16(buf, 4);
+    let answers = read_u16(buf, 6);
+
+    if questions != 1 {
+        return Ok(None)
+    }
+    let (qname, mut pos) = try!(read_name(buf, HEADER_LEN));
+    if buf.len() < pos + 4 {
+        return Err(malformed())
+    }
+    if qname != name.trim_right_matches('.') ||
+       read_u16(buf, pos) != qtype ||
+       read_u16(buf, pos + 2) != CLASS_IN {
+        return Ok(None)
+    }
+    pos += 4;
+
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        let (owner, next) = try!(read_name(buf, pos));
+        pos = next;
+        if buf.len() < pos + 10 {
+            return Err(malformed())
+        }
+        let rtype = read_u16(buf, pos);
+        let class = read_u16(buf, pos + 2);
+        let ttl = (read_u16(buf, pos + 4) as u32) << 16 |
+                  read_u16(buf, pos + 6) as u32;
+        let len = read_u16(buf, pos + 8) as usize;
+        pos += 10;
+        if buf.len() < pos + len {
+            return Err(malformed())
+        }
+        let data = pos;
+        pos += len;
+        if class != CLASS_IN {
+            continue
+        }
+        // RFC 2181 says TTLs with the top bit set are to be treated as zero.
+        let ttl = if ttl & 0x8000_0000 != 0 {0} else {ttl};
+        records.push(Record {
+            owner: owner,
+            rtype: rtype,
+            ttl: ttl,
+            data: data,
+            len: len,
+        });
+    }
+
+    let mut answer = Answer {
+        rcode: flags & 0xf,
+        truncated: flags & FLAG_TRUNCATED != 0,
+        addrs: Vec::new(),
+        ttl: u32::max_value(),
+    };
+
+    // Follow any CNAME records from the name we asked about to the name the
+    // addresses actually belong to, as recursive servers include the records
+    // they point to as well. The chain is bounded by the number of records
+    // so a loop of aliases can't keep us here.
+    let mut owner = name.trim_right_matches('.').to_string();
+    for _ in 0..records.len() {
+        let alias = records.iter().find(|r| {
+            r.rtype == TYPE_CNAME && r.owner == owner
+        });
+        let alias = match alias {
+            Some(alias) => alias,
+            None => break,
+        };
+        owner = try!(read_name(buf, alias.data)).0;
+        if alias.ttl < answer.ttl {
+            answer.ttl = alias.ttl;
+        }
+    }
+
+    for record in records.iter() {
+        if record.rtype != qtype || record.owner != owner {
+            continue
+        }
+        let data = &buf[record.data..record.data + record.len];
+        let addr = match (record.rtype, record.len) {
+            (TYPE_A, 4) => {
+                IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3]))
+            }
+            (TYPE_AAAA, 16) => {
+                let mut segments = [0; 8];
+                for (i, segment) in segments.iter_mut().enumerate() {
+                    *segment = read_u16(data, i * 2);
+                }
+                IpAddr::V6(Ipv6Addr::new(segments[0], segments[1],
+                                         segments[2], segments[3],
+                                         segments[4], segments[5],
+                                         segments[6], segments[7]))
+            }
+            _ => return Err(malformed()),
+        };
+        answer.addrs.push(addr);
+        if record.ttl < answer.ttl {
+            answer.ttl = record.ttl;
+        }
+    }
+    Ok(Some(answer))
+}
+
+// A resource record from the answer section, with its data left in place.
+struct Record {
+    owner: String,
+    rtype: u16,
+    ttl: u32,
+    data: usize,
+    len: usize,
+}
+
+// Reads the name starting at `pos`, following compression pointers, and
+// returns it normalized along with the position just past it.
+fn read_name(buf: &[u8], mut pos: usize) -> io::Result<(String, usize)> {
+    let mut name = String::new();
+    let mut end = None;
+    loop {
+        let len = match buf.get(pos) {
+            Some(&len) => len as usize,
+            None => return Err(malformed()),
+        };
+        if len == 0 {
+            return Ok((name, end.unwrap_or(pos + 1)))
+        } else if len & 0xc0 == 0xc0 {
+            let target = match buf.get(pos + 1) {
+                Some(&low) => (len & 0x3f) << 8 | low as usize,
+                None => return Err(malformed()),
+            };
+            // Pointers must point backwards, so following them always ends.
+            if target >= pos {
+                return Err(malformed())
+            }
+            if end.is_none() {
+                end = Some(pos + 2);
+            }
+            pos = target;
+            continue
+        } else if len & 0xc0 != 0 {
+            return Err(malformed())
+        }
+        let label = match buf.get(pos + 1..pos + 1 + len) {
+            Some(label) => label,
+            None => return Err(malformed()),
+        };
+        if !name.is_empty() {
+            name.push('.');
+        }
+        name.push_str(&String::from_utf8_lossy(label).to_lowercase());
+        pos += len + 1;
+    }
+}
+
+fn push_u16(buf: &mut Vec<u8>, n: u16) {
+    buf.push((n >> 8) as u8);
+    buf.push(n as u8);
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> u16 {
+    (buf[pos] as u16) << 8 | buf[pos + 1] as u16
+}
+
+fn invalid_name(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput,
+                   format!("invalid host name: {}", name))
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed DNS response")
+}
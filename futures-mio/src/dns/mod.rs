@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::stream::Stream;
+use futures::{Future, Task, Poll, failed, finished, promise};
+use futures_io::{IoFuture, read_exact, write_all};
+
+use {LoopHandle, UdpSocket};
+use self::message::{Answer, TYPE_A, TYPE_AAAA};
+
+pub use self::config::ResolverConfig;
+
+mod config;
+mod message;
+
+/// An asynchronous DNS resolver, looking up the addresses of hosts without
+/// blocking the event loop.
+///
+/// Names are looked up in the configured hosts first, and otherwise both
+/// their IPv4 and IPv6 addresses are asked for from the configured name
+/// servers, over UDP, or TCP if a response is too large. Addresses from name
+/// servers are cached for as long as their records' TTL allows.
+///
+/// Resolvers are created through the `LoopHandle::resolver` method. They can
+/// be cloned, and the clones share a cache.
+#[derive(Clone)]
+pub struct Resolver {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    handle: LoopHandle,
+    config: ResolverConfig,
+    cache: Mutex<HashMap<(String, u16), CacheEntry>>,
+    // Query ids are what stops anyone who can't see our queries from
+    // answering them with addresses of their choosing, so they need to be
+    // unpredictable. The hash state of std's hash maps is randomly keyed by
+    // the OS, so we take ids from hashing a counter with it.
+    id_key: RandomState,
+    next_id: AtomicUsize,
+}
+
+struct CacheEntry {
+    addrs: Vec<IpAddr>,
+    expires: Instant,
+}
+
+impl LoopHandle {
+    /// Creates a new resolver which uses this event loop for its queries.
+    pub fn resolver(self, config: ResolverConfig) -> Resolver {
+        Resolver {
+            inner: Arc::new(Inner {
+                handle: self,
+                config: config,
+                cache: Mutex::new(HashMap::new()),
+                id_key: RandomState::new(),
+                next_id: AtomicUsize::new(0),
+            }),
+        }
+    }
+}
+
+impl Resolver {
+    /// Looks up the addresses of `host`, returning a future of all of them.
+    ///
+    /// If `host` is an IP address itself then that's returned. Otherwise any
+    /// IPv4 addresses are listed before any IPv6 addresses, and the future
+    /// resolves to an error if there are none at all.
+    pub fn lookup(&self, host: &str) -> Box<IoFuture<Vec<IpAddr>>> {
+        if let Ok(addr) = host.parse() {
+            return finished(vec![addr]).boxed()
+        }
+        if let Some(addrs) = self.inner.config.hosts(host) {
+            return finished(addrs.to_vec()).boxed()
+        }
+        if self.inner.config.os_resolver() {
+            return os_lookup(host)
+        }
+
+        let name = config::normalize(host);
+        let v4 = self.query(&name, TYPE_A).then(|r| Ok::<_, io::Error>(r));
+        let v6 = self.query(&name, TYPE_AAAA).then(|r| Ok::<_, io::Error>(r));
+        v4.join(v6).and_then(move |(v4, v6)| {
+            let mut addrs = match (v4, v6) {
+                (Ok(mut v4), Ok(v6)) => {
+                    v4.extend(v6);
+                    v4
+                }
+                (Ok(addrs), Err(e)) | (Err(e), Ok(addrs)) => {
+                    debug!("lookup of {} partially failed: {}", name, e);
+                    addrs
+                }
+                (Err(e), Err(_)) => return Err(e),
+            };
+            addrs.dedup();
+            found(&name, addrs)
+        }).boxed()
+    }
+
+    /// Returns a handle to the event loop this resolver uses for its queries.
+    pub fn loop_handle(&self) -> &LoopHandle {
+        &self.inner.handle
+    }
+
+    /// Forgets all of the cached addresses.
+    pub fn clear_cache(&self) {
+        self.inner.cache.lock().unwrap().clear();
+    }
+
+    // Asks for the records of type `qtype` for `name`, from the cache if
+    // possible and otherwise from each name server in turn until one answers.
+    fn query(&self, name: &str, qtype: u16) -> Box<IoFuture<Vec<IpAddr>>> {
+        let key = (name.to_string(), qtype);
+        if let Some(entry) = self.inner.cache.lock().unwrap().get(&key) {
+            if entry.expires > Instant::now() {
+                return finished(entry.addrs.clone()).boxed()
+            }
+        }
+
+        let config = &self.inner.config;
+        let mut servers = Vec::new();
+        for _ in 0..config.attempts() {
+            servers.extend(config.nameservers().iter().cloned());
+        }
+        servers.reverse();
+        let err = io::Error::new(io::ErrorKind::Other,
+                                 "no name servers configured");
+
+        let answer = try_servers(self.inner.clone(), name.to_string(), qtype,
+                                 servers, err);
+        let inner = self.inner.clone();
+        answer.map(move |answer| {
+            if !answer.addrs.is_empty() {
+                let ttl = Duration::from_secs(answer.ttl as u64);
+                inner.cache.lock().unwrap().insert(key, CacheEntry {
+                    addrs: answer.addrs.clone(),
+                    expires: Instant::now() + ttl,
+                });
+            }
+            answer.addrs
+        }).boxed()
+    }
+}
+
+fn found(name: &str, addrs: Vec<IpAddr>) -> io::Result<Vec<IpAddr>> {
+    if addrs.is_empty() {
+        Err(io::Error::new(io::ErrorKind::NotFound,
+                           format!("no addresses found for {}", name)))
+    } else {
+        Ok(addrs)
+    }
+}
+
+// Looks up `host` with the operating system's resolver. That blocks, so it's
+// done on a thread of its own rather than on the event loop.
+fn os_lookup(host: &str) -> Box<IoFuture<Vec<IpAddr>>> {
+    let (tx, rx) = promise();
+    let host = host.to_string();
+    thread::spawn(move || {
+        let res = (&host[..], 0).to_socket_addrs().and_then(|addrs| {
+            // List IPv4 addresses first, like our own lookups do.
+            let mut addrs = addrs.map(|a| a.ip()).collect::<Vec<_>>();
+            addrs.sort_by_key(|a| {
+                match *a {
+                    IpAddr::V4(..) => 0,
+                    IpAddr::V6(..) => 1,
+                }
+            });
+            addrs.dedup();
+            found(&host, addrs)
+        });
+        tx.complete(res);
+    });
+    rx.then(|res| {
+        match res {
+            Ok(res) => res,
+            Err(_) => Err(io::Error::new(io::ErrorKind::Other,
+                                         "lookup thread panicked")),
+        }
+    }).boxed()
+}
+
+// Sends the query to the last of `servers`, moving on to the next one if that
+// fails, and failing with `err` once there are none left.
+fn try_servers(inner: Arc<Inner>,
+               name: String,
+               qtype: u16,
+               mut servers: Vec<SocketAddr>,
+               err: io::Error) -> Box<IoFuture<Answer>> {
+    let server = match servers.pop() {
+        Some(server) => server,
+        None => return failed(err).boxed(),
+    };
+    exchange(&inner, server, &name, qtype).or_else(move |e| {
+        debug!("query for {} to {} failed: {}", name, server, e);
+        try_servers(inner, name, qtype, servers, e)
+    }).boxed()
+}
+
+// Sends a single query to `server` and waits for its response, with a
+// timeout.
+fn exchange(inner: &Inner, server: SocketAddr, name: &str, qtype: u16)
+            -> Box<IoFuture<Answer>> {
+    let id = inner.next_id();
+    let query = match message::query(id, name, qtype) {
+        Ok(query) => query,
+        Err(e) => return failed(e).boxed(),
+    };
+    let local = match server {
+        SocketAddr::V4(..) => "0.0.0.0:0".parse().unwrap(),
+        SocketAddr::V6(..) => "[::]:0".parse().unwrap(),
+    };
+
+    let handle = inner.handle.clone();
+    let name = name.to_string();
+    let socket = inner.handle.clone().udp_bind(&local);
+    let response = socket.and_then(move |socket| {
+        try!(socket.send_to(&query, &server));
+        Ok(UdpResponse {
+            socket: socket,
+            server: server,
+            id: id,
+            name: name.clone(),
+            qtype: qtype,
+        }.map(move |answer| (answer, query, name)))
+    }).flatten().and_then(move |(answer, query, name)| {
+        if answer.truncated {
+            debug!("response from {} truncated, retrying over TCP", server);
+            tcp_exchange(handle, server, query, id, name, qtype)
+        } else {
+            finished(answer).boxed()
+        }
+    }).and_then(|answer| {
+        match answer.rcode {
+            // If the name doesn't exist then asking other servers won't help,
+            // so that's the same as there being no records for it.
+            message::RCODE_NO_ERROR |
+            message::RCODE_NAME_ERROR => Ok(answer),
+            rcode => {
+                Err(io::Error::new(io::ErrorKind::Other,
+                                   format!("name server returned error code {}",
+                                           rcode)))
+            }
+        }
+    });
+
+    let timeout = inner.handle.clone().timeout(inner.config.timeout());
+    let timeout = timeout.and_then(|t| t).and_then(|()| {
+        Err(io::Error::new(io::ErrorKind::TimedOut, "DNS query timed out"))
+    });
+    response.select(timeout).then(|res| {
+        match res {
+            Ok((answer, _)) => Ok(answer),
+            Err((e, _)) => Err(e),
+        }
+    }).boxed()
+}
+
+// Sends a query to `server` over TCP, which is used when the response was too
+// large for UDP.
+fn tcp_exchange(handle: LoopHandle,
+                server: SocketAddr,
+                query: Vec<u8>,
+                id: u16,
+                name: String,
+                qtype: u16) -> Box<IoFuture<Answer>> {
+    let mut msg = Vec::with_capacity(query.len() + 2);
+    msg.push((query.len() >> 8) as u8);
+    msg.push(query.len() as u8);
+    msg.extend_from_slice(&query);
+
+    handle.tcp_connect(&server).and_then(move |stream| {
+        write_all(stream, msg)
+    }).and_then(|(stream, _)| {
+        read_exact(stream, [0u8; 2])
+    }).and_then(|(stream, len)| {
+        let len = (len[0] as usize) << 8 | len[1] as usize;
+        read_exact(stream, vec![0; len])
+    }).and_then(move |(_, buf)| {
+        match try!(message::parse(&buf, id, &name, qtype)) {
+            Some(answer) => Ok(answer),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData,
+                                       "unexpected DNS response")),
+        }
+    }).boxed()
+}
+
+impl Inner {
+    fn next_id(&self) -> u16 {
+        let mut hasher = self.id_key.build_hasher();
+        hasher.write_usize(self.next_id.fetch_add(1, Ordering::SeqCst));
+        hasher.finish() as u16
+    }
+}
+
+/// A future waiting for the response to a query sent over UDP.
+struct UdpResponse {
+    socket: UdpSocket,
+    server: SocketAddr,
+    id: u16,
+    name: String,
+    qtype: u16,
+}
+
+impl Future for UdpResponse {
+    type Item = Answer;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Answer, io::Error> {
+        // Consume any readiness first, so a response which arrives after we
+        // last see `WouldBlock` below still wakes us up.
+        let _ = self.socket.poll(task);
+
+        let mut buf = [0; 512];
+        loop {
+            let (n, addr) = match self.socket.recv_from(&mut buf) {
+                Ok(pair) => pair,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    return Poll::NotReady
+                }
+                Err(e) => return Poll::Err(e),
+            };
+            if addr != self.server {
+                debug!("ignoring DNS response from {}", addr);
+                continue
+            }
+            match message::parse(&buf[..n], self.id, &self.name,
+                                 self.qtype) {
+                Ok(Some(answer)) => return Poll::Ok(answer),
+                Ok(None) => debug!("ignoring unexpected DNS response"),
+                Err(e) => debug!("ignoring DNS response: {}", e),
+            }
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.socket.schedule(task)
+    }
+}
@@ -1,4 +1,5 @@
 use std::cell::{Cell, RefCell};
+use std::cmp;
 use std::io::{self, ErrorKind};
 use std::marker;
 use std::mem;
@@ -6,14 +7,19 @@ use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
 use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
 use std::time::{Instant, Duration};
 
-use futures::{Future, Task, TaskHandle, Poll};
+use futures::{Future, IntoFuture, Task, TaskHandle, Poll, Promise, Complete,
+              promise};
 use futures::executor::{ExecuteCallback, Executor};
-use futures_io::Ready;
+use futures_io::{IoFuture, Ready};
+#[cfg(target_os = "linux")]
+use libc;
 use mio;
 use slab::Slab;
 
+use {Resolver, ResolverConfig};
 use channel::{Sender, Receiver, channel};
 use event_loop::dropbox::DropBox;
 use slot::{self, Slot};
@@ -34,6 +40,19 @@ const SLAB_CAPACITY: usize = 1024 * 64;
 pub struct Loop {
     id: usize,
     active: Cell<bool>,
+    // The number of futures spawned with `LoopHandle::spawn`, or passed to
+    // `Loop::run`, which haven't completed yet, and whether we're waiting for
+    // that to reach zero before shutting down. While draining,
+    // `drain_deadline` is when we give up waiting, and `shutdown_waiters` are
+    // the futures returned from `LoopHandle::shutdown_signal` which haven't
+    // been told yet.
+    spawned: Cell<usize>,
+    draining: Cell<bool>,
+    drain_deadline: Cell<Option<Instant>>,
+    shutdown_waiters: RefCell<Vec<Complete<()>>>,
+    // The resolver handed out by `LoopHandle::system_resolver`, created the
+    // first time it's asked for.
+    system_resolver: RefCell<Option<Resolver>>,
     io: mio::Poll,
     tx: Arc<MioSender>,
     rx: Receiver<Message>,
@@ -48,6 +67,12 @@ pub struct Loop {
     timer_wheel: RefCell<TimerWheel<usize>>,
     timeouts: RefCell<Slab<(Timeout, TimeoutState), usize>>,
 
+    // Configuration from the `LoopBuilder` that's needed after creation.
+    max_sources: Option<usize>,
+    events_per_poll: Option<usize>,
+    name: Option<String>,
+    cpu_affinity: Option<usize>,
+
     // A `Loop` cannot be sent to other threads as it's used as a proxy for data
     // that belongs to the thread the loop was running on at some point. In
     // other words, the safety of `DropBox` below relies on loops not crossing
@@ -55,6 +80,165 @@ pub struct Loop {
     _marker: marker::PhantomData<Rc<u32>>,
 }
 
+/// A builder for configuring an event loop before it's created.
+///
+/// This can be used to tune an event loop for its workload, and `Loop::new` is
+/// the same as building with all the defaults.
+#[derive(Clone, Debug)]
+pub struct LoopBuilder {
+    source_capacity: usize,
+    max_sources: Option<usize>,
+    events_per_poll: Option<usize>,
+    timer_tick: Option<Duration>,
+    timer_capacity: usize,
+    name: Option<String>,
+    cpu_affinity: Option<usize>,
+}
+
+impl LoopBuilder {
+    /// Creates a new builder with the default configuration.
+    pub fn new() -> LoopBuilder {
+        LoopBuilder {
+            source_capacity: SLAB_CAPACITY,
+            max_sources: None,
+            events_per_poll: None,
+            timer_tick: None,
+            timer_capacity: SLAB_CAPACITY,
+            name: None,
+            cpu_affinity: None,
+        }
+    }
+
+    /// Sets the number of sources, such as sockets, the loop has room for
+    /// when it's created.
+    ///
+    /// Once this many sources are registered the space is doubled, up to the
+    /// limit set by `max_sources` if there is one. The default is 65536.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `capacity` is zero.
+    pub fn source_capacity(&mut self, capacity: usize) -> &mut LoopBuilder {
+        assert!(capacity > 0, "source capacity must be nonzero");
+        self.source_capacity = capacity;
+        self
+    }
+
+    /// Sets the maximum number of sources which can be registered with the
+    /// loop at once.
+    ///
+    /// Once the limit is reached registering another source fails with an
+    /// error until an existing one is dropped. By default there's no limit.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `max` is zero.
+    pub fn max_sources(&mut self, max: usize) -> &mut LoopBuilder {
+        assert!(max > 0, "maximum number of sources must be nonzero");
+        self.max_sources = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of I/O events the loop pulls out of the
+    /// operating system each time it polls.
+    ///
+    /// Any further events are picked up by the next poll, so a larger batch
+    /// means fewer system calls when many sources are busy at once. The
+    /// default is the one used by mio.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `events` is zero.
+    pub fn events_per_poll(&mut self, events: usize) -> &mut LoopBuilder {
+        assert!(events > 0, "events per poll must be nonzero");
+        self.events_per_poll = Some(events);
+        self
+    }
+
+    /// Sets the resolution of the loop's timers.
+    ///
+    /// Timeouts are rounded to the nearest multiple of this duration since the
+    /// loop was created, so a smaller tick makes timeouts more precise at the
+    /// cost of the loop waking up more often when many timeouts are pending.
+    /// The default is 100ms.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `tick` is zero.
+    pub fn timer_tick(&mut self, tick: Duration) -> &mut LoopBuilder {
+        assert!(tick > Duration::new(0, 0), "timer tick must be nonzero");
+        self.timer_tick = Some(tick);
+        self
+    }
+
+    /// Sets the number of timeouts the loop has room for when it's created.
+    ///
+    /// Once this many timeouts are pending the space is doubled. The default
+    /// is 65536.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `capacity` is zero.
+    pub fn timer_capacity(&mut self, capacity: usize) -> &mut LoopBuilder {
+        assert!(capacity > 0, "timer capacity must be nonzero");
+        self.timer_capacity = capacity;
+        self
+    }
+
+    /// Sets the name of the loop, which can be retrieved with `Loop::name` and
+    /// is given to the thread started by `spawn_thread`.
+    pub fn name(&mut self, name: &str) -> &mut LoopBuilder {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Asks for the thread running the loop to be pinned to the CPU with
+    /// index `cpu`.
+    ///
+    /// This is only a hint: the thread is pinned each time `Loop::run` is
+    /// called, on platforms which support it and if the CPU exists, and is
+    /// otherwise left alone. The CPUs the thread was allowed to run on before
+    /// are restored when `run` returns. By default the thread isn't pinned.
+    pub fn cpu_affinity(&mut self, cpu: usize) -> &mut LoopBuilder {
+        self.cpu_affinity = Some(cpu);
+        self
+    }
+
+    /// Creates a new event loop with this configuration, returning any error
+    /// that happened during the creation.
+    pub fn build(&self) -> io::Result<Loop> {
+        Loop::from_builder(self)
+    }
+
+    /// Creates a new event loop with this configuration on a new thread, and
+    /// runs it there until it's shut down.
+    ///
+    /// The thread is given the name set with `name`, if any. See
+    /// `Loop::spawn_thread` for more details.
+    pub fn spawn_thread(&self) -> io::Result<(LoopHandle, JoinHandle<()>)> {
+        let builder = self.clone();
+        let (tx, rx) = mpsc::channel();
+        let mut thread = thread::Builder::new();
+        if let Some(ref name) = self.name {
+            thread = thread.name(name.clone());
+        }
+        let join = try!(thread.spawn(move || {
+            let mut lp = match builder.build() {
+                Ok(lp) => lp,
+                Err(e) => return drop(tx.send(Err(e))),
+            };
+            drop(tx.send(Ok(lp.handle())));
+            lp._run();
+        }));
+        match rx.recv() {
+            Ok(Ok(handle)) => Ok((handle, join)),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(io::Error::new(ErrorKind::Other,
+                                         "event loop thread panicked")),
+        }
+    }
+}
+
 struct MioSender {
     inner: Sender<Message>,
 }
@@ -73,6 +257,10 @@ pub struct LoopHandle {
 struct Scheduled {
     source: IoSource,
     waiter: Option<TaskHandle>,
+    // Whether the source is registered to receive another event. This is only
+    // ever false for oneshot sources, which need to be reregistered after each
+    // event.
+    armed: bool,
 }
 
 enum TimeoutState {
@@ -86,20 +274,46 @@ enum Message {
     DropSource(usize),
     Schedule(usize, TaskHandle),
     Deschedule(usize),
+    SetInterest(usize, Ready),
     AddTimeout(Instant, Arc<Slot<io::Result<TimeoutToken>>>),
     UpdateTimeout(TimeoutToken, TaskHandle),
+    ResetTimeout(TimeoutToken, Instant),
     CancelTimeout(TimeoutToken),
     Run(Box<ExecuteCallback>),
     Drop(DropBox<dropbox::MyDrop>),
+    SpawnDone,
+    RunDone,
+    Drain(Option<Instant>),
     Shutdown,
 }
 
 /// Type of I/O objects inserted into the event loop, created by `Source::new`.
 pub struct Source<E: ?Sized> {
     readiness: AtomicUsize,
+    interest: AtomicUsize,
+    trigger: Trigger,
     io: E,
 }
 
+/// How a `Source` is notified of readiness by the event loop, chosen when it's
+/// created with `Source::with_interest`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    /// The source is notified when it becomes ready, and then not again until
+    /// it has stopped being ready, for example until a read has returned
+    /// `WouldBlock`. This is the default.
+    Edge,
+
+    /// The source is notified for as long as it is ready, so the event loop
+    /// keeps waking up until the readiness has been dealt with or the
+    /// interest in it has been removed.
+    Level,
+
+    /// The source is notified at most once each time that a task waits for
+    /// it with `LoopHandle::schedule`.
+    Oneshot,
+}
+
 /// I/O objects inserted into the event loop
 pub type IoSource = Arc<Source<mio::Evented + Sync + Send>>;
 
@@ -108,8 +322,33 @@ fn register(poll: &mio::Poll,
             sched: &Scheduled) -> io::Result<()> {
     poll.register(&sched.source.io,
                   mio::Token(token),
-                  mio::EventSet::readable() | mio::EventSet::writable(),
-                  mio::PollOpt::edge())
+                  sched.source.event_set(),
+                  sched.source.poll_opt())
+}
+
+fn reregister(poll: &mio::Poll,
+              token: usize,
+              sched: &Scheduled) -> io::Result<()> {
+    poll.reregister(&sched.source.io,
+                    mio::Token(token),
+                    sched.source.event_set(),
+                    sched.source.poll_opt())
+}
+
+// Reregisters a source to hear about its next events, returning whether
+// that worked. Failing here usually means the descriptor was already closed
+// out from under us, in which case there's nobody to tell but the waiter.
+fn rearm(poll: &mio::Poll, token: usize, sched: &mut Scheduled) -> bool {
+    match reregister(poll, token, sched) {
+        Ok(()) => {
+            sched.armed = true;
+            true
+        }
+        Err(e) => {
+            debug!("failed to reregister source {}: {}", token, e);
+            false
+        }
+    }
 }
 
 fn deregister(poll: &mio::Poll, sched: &Scheduled) {
@@ -117,10 +356,67 @@ fn deregister(poll: &mio::Poll, sched: &Scheduled) {
     poll.deregister(&sched.source.io).unwrap();
 }
 
+// Pins the current thread to `cpu`, returning the set of CPUs it was allowed
+// to run on before so that can be restored afterwards.
+#[cfg(target_os = "linux")]
+fn set_cpu_affinity(cpu: usize) -> io::Result<libc::cpu_set_t> {
+    unsafe {
+        let mut prev: libc::cpu_set_t = mem::zeroed();
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        if cpu >= mem::size_of_val(&set) * 8 {
+            return Err(io::Error::new(ErrorKind::InvalidInput,
+                                      "cpu index out of range"))
+        }
+        if libc::sched_getaffinity(0, mem::size_of_val(&prev), &mut prev) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, mem::size_of_val(&set), &set) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(prev)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn restore_cpu_affinity(prev: &libc::cpu_set_t) -> io::Result<()> {
+    unsafe {
+        if libc::sched_setaffinity(0, mem::size_of_val(prev), prev) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_cpu_affinity(_cpu: usize) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn restore_cpu_affinity(_prev: &()) -> io::Result<()> {
+    Ok(())
+}
+
 impl Loop {
     /// Creates a new event loop, returning any error that happened during the
     /// creation.
+    ///
+    /// The loop is created with the default configuration, see `LoopBuilder`
+    /// for creating a loop with a different one.
     pub fn new() -> io::Result<Loop> {
+        LoopBuilder::new().build()
+    }
+
+    fn from_builder(builder: &LoopBuilder) -> io::Result<Loop> {
+        let timer_wheel = match builder.timer_tick {
+            Some(tick) => TimerWheel::with_tick(tick),
+            None => TimerWheel::new(),
+        };
+        let source_capacity = match builder.max_sources {
+            Some(max) => cmp::min(builder.source_capacity, max),
+            None => builder.source_capacity,
+        };
         let (tx, rx) = channel();
         let io = try!(mio::Poll::new());
         try!(io.register(&rx,
@@ -130,12 +426,22 @@ impl Loop {
         Ok(Loop {
             id: NEXT_LOOP_ID.fetch_add(1, Ordering::Relaxed),
             active: Cell::new(true),
+            spawned: Cell::new(0),
+            draining: Cell::new(false),
+            drain_deadline: Cell::new(None),
+            shutdown_waiters: RefCell::new(Vec::new()),
+            system_resolver: RefCell::new(None),
             io: io,
             tx: Arc::new(MioSender { inner: tx }),
             rx: rx,
-            dispatch: RefCell::new(Slab::new_starting_at(1, SLAB_CAPACITY)),
-            timeouts: RefCell::new(Slab::new_starting_at(0, SLAB_CAPACITY)),
-            timer_wheel: RefCell::new(TimerWheel::new()),
+            dispatch: RefCell::new(Slab::new_starting_at(1, source_capacity)),
+            timeouts: RefCell::new(Slab::new_starting_at(0,
+                                                         builder.timer_capacity)),
+            timer_wheel: RefCell::new(timer_wheel),
+            max_sources: builder.max_sources,
+            events_per_poll: builder.events_per_poll,
+            name: builder.name.clone(),
+            cpu_affinity: builder.cpu_affinity,
             _marker: marker::PhantomData,
         })
     }
@@ -171,22 +477,66 @@ impl Loop {
     /// Runs a future until completion, driving the event loop while we're
     /// otherwise waiting for the future to complete.
     ///
-    /// Returns the value that the future resolves to.
+    /// Returns the value that the future resolves to. If the loop is shutting
+    /// down gracefully when the future completes then this keeps running the
+    /// loop until the futures spawned onto it have completed as well, and a
+    /// graceful shutdown likewise waits for this future.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the loop stops before the future completes,
+    /// because of `LoopHandle::shutdown` or because the deadline given to
+    /// `LoopHandle::shutdown_gracefully_timeout` passed.
     pub fn run<F: Future>(&mut self, f: F) -> Result<F::Item, F::Error> {
         let (tx_res, rx_res) = mpsc::channel();
         let handle = self.handle();
+        self.spawned.set(self.spawned.get() + 1);
         f.then(move |res| {
-            handle.shutdown();
-            tx_res.send(res)
+            drop(tx_res.send(res));
+            handle.send(Message::RunDone);
+            Ok::<(), ()>(())
         }).forget();
 
         self._run();
 
-        rx_res.recv().unwrap()
+        match rx_res.try_recv() {
+            Ok(res) => res,
+            Err(_) => panic!("event loop stopped before the future passed to \
+                              `run` completed"),
+        }
+    }
+
+    /// Creates a new event loop with the default configuration on a new thread,
+    /// and runs it there until it's shut down.
+    ///
+    /// This returns a handle to the new loop, which can be used to spawn
+    /// futures onto it with `LoopHandle::spawn`, along with a handle to join
+    /// the thread. The thread exits once the loop is shut down with
+    /// `LoopHandle::shutdown` or `LoopHandle::shutdown_gracefully`.
+    pub fn spawn_thread() -> io::Result<(LoopHandle, JoinHandle<()>)> {
+        LoopBuilder::new().spawn_thread()
+    }
+
+    /// Returns the name given to this loop by `LoopBuilder::name`, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|s| &s[..])
     }
 
     fn _run(&mut self) {
-        let mut events = mio::Events::new();
+        let prev_affinity = self.cpu_affinity.and_then(|cpu| {
+            match set_cpu_affinity(cpu) {
+                Ok(prev) => Some(prev),
+                Err(e) => {
+                    debug!("failed to pin loop {:?} to cpu {}: {}",
+                           self.name, cpu, e);
+                    None
+                }
+            }
+        });
+        let mut events = match self.events_per_poll {
+            Some(n) => mio::Events::with_capacity(n),
+            None => mio::Events::new(),
+        };
         self.active.set(true);
         while self.active.get() {
             let amt;
@@ -195,7 +545,12 @@ impl Loop {
             // attaching strace, or similar.
             let start = Instant::now();
             loop {
-                let timeout = self.timer_wheel.borrow().next_timeout().map(|t| {
+                let next = self.timer_wheel.borrow().next_timeout();
+                let next = match (next, self.drain_deadline.get()) {
+                    (Some(a), Some(b)) => Some(cmp::min(a, b)),
+                    (a, b) => a.or(b),
+                };
+                let timeout = next.map(|t| {
                     if t < start {
                         Duration::new(0, 0)
                     } else {
@@ -243,6 +598,9 @@ impl Loop {
                 let mut waiter = None;
                 if let Some(sched) = self.dispatch.borrow_mut().get_mut(token) {
                     waiter = sched.waiter.take();
+                    if sched.source.trigger == Trigger::Oneshot {
+                        sched.armed = false;
+                    }
                     if event.kind().is_readable() {
                         sched.source.readiness.fetch_or(1, Ordering::Relaxed);
                     }
@@ -260,6 +618,28 @@ impl Loop {
             }
 
             debug!("loop process - {} events, {:?}", amt, start.elapsed());
+
+            // Finally, if we're draining and ran out of time to do so, stop
+            // waiting for whatever's left.
+            if let Some(deadline) = self.drain_deadline.get() {
+                if Instant::now() >= deadline {
+                    debug!("deadline for spawned futures passed, shutting down");
+                    self.active.set(false);
+                }
+            }
+        }
+
+        // A graceful shutdown only applies to this run of the loop, so the
+        // loop can be run again afterwards without it still refusing new I/O
+        // objects or stopping straight away once the deadline has passed.
+        // Any shutdown waiters were already resolved when draining started.
+        self.draining.set(false);
+        self.drain_deadline.set(None);
+
+        if let Some(prev) = prev_affinity {
+            if let Err(e) = restore_cpu_affinity(&prev) {
+                debug!("failed to unpin loop {:?}: {}", self.name, e);
+            }
         }
 
         debug!("loop is done!");
@@ -288,13 +668,27 @@ impl Loop {
     }
 
     fn add_source(&self, source: IoSource) -> io::Result<usize> {
+        if self.draining.get() {
+            return Err(io::Error::new(ErrorKind::Other,
+                                      "event loop is shutting down"))
+        }
         let sched = Scheduled {
             source: source,
             waiter: None,
+            armed: true,
         };
         let mut dispatch = self.dispatch.borrow_mut();
         if dispatch.vacant_entry().is_none() {
-            let amt = dispatch.count();
+            let len = dispatch.count();
+            let amt = match self.max_sources {
+                Some(max) => cmp::min(len, max - len),
+                None => len,
+            };
+            if amt == 0 {
+                return Err(io::Error::new(ErrorKind::Other,
+                                          "too many sources registered with \
+                                           the event loop"))
+            }
             dispatch.grow(amt);
         }
         let entry = dispatch.vacant_entry().unwrap();
@@ -310,10 +704,21 @@ impl Loop {
     fn schedule(&self, token: usize, wake: TaskHandle) {
         let to_call = {
             let mut dispatch = self.dispatch.borrow_mut();
-            let sched = dispatch.get_mut(token).unwrap();
+            let sched = match dispatch.get_mut(token) {
+                Some(sched) => sched,
+                None => {
+                    debug!("schedule for dropped source {}", token);
+                    return
+                }
+            };
             if sched.source.readiness.load(Ordering::Relaxed) != 0 {
                 sched.waiter = None;
                 Some(wake)
+            } else if !sched.armed && !rearm(&self.io, token, sched) {
+                // The task won't hear from us again, so wake it up now to
+                // find out what's wrong by trying its I/O.
+                sched.waiter = None;
+                Some(wake)
             } else {
                 sched.waiter = Some(wake);
                 None
@@ -331,6 +736,29 @@ impl Loop {
         sched.waiter = None;
     }
 
+    fn set_interest(&self, token: usize, interest: Ready) {
+        let to_wake = {
+            let mut dispatch = self.dispatch.borrow_mut();
+            let sched = match dispatch.get_mut(token) {
+                Some(sched) => sched,
+                None => {
+                    debug!("set interest for dropped source {}", token);
+                    return
+                }
+            };
+            sched.source.interest.store(ready_bits(interest),
+                                        Ordering::SeqCst);
+            if rearm(&self.io, token, sched) {
+                None
+            } else {
+                sched.waiter.take()
+            }
+        };
+        if let Some(to_wake) = to_wake {
+            self.notify_handle(to_wake);
+        }
+    }
+
     fn add_timeout(&self, at: Instant) -> io::Result<TimeoutToken> {
         let mut timeouts = self.timeouts.borrow_mut();
         if timeouts.vacant_entry().is_none() {
@@ -350,6 +778,15 @@ impl Loop {
         }
     }
 
+    fn reset_timeout(&self, token: &TimeoutToken, at: Instant) {
+        let mut timeouts = self.timeouts.borrow_mut();
+        let pair = &mut timeouts[token.token];
+        let mut timer_wheel = self.timer_wheel.borrow_mut();
+        timer_wheel.cancel(&pair.0);
+        pair.0 = timer_wheel.insert(at, token.token);
+        pair.1.reset();
+    }
+
     fn cancel_timeout(&self, token: &TimeoutToken) {
         let pair = self.timeouts.borrow_mut().remove(token.token);
         if let Some((timeout, _state)) = pair {
@@ -357,6 +794,52 @@ impl Loop {
         }
     }
 
+    fn check_drained(&self) {
+        if self.draining.get() && self.spawned.get() == 0 {
+            debug!("all spawned futures done, shutting down");
+            self.active.set(false);
+        }
+    }
+
+    fn drain(&self, deadline: Option<Instant>) {
+        if !self.draining.get() {
+            debug!("starting graceful shutdown");
+            self.draining.set(true);
+            let waiters = mem::replace(&mut *self.shutdown_waiters.borrow_mut(),
+                                       Vec::new());
+            for waiter in waiters {
+                waiter.complete(());
+            }
+        }
+        if let Some(deadline) = deadline {
+            let deadline = match self.drain_deadline.get() {
+                Some(prev) => cmp::min(prev, deadline),
+                None => deadline,
+            };
+            self.drain_deadline.set(Some(deadline));
+        }
+        self.check_drained();
+    }
+
+    fn system_resolver(&self) -> io::Result<Resolver> {
+        let mut resolver = self.system_resolver.borrow_mut();
+        if let Some(ref resolver) = *resolver {
+            return Ok(resolver.clone())
+        }
+        let config = try!(ResolverConfig::system());
+        let new = self.handle().resolver(config);
+        *resolver = Some(new.clone());
+        Ok(new)
+    }
+
+    fn add_shutdown_waiter(&self, waiter: Complete<()>) {
+        if self.draining.get() {
+            waiter.complete(());
+        } else {
+            self.shutdown_waiters.borrow_mut().push(waiter);
+        }
+    }
+
     fn consume_queue(&self) {
         // TODO: can we do better than `.unwrap()` here?
         while let Some(msg) = self.rx.recv().unwrap() {
@@ -374,6 +857,25 @@ impl Loop {
             Message::DropSource(tok) => self.drop_source(tok),
             Message::Schedule(tok, wake) => self.schedule(tok, wake),
             Message::Deschedule(tok) => self.deschedule(tok),
+            Message::SetInterest(tok, interest) => {
+                self.set_interest(tok, interest)
+            }
+            Message::SpawnDone => {
+                self.spawned.set(self.spawned.get() - 1);
+                self.check_drained();
+            }
+            Message::RunDone => {
+                // The future passed to `run` is counted as a spawned future,
+                // and it only stops the loop straight away when the loop
+                // isn't waiting for the others anyway.
+                self.spawned.set(self.spawned.get() - 1);
+                if self.draining.get() {
+                    self.check_drained();
+                } else {
+                    self.active.set(false);
+                }
+            }
+            Message::Drain(deadline) => self.drain(deadline),
             Message::Shutdown => self.active.set(false),
 
             Message::AddTimeout(at, slot) => {
@@ -381,6 +883,7 @@ impl Loop {
                     .ok().expect("interference with try_produce on timeout");
             }
             Message::UpdateTimeout(t, handle) => self.update_timeout(&t, handle),
+            Message::ResetTimeout(t, at) => self.reset_timeout(&t, at),
             Message::CancelTimeout(t) => self.cancel_timeout(&t),
             Message::Run(f) => f.call(),
             Message::Drop(data) => drop(data),
@@ -495,6 +998,23 @@ impl LoopHandle {
         self.send(Message::Deschedule(tok));
     }
 
+    /// Changes which kinds of readiness the event loop listens for on a
+    /// source, such as to stop waking up for writability while there's
+    /// nothing to write.
+    ///
+    /// The source must have been registered through the `add_source` method,
+    /// and this takes effect for the next events the event loop sees. See
+    /// `Source::with_interest` for the interest a source starts off with.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the event loop this handle is associated
+    /// with has gone away, or if there is an error communicating with the event
+    /// loop.
+    pub fn set_interest(&self, tok: usize, interest: Ready) {
+        self.send(Message::SetInterest(tok, interest));
+    }
+
     /// Unregister all information associated with a token on an event loop,
     /// deallocating all internal resources assigned to the given token.
     ///
@@ -541,6 +1061,21 @@ impl LoopHandle {
         self.send(Message::UpdateTimeout(timeout, task.handle().clone()))
     }
 
+    /// Resets a previously added timeout to fire at `at` instead.
+    ///
+    /// The timeout keeps its token, and if a task is waiting on it then that
+    /// task is notified once the new instant is reached. This works both
+    /// before and after the timeout has fired.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the timeout specified was not created by this
+    /// loop handle's `add_timeout` method.
+    pub fn reset_timeout(&self, timeout: &TimeoutToken, at: Instant) {
+        let timeout = TimeoutToken { token: timeout.token };
+        self.send(Message::ResetTimeout(timeout, at))
+    }
+
     /// Cancel a previously added timeout.
     ///
     /// # Panics
@@ -599,6 +1134,109 @@ impl LoopHandle {
     pub fn shutdown(&self) {
         self.send(Message::Shutdown);
     }
+
+    /// Send a message to the associated event loop that it should shut down
+    /// once all of the futures spawned onto it with `spawn`, as well as the
+    /// future it's running with `Loop::run`, if any, have completed.
+    ///
+    /// Once the loop receives this message it starts shutting down:
+    ///
+    /// * The futures returned from `shutdown_signal` resolve.
+    /// * Listeners stop accepting connections, so the streams returned from
+    ///   `TcpListener::incoming` and `UnixListener::incoming` end.
+    /// * New I/O objects can no longer be registered with the loop, and trying
+    ///   to do so returns an error.
+    ///
+    /// Futures can still be spawned while the loop is waiting for the others
+    /// to complete, and the loop waits for those as well. Like `shutdown`
+    /// this only causes the loop to break out of its current iteration, and
+    /// a loop run with `Loop::spawn_thread` exits its thread.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the event loop this handle is associated
+    /// with has gone away, or if there is an error communicating with the event
+    /// loop.
+    pub fn shutdown_gracefully(&self) {
+        self.send(Message::Drain(None));
+    }
+
+    /// Like `shutdown_gracefully`, except that the loop only waits `dur` for
+    /// the spawned futures to complete, and shuts down anyway after that.
+    ///
+    /// Any futures which haven't completed by then are dropped along with the
+    /// loop.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the event loop this handle is associated
+    /// with has gone away, or if there is an error communicating with the event
+    /// loop.
+    pub fn shutdown_gracefully_timeout(&self, dur: Duration) {
+        self.send(Message::Drain(Some(Instant::now() + dur)));
+    }
+
+    /// Returns a future which resolves once the associated event loop starts
+    /// shutting down gracefully.
+    ///
+    /// This can be used to find out when to stop doing new work, such as
+    /// reading new requests from a connection, so that the futures spawned
+    /// onto the loop can complete and let it shut down. The future also
+    /// resolves if the loop goes away without shutting down gracefully.
+    pub fn shutdown_signal(&self) -> Box<IoFuture<()>> {
+        let (tx, rx) = promise();
+        self.send(Message::Run(Box::new(move || {
+            CURRENT_LOOP.with(|lp| lp.add_shutdown_waiter(tx));
+        })));
+        rx.then(|_| Ok(())).boxed()
+    }
+
+    /// Returns a future of a resolver configured like the system's own, see
+    /// `ResolverConfig::system`, which uses the associated event loop.
+    ///
+    /// The system's configuration is only read the first time this is called
+    /// for a loop, and after that the same resolver, along with its cache, is
+    /// shared by everything using the loop. This is the resolver used by
+    /// `tcp_connect_host`.
+    pub fn system_resolver(&self) -> Box<IoFuture<Resolver>> {
+        let (tx, rx) = promise();
+        self.send(Message::Run(Box::new(move || {
+            CURRENT_LOOP.with(|lp| tx.complete(lp.system_resolver()));
+        })));
+        rx.then(|res| {
+            match res {
+                Ok(res) => res,
+                Err(_) => Err(io::Error::new(ErrorKind::Other,
+                                             "event loop went away")),
+            }
+        }).boxed()
+    }
+
+    /// Spawns a future to run on the associated event loop, returning a
+    /// promise of its result.
+    ///
+    /// The future is created and first polled on the event loop's thread, so
+    /// this is the way to run work on a loop running on another thread, such
+    /// as one created with `Loop::spawn_thread`. The loop keeps track of the
+    /// futures spawned onto it, so `shutdown_gracefully` can wait for them.
+    ///
+    /// The returned promise is canceled if the future is dropped before
+    /// completing, for example if the loop goes away.
+    pub fn spawn<F>(&self, f: F) -> Promise<Result<F::Item, F::Error>>
+        where F: IntoFuture,
+    {
+        let (tx, rx) = promise();
+        let handle = self.clone();
+        self.send(Message::Run(Box::new(move || {
+            CURRENT_LOOP.with(|lp| lp.spawned.set(lp.spawned.get() + 1));
+            f.into_future().then(move |res| {
+                tx.complete(res);
+                handle.send(Message::SpawnDone);
+                Ok::<(), ()>(())
+            }).forget();
+        })));
+        rx
+    }
 }
 
 /// A future which will resolve a unique `tok` token for an I/O object.
@@ -969,6 +1607,12 @@ impl TimeoutState {
         None
     }
 
+    fn reset(&mut self) {
+        if let TimeoutState::Fired = *self {
+            *self = TimeoutState::NotFired;
+        }
+    }
+
     fn fire(&mut self) -> Option<TaskHandle> {
         match mem::replace(self, TimeoutState::Fired) {
             TimeoutState::NotFired => None,
@@ -980,9 +1624,24 @@ impl TimeoutState {
 
 impl<E> Source<E> {
     /// Creates a new `Source` wrapping the provided source of events.
+    ///
+    /// The source is interested in both readability and writability, with
+    /// edge-triggered notifications.
     pub fn new(e: E) -> Source<E> {
+        Source::with_interest(e, Ready::ReadWrite, Trigger::Edge)
+    }
+
+    /// Creates a new `Source` wrapping the provided source of events, which
+    /// is only notified of the kinds of readiness in `interest` and is
+    /// notified as described by `trigger`.
+    ///
+    /// The interest can be changed after the source has been added to an
+    /// event loop with `LoopHandle::set_interest`, but the trigger is fixed.
+    pub fn with_interest(e: E, interest: Ready, trigger: Trigger) -> Source<E> {
         Source {
             readiness: AtomicUsize::new(0),
+            interest: AtomicUsize::new(ready_bits(interest)),
+            trigger: trigger,
             io: e,
         }
     }
@@ -991,10 +1650,10 @@ impl<E> Source<E> {
 impl<E: ?Sized> Source<E> {
     /// Consumes the last readiness notification that this source received.
     ///
-    /// Currently sources receive readiness notifications on an edge-basis. That
-    /// is, once you receive a notification that an object can be read, you
-    /// won't receive any more notifications until all of that data has been
-    /// read.
+    /// By default sources receive readiness notifications on an edge-basis.
+    /// That is, once you receive a notification that an object can be read,
+    /// you won't receive any more notifications until all of that data has
+    /// been read. See `Trigger` for the other options.
     ///
     /// The event loop will fill in this information and then inform futures
     /// that they're ready to go with the `schedule` method, and then the `poll`
@@ -1013,6 +1672,47 @@ impl<E: ?Sized> Source<E> {
     pub fn io(&self) -> &E {
         &self.io
     }
+
+    /// Returns the kinds of readiness this source is currently interested in.
+    pub fn interest(&self) -> Ready {
+        match self.interest.load(Ordering::SeqCst) {
+            1 => Ready::Read,
+            2 => Ready::Write,
+            3 => Ready::ReadWrite,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns how this source is notified of readiness.
+    pub fn trigger(&self) -> Trigger {
+        self.trigger
+    }
+
+    fn event_set(&self) -> mio::EventSet {
+        match self.interest() {
+            Ready::Read => mio::EventSet::readable(),
+            Ready::Write => mio::EventSet::writable(),
+            Ready::ReadWrite => {
+                mio::EventSet::readable() | mio::EventSet::writable()
+            }
+        }
+    }
+
+    fn poll_opt(&self) -> mio::PollOpt {
+        match self.trigger {
+            Trigger::Edge => mio::PollOpt::edge(),
+            Trigger::Level => mio::PollOpt::level(),
+            Trigger::Oneshot => mio::PollOpt::edge() | mio::PollOpt::oneshot(),
+        }
+    }
+}
+
+fn ready_bits(ready: Ready) -> usize {
+    match ready {
+        Ready::Read => 1,
+        Ready::Write => 2,
+        Ready::ReadWrite => 3,
+    }
 }
 
 impl Executor for MioSender {
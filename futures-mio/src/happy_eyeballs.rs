@@ -0,0 +1,157 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use futures::{Future, Task, Poll};
+use futures_io::IoFuture;
+
+use {LoopHandle, Resolver, TcpStream};
+
+// How long to wait for a connection attempt before starting the next one in
+// parallel, as recommended by RFC 8305.
+const CONNECTION_ATTEMPT_DELAY_MS: u64 = 250;
+
+impl LoopHandle {
+    /// Create a new TCP stream connected to `port` on `host`, which can be a
+    /// host name or an IP address.
+    ///
+    /// The host name is looked up with the loop's resolver configured like
+    /// the system's own, see `system_resolver`, and the connection is made as
+    /// described in `Resolver::tcp_connect`.
+    pub fn tcp_connect_host(self, host: &str, port: u16)
+                            -> Box<IoFuture<TcpStream>> {
+        let host = host.to_string();
+        self.system_resolver().and_then(move |resolver| {
+            resolver.tcp_connect(&host, port)
+        }).boxed()
+    }
+}
+
+impl Resolver {
+    /// Create a new TCP stream connected to `port` on `host`, which can be a
+    /// host name or an IP address, on this resolver's event loop.
+    ///
+    /// When a host has several addresses they're tried following the "Happy
+    /// Eyeballs" algorithm of RFC 8305: IPv6 and IPv4 addresses are tried in
+    /// turn, starting with IPv6, and each attempt is given a short head start
+    /// before the next one begins alongside it. The first connection to be
+    /// established is returned and the other attempts are abandoned. If all of
+    /// them fail then the error from the last one to fail is returned.
+    ///
+    /// Unlike RFC 8305, no attempt starts until both the IPv6 and the IPv4
+    /// addresses of `host` have been looked up, rather than starting as soon
+    /// as the IPv6 addresses are known or shortly after the IPv4 ones are.
+    pub fn tcp_connect(&self, host: &str, port: u16)
+                       -> Box<IoFuture<TcpStream>> {
+        let handle = self.loop_handle().clone();
+        self.lookup(host).and_then(move |addrs| {
+            HappyEyeballs {
+                handle: handle,
+                addrs: interleave(addrs, port),
+                attempts: Vec::new(),
+                delay: None,
+                err: None,
+            }
+        }).boxed()
+    }
+}
+
+// Orders `addrs` to alternate between IPv6 and IPv4 addresses, starting with
+// IPv6. The list is reversed, so the next address to try is the last one.
+fn interleave(addrs: Vec<IpAddr>, port: u16) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(|a| {
+        match *a {
+            IpAddr::V6(..) => true,
+            IpAddr::V4(..) => false,
+        }
+    });
+    let mut v6 = v6.into_iter();
+    let mut v4 = v4.into_iter();
+    let mut ret = Vec::new();
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => break,
+            (a, b) => {
+                ret.extend(a.map(|a| SocketAddr::new(a, port)));
+                ret.extend(b.map(|b| SocketAddr::new(b, port)));
+            }
+        }
+    }
+    ret.reverse();
+    ret
+}
+
+/// A future racing staggered connection attempts to a list of addresses.
+struct HappyEyeballs {
+    handle: LoopHandle,
+    addrs: Vec<SocketAddr>,
+    attempts: Vec<Box<IoFuture<TcpStream>>>,
+    // When to start the next attempt if none of the current ones have
+    // finished by then.
+    delay: Option<Box<IoFuture<()>>>,
+    err: Option<io::Error>,
+}
+
+impl Future for HappyEyeballs {
+    type Item = TcpStream;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<TcpStream, io::Error> {
+        loop {
+            let mut i = 0;
+            while i < self.attempts.len() {
+                match self.attempts[i].poll(task) {
+                    // Dropping the other attempts along with this future
+                    // closes their sockets.
+                    Poll::Ok(stream) => return Poll::Ok(stream),
+                    Poll::Err(e) => {
+                        debug!("connection attempt failed: {}", e);
+                        self.attempts.remove(i);
+                        self.err = Some(e);
+                    }
+                    Poll::NotReady => i += 1,
+                }
+            }
+
+            // The next attempt starts as soon as there are none in progress,
+            // or once the current ones have had long enough on their own.
+            let mut start_next = self.attempts.is_empty();
+            let fired = match self.delay {
+                Some(ref mut delay) => !delay.poll(task).is_not_ready(),
+                None => false,
+            };
+            if fired {
+                self.delay = None;
+                start_next = true;
+            }
+            if !start_next {
+                return Poll::NotReady
+            }
+
+            let addr = match self.addrs.pop() {
+                Some(addr) => addr,
+                None if self.attempts.is_empty() => {
+                    return Poll::Err(self.err.take().unwrap_or_else(|| {
+                        io::Error::new(io::ErrorKind::Other,
+                                       "no addresses to connect to")
+                    }))
+                }
+                None => return Poll::NotReady,
+            };
+            debug!("attempting to connect to {}", addr);
+            self.attempts.push(self.handle.clone().tcp_connect(&addr));
+            let dur = Duration::from_millis(CONNECTION_ATTEMPT_DELAY_MS);
+            let delay = self.handle.clone().timeout(dur).flatten();
+            self.delay = Some(delay.boxed());
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        for attempt in self.attempts.iter_mut() {
+            attempt.schedule(task);
+        }
+        if let Some(ref mut delay) = self.delay {
+            delay.schedule(task);
+        }
+    }
+}
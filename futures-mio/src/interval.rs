@@ -0,0 +1,147 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::stream::Stream;
+use futures::{Future, Task, Poll};
+use futures_io::IoFuture;
+
+use {Delay, LoopHandle};
+
+/// A stream which yields at a fixed period, created through the
+/// `LoopHandle::interval` or `LoopHandle::interval_at` methods.
+///
+/// Each item is the instant at which that tick was scheduled, which may be
+/// a little earlier than when it was actually delivered. Ticks are scheduled
+/// relative to the start of the interval rather than to when the previous
+/// tick was seen, so an interval doesn't drift over time.
+///
+/// If a tick is seen late, for example because the event loop was busy,
+/// what happens to the ticks after it is configured with
+/// `set_missed_tick_behavior`.
+pub struct Interval {
+    delay: Delay,
+    period: Duration,
+    behavior: MissedTickBehavior,
+}
+
+/// What an `Interval` does when ticks are missed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissedTickBehavior {
+    /// Yield all of the missed ticks as quickly as possible, until the
+    /// interval has caught up with its original schedule. This is the
+    /// default.
+    Burst,
+
+    /// Start the schedule over again from when the late tick was seen, so the
+    /// next tick is one full period after that.
+    Delay,
+
+    /// Drop the missed ticks, and yield the next tick at the next point on
+    /// the original schedule.
+    Skip,
+}
+
+impl LoopHandle {
+    /// Creates a new interval which first ticks at `dur` time into the future
+    /// and then every `dur` after that.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `dur` is zero.
+    pub fn interval(self, dur: Duration) -> Box<IoFuture<Interval>> {
+        self.interval_at(Instant::now() + dur, dur)
+    }
+
+    /// Creates a new interval which first ticks at `start` and then every
+    /// `dur` after that.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `dur` is zero.
+    pub fn interval_at(self, start: Instant, dur: Duration)
+                       -> Box<IoFuture<Interval>> {
+        assert!(dur > Duration::new(0, 0), "interval period must be nonzero");
+        self.delay_at(start).map(move |delay| {
+            Interval {
+                delay: delay,
+                period: dur,
+                behavior: MissedTickBehavior::Burst,
+            }
+        }).boxed()
+    }
+}
+
+impl Interval {
+    /// Returns the period of this interval.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns what this interval does when ticks are missed.
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.behavior
+    }
+
+    /// Sets what this interval does when ticks are missed.
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.behavior = behavior;
+    }
+
+    /// Moves the next tick to one full period from now, with the ticks after
+    /// it following on from that.
+    pub fn reset(&mut self) {
+        let next = Instant::now() + self.period;
+        self.delay.reset(next);
+    }
+
+    fn next_tick(&self, tick: Instant, now: Instant) -> Instant {
+        let next = tick + self.period;
+        if next > now {
+            return next
+        }
+        match self.behavior {
+            MissedTickBehavior::Burst => next,
+            MissedTickBehavior::Delay => now + self.period,
+            MissedTickBehavior::Skip => {
+                // After a long enough stall the arithmetic here could
+                // overflow, in which case the next tick is as good as one
+                // period from now anyway.
+                let nanos = |d: Duration| {
+                    d.as_secs().checked_mul(1_000_000_000)
+                     .and_then(|n| n.checked_add(d.subsec_nanos() as u64))
+                };
+                let skip = nanos(now - tick).and_then(|behind| {
+                    let period = nanos(self.period).unwrap_or(u64::max_value());
+                    (behind / period + 1).checked_mul(period)
+                });
+                let next = skip.and_then(|n| {
+                    let skip = Duration::new(n / 1_000_000_000,
+                                             (n % 1_000_000_000) as u32);
+                    tick.checked_add(skip)
+                });
+                next.unwrap_or(now + self.period)
+            }
+        }
+    }
+}
+
+impl Stream for Interval {
+    type Item = Instant;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Instant>, io::Error> {
+        match self.delay.poll(task) {
+            Poll::Ok(()) => {}
+            Poll::NotReady => return Poll::NotReady,
+            Poll::Err(e) => return Poll::Err(e),
+        }
+        let tick = self.delay.deadline();
+        let next = self.next_tick(tick, Instant::now());
+        self.delay.reset(next);
+        Poll::Ok(Some(tick))
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.delay.schedule(task)
+    }
+}
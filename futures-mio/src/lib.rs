@@ -1,13 +1,15 @@
 //! Mio bindings with streams and futures
 //!
 //! This crate uses the `futures_io` and `futures` crates to provide a thin
-//! binding on top of mio of TCP and UDP sockets.
+//! binding on top of mio of TCP, UDP and Unix domain sockets.
 
 #![deny(missing_docs)]
 
 extern crate futures;
 extern crate futures_io;
+extern crate libc;
 extern crate mio;
+extern crate net2;
 extern crate slab;
 
 #[macro_use]
@@ -17,9 +19,24 @@ extern crate scoped_tls;
 extern crate log;
 
 mod readiness_stream;
+mod runtime;
+mod shutdown;
+mod delay;
+mod dns;
+#[cfg(unix)]
+mod signal;
 mod event_loop;
+mod happy_eyeballs;
+mod interval;
+#[cfg(unix)]
+mod process;
+mod splice;
+mod sockopt;
 mod tcp;
+mod tcp_builder;
 mod udp;
+#[cfg(unix)]
+mod unix;
 mod timeout;
 mod timer_wheel;
 #[path = "../../src/slot.rs"]
@@ -29,9 +46,22 @@ mod lock;
 mod mpsc_queue;
 mod channel;
 
-pub use event_loop::{Loop, LoopHandle, AddSource, AddTimeout};
+pub use event_loop::{Loop, LoopBuilder, LoopHandle, AddSource, AddTimeout};
 pub use event_loop::{LoopData, AddLoopData, TimeoutToken, IoSource, Source};
+pub use event_loop::Trigger;
+pub use delay::Delay;
+pub use dns::{Resolver, ResolverConfig};
+pub use interval::{Interval, MissedTickBehavior};
+#[cfg(unix)]
+pub use process::{Child, ChildStdin, ChildStdout, ChildStderr};
 pub use readiness_stream::ReadinessStream;
+pub use runtime::{Balance, Runtime, RuntimeHandle};
+#[cfg(unix)]
+pub use signal::SignalStream;
+pub use splice::{copy_splice, send_file, CopySplice, SendFile};
 pub use tcp::{TcpListener, TcpStream};
+pub use tcp_builder::TcpBuilder;
 pub use timeout::Timeout;
 pub use udp::UdpSocket;
+#[cfg(unix)]
+pub use unix::{UnixDatagram, UnixListener, UnixStream, UCred};
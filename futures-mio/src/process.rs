@@ -0,0 +1,292 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::prelude::*;
+use std::process::{self, Command, ExitStatus};
+use std::sync::Arc;
+
+use futures::stream::Stream;
+use futures::{Future, failed, finished, Poll, Task};
+use futures_io::{Ready, IoFuture};
+use libc;
+
+use {ReadinessStream, LoopHandle, SignalStream};
+use event_loop::Source;
+use unix::{self, Fd};
+
+/// A child process spawned on an event loop with `LoopHandle::spawn_command`.
+///
+/// The child is itself a future which resolves to its exit status once it
+/// exits. Waiting for the exit doesn't block any thread, instead the event
+/// loop is woken up by the `SIGCHLD` signal.
+///
+/// Any of the child's standard I/O streams which were configured with
+/// `Stdio::piped` are available as nonblocking pipes in the `stdin`, `stdout`
+/// and `stderr` fields. These can be taken out of the child and used with the
+/// combinators in `futures_io` while the exit status is being waited for.
+///
+/// Note that, like with `std::process::Child`, dropping a `Child` doesn't kill
+/// the process, nor does it reap the process once it exits.
+pub struct Child {
+    child: process::Child,
+    sigchld: SignalStream,
+
+    /// The handle for writing to the child's standard input, if it was piped.
+    pub stdin: Option<ChildStdin>,
+
+    /// The handle for reading from the child's standard output, if it was
+    /// piped.
+    pub stdout: Option<ChildStdout>,
+
+    /// The handle for reading from the child's standard error, if it was
+    /// piped.
+    pub stderr: Option<ChildStderr>,
+}
+
+/// A nonblocking handle to a child's standard input.
+///
+/// Dropping this handle closes the pipe, which the child will see as the end
+/// of its input.
+pub struct ChildStdin {
+    inner: Pipe,
+}
+
+/// A nonblocking handle to a child's standard output.
+pub struct ChildStdout {
+    inner: Pipe,
+}
+
+/// A nonblocking handle to a child's standard error.
+pub struct ChildStderr {
+    inner: Pipe,
+}
+
+struct Pipe {
+    source: Arc<Source<Fd<File>>>,
+    ready: ReadinessStream,
+}
+
+impl LoopHandle {
+    /// Spawns the command `cmd` as a child process whose exit and pipes are
+    /// managed by this event loop.
+    ///
+    /// The process itself is spawned immediately, just like with
+    /// `Command::spawn`, and the returned future resolves once its pipes have
+    /// been registered with the event loop.
+    pub fn spawn_command(self, cmd: &mut Command) -> Box<IoFuture<Child>> {
+        // Install our `SIGCHLD` handler first so there's no window where the
+        // child could exit without us hearing about it.
+        let sigchld = self.clone().signal(libc::SIGCHLD);
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => return failed(e).boxed(),
+        };
+
+        let stdin = Pipe::new(child.stdin.take(), self.clone());
+        let stdout = Pipe::new(child.stdout.take(), self.clone());
+        let stderr = Pipe::new(child.stderr.take(), self);
+        sigchld.join(stdin).join(stdout.join(stderr))
+               .then(move |res| {
+            match res {
+                Ok(((sigchld, stdin), (stdout, stderr))) => {
+                    Ok(Child {
+                        child: child,
+                        sigchld: sigchld,
+                        stdin: stdin.map(|p| ChildStdin { inner: p }),
+                        stdout: stdout.map(|p| ChildStdout { inner: p }),
+                        stderr: stderr.map(|p| ChildStderr { inner: p }),
+                    })
+                }
+                Err(e) => {
+                    // There's nothing to manage the child with, so don't
+                    // leave it running.
+                    drop(child.kill());
+                    drop(child.wait());
+                    Err(e)
+                }
+            }
+        }).boxed()
+    }
+}
+
+impl Child {
+    /// Returns the OS-assigned process identifier of the child.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Forces the child to exit by sending it `SIGKILL`.
+    ///
+    /// The exit status is still delivered through this future once the child
+    /// has actually exited.
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.child.kill()
+    }
+}
+
+impl Future for Child {
+    type Item = ExitStatus;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<ExitStatus, io::Error> {
+        loop {
+            // `SIGCHLD` may be for any child of this process, so each time
+            // one arrives we just check whether ours is done yet.
+            match self.child.try_wait() {
+                Ok(Some(status)) => return Poll::Ok(status),
+                Ok(None) => {}
+                Err(e) => return Poll::Err(e),
+            }
+            match self.sigchld.poll(task) {
+                Poll::Ok(Some(_)) => {}
+                Poll::Ok(None) => panic!("signal stream ended"),
+                Poll::NotReady => return Poll::NotReady,
+                Poll::Err(e) => return Poll::Err(e),
+            }
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.sigchld.schedule(task)
+    }
+}
+
+impl fmt::Debug for Child {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Child")
+         .field("id", &self.child.id())
+         .finish()
+    }
+}
+
+impl Pipe {
+    fn new<T>(io: Option<T>, handle: LoopHandle) -> Box<IoFuture<Option<Pipe>>>
+        where T: IntoRawFd,
+    {
+        let io = match io {
+            Some(io) => io,
+            None => return finished(None).boxed(),
+        };
+        let file = unsafe { File::from_raw_fd(io.into_raw_fd()) };
+        if let Err(e) = set_nonblocking(file.as_raw_fd()) {
+            return failed(e).boxed()
+        }
+        unix::register(file, handle, |source, ready| {
+            Some(Pipe {
+                source: source,
+                ready: ready,
+            })
+        })
+    }
+
+    fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        (&self.source.io().0).read(buf)
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        (&self.source.io().0).write(buf)
+    }
+}
+
+fn set_nonblocking(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 ||
+           libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+    }
+    Ok(())
+}
+
+impl Stream for Pipe {
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        self.ready.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.ready.schedule(task)
+    }
+}
+
+impl Write for ChildStdin {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for ChildStdout {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Read for ChildStderr {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Stream for ChildStdin {
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        self.inner.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.inner.schedule(task)
+    }
+}
+
+impl Stream for ChildStdout {
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        self.inner.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.inner.schedule(task)
+    }
+}
+
+impl Stream for ChildStderr {
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        self.inner.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.inner.schedule(task)
+    }
+}
+
+impl AsRawFd for ChildStdin {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.source.io().0.as_raw_fd()
+    }
+}
+
+impl AsRawFd for ChildStdout {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.source.io().0.as_raw_fd()
+    }
+}
+
+impl AsRawFd for ChildStderr {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.source.io().0.as_raw_fd()
+    }
+}
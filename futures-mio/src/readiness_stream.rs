@@ -13,12 +13,11 @@ use event_loop::{IoSource, LoopHandle, AddSource};
 /// associated with a specific event loop and source of events that will be
 /// registered with an event loop.
 ///
-/// Currently readiness streams have "edge" semantics. That is, if a stream
+/// By default readiness streams have "edge" semantics. That is, if a stream
 /// receives a readable notification it will not receive another readable
-/// notification until all bytes have been read from the stream.
-///
-/// Note that the precise semantics of when notifications are received will
-/// likely be configurable in the future.
+/// notification until all bytes have been read from the stream. Sources
+/// created with `Source::with_interest` can choose other semantics, as well
+/// as only being notified of some kinds of readiness.
 pub struct ReadinessStream {
     io_token: usize,
     loop_handle: LoopHandle,
@@ -45,6 +44,13 @@ impl ReadinessStream {
             handle: Some(loop_handle),
         }
     }
+
+    /// Changes which kinds of readiness this stream is notified of.
+    ///
+    /// See `LoopHandle::set_interest` for more details.
+    pub fn set_interest(&self, interest: Ready) {
+        self.loop_handle.set_interest(self.io_token, interest)
+    }
 }
 
 impl Future for ReadinessStreamNew {
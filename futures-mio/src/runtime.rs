@@ -0,0 +1,263 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::JoinHandle;
+
+use futures::stream::Stream;
+use futures::{Future, IntoFuture, Promise, finished};
+use futures_io::IoFuture;
+
+use {LoopBuilder, LoopHandle, TcpListener, TcpStream};
+use tcp;
+
+/// A pool of event loops, each running on its own thread.
+///
+/// A single event loop only ever uses one thread, so a runtime is a way to
+/// spread I/O over several threads. Futures can be spawned onto any of its
+/// loops through a `RuntimeHandle`, and connections accepted by one listener
+/// can be handed out among the loops with `RuntimeHandle::serve`.
+///
+/// Dropping a runtime shuts down all of its loops and waits for their threads
+/// to exit.
+pub struct Runtime {
+    handle: RuntimeHandle,
+    threads: Vec<JoinHandle<()>>,
+}
+
+/// A handle to the event loops of a `Runtime`, used to spawn work onto them.
+///
+/// Handles can be cloned and sent to other threads, and all clones refer to
+/// the same loops.
+#[derive(Clone)]
+pub struct RuntimeHandle {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    loops: Vec<Worker>,
+    next: AtomicUsize,
+}
+
+struct Worker {
+    handle: LoopHandle,
+    connections: AtomicUsize,
+}
+
+/// How `RuntimeHandle::serve` picks the loop which serves each connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Balance {
+    /// Hand connections to each loop in turn.
+    RoundRobin,
+
+    /// Hand each connection to the loop currently serving the fewest
+    /// connections.
+    LeastConnections,
+}
+
+impl Runtime {
+    /// Creates a new runtime of `loops` event loops with the default
+    /// configuration.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `loops` is zero.
+    pub fn new(loops: usize) -> io::Result<Runtime> {
+        Runtime::with_builder(loops, &LoopBuilder::new())
+    }
+
+    /// Creates a new runtime of `loops` event loops, each configured by
+    /// `builder`.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `loops` is zero.
+    pub fn with_builder(loops: usize, builder: &LoopBuilder)
+                        -> io::Result<Runtime> {
+        assert!(loops > 0, "a runtime needs at least one loop");
+        let mut workers = Vec::new();
+        let mut threads = Vec::new();
+        for _ in 0..loops {
+            match builder.spawn_thread() {
+                Ok((handle, thread)) => {
+                    workers.push(Worker {
+                        handle: handle,
+                        connections: AtomicUsize::new(0),
+                    });
+                    threads.push(thread);
+                }
+                Err(e) => {
+                    for (worker, thread) in workers.iter().zip(threads) {
+                        worker.handle.shutdown();
+                        drop(thread.join());
+                    }
+                    return Err(e)
+                }
+            }
+        }
+        Ok(Runtime {
+            handle: RuntimeHandle {
+                inner: Arc::new(Inner {
+                    loops: workers,
+                    next: AtomicUsize::new(0),
+                }),
+            },
+            threads: threads,
+        })
+    }
+
+    /// Returns a handle to the loops of this runtime.
+    pub fn handle(&self) -> &RuntimeHandle {
+        &self.handle
+    }
+
+    /// Shuts down each loop once all of the futures spawned onto it have
+    /// completed, and waits for their threads to exit.
+    ///
+    /// See `LoopHandle::shutdown_gracefully` for more details.
+    pub fn shutdown_gracefully(mut self) {
+        for worker in self.handle.inner.loops.iter() {
+            worker.handle.shutdown_gracefully();
+        }
+        for thread in self.threads.drain(..) {
+            drop(thread.join());
+        }
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        if self.threads.is_empty() {
+            return
+        }
+        for worker in self.handle.inner.loops.iter() {
+            worker.handle.shutdown();
+        }
+        for thread in self.threads.drain(..) {
+            drop(thread.join());
+        }
+    }
+}
+
+impl RuntimeHandle {
+    /// Returns the number of loops in the runtime.
+    pub fn loops(&self) -> usize {
+        self.inner.loops.len()
+    }
+
+    /// Returns a handle to the loop with index `idx`.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `idx` isn't less than `loops()`.
+    pub fn loop_handle(&self, idx: usize) -> &LoopHandle {
+        &self.inner.loops[idx].handle
+    }
+
+    /// Returns the number of connections handed to the loop with index `idx`
+    /// by `serve` which are still being served.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `idx` isn't less than `loops()`.
+    pub fn connections(&self, idx: usize) -> usize {
+        self.inner.loops[idx].connections.load(Ordering::SeqCst)
+    }
+
+    /// Spawns a future onto one of the runtime's loops, picking each loop in
+    /// turn, and returns a promise of its result.
+    ///
+    /// See `LoopHandle::spawn` for more details.
+    pub fn spawn<F>(&self, f: F) -> Promise<Result<F::Item, F::Error>>
+        where F: IntoFuture,
+    {
+        let idx = self.pick(Balance::RoundRobin);
+        self.spawn_on(idx, f)
+    }
+
+    /// Spawns a future onto the loop with index `idx`, returning a promise of
+    /// its result.
+    ///
+    /// See `LoopHandle::spawn` for more details.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `idx` isn't less than `loops()`.
+    pub fn spawn_on<F>(&self, idx: usize, f: F)
+                       -> Promise<Result<F::Item, F::Error>>
+        where F: IntoFuture,
+    {
+        self.inner.loops[idx].handle.spawn(f)
+    }
+
+    /// Returns a future which accepts connections on `listener` and hands each
+    /// one to a loop picked according to `balance`.
+    ///
+    /// Each connection is registered with the loop it was handed to, and then
+    /// `f` is called on that loop with the connection, the address of its
+    /// peer and a handle to the loop. The future returned by `f` is spawned
+    /// onto the loop, and the connection counts as being served until that
+    /// future completes. Errors from that future are ignored.
+    ///
+    /// The returned future itself only completes if accepting a connection
+    /// fails, or once the loop that `listener` is registered with starts
+    /// shutting down gracefully. It can be run on any loop, such as by
+    /// spawning it onto one of this runtime's loops.
+    pub fn serve<F, R>(&self, listener: TcpListener, balance: Balance, f: F)
+                       -> Box<IoFuture<()>>
+        where F: Fn(TcpStream, SocketAddr, LoopHandle) -> R + Send + Sync + 'static,
+              R: IntoFuture<Item=()>,
+    {
+        let runtime = self.clone();
+        let f = Arc::new(f);
+        tcp::accept(listener).for_each(move |(tcp, addr)| {
+            let idx = runtime.pick(balance);
+            let worker = &runtime.inner.loops[idx];
+            worker.connections.fetch_add(1, Ordering::SeqCst);
+
+            let runtime = runtime.clone();
+            let handle = worker.handle.clone();
+            let f = f.clone();
+            let registered = tcp::register_accepted(tcp, handle.clone());
+            let conn = registered.then(move |res| {
+                match res {
+                    Ok(stream) => {
+                        f(stream, addr, handle).into_future().then(|res| {
+                            if res.is_err() {
+                                debug!("error serving connection from {}", addr);
+                            }
+                            Ok::<(), ()>(())
+                        }).boxed()
+                    }
+                    Err(e) => {
+                        debug!("failed to register connection from {}: {}",
+                               addr, e);
+                        finished(()).boxed()
+                    }
+                }
+            }).then(move |res: Result<(), ()>| {
+                let worker = &runtime.inner.loops[idx];
+                worker.connections.fetch_sub(1, Ordering::SeqCst);
+                res
+            });
+            drop(worker.handle.spawn(conn));
+            Ok(())
+        }).boxed()
+    }
+
+    fn pick(&self, balance: Balance) -> usize {
+        let loops = &self.inner.loops;
+        let start = self.inner.next.fetch_add(1, Ordering::Relaxed);
+        match balance {
+            Balance::RoundRobin => start % loops.len(),
+            Balance::LeastConnections => {
+                // Start looking at a different loop each time so ties are
+                // still spread around.
+                let order = (0..loops.len()).map(|i| (start + i) % loops.len());
+                order.min_by_key(|&i| {
+                    loops[i].connections.load(Ordering::SeqCst)
+                }).unwrap()
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::io;
+
+use futures::stream::Stream;
+use futures::{Future, Task, Poll};
+use futures_io::IoFuture;
+
+use LoopHandle;
+
+/// A stream which ends early once the event loop starts shutting down
+/// gracefully, used to stop listeners from accepting new connections.
+pub struct UntilShutdown<S> {
+    stream: S,
+    signal: Option<Box<IoFuture<()>>>,
+}
+
+/// Wraps `stream` so that it ends once the event loop `handle` starts shutting
+/// down gracefully.
+pub fn until_shutdown<S>(stream: S, handle: &LoopHandle) -> UntilShutdown<S>
+    where S: Stream<Error=io::Error>,
+{
+    UntilShutdown {
+        stream: stream,
+        signal: Some(handle.shutdown_signal()),
+    }
+}
+
+impl<S> Stream for UntilShutdown<S>
+    where S: Stream<Error=io::Error>,
+{
+    type Item = S::Item;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<S::Item>, io::Error> {
+        let done = match self.signal {
+            Some(ref mut signal) => !signal.poll(task).is_not_ready(),
+            None => true,
+        };
+        if done {
+            debug!("loop is shutting down, ending stream");
+            self.signal = None;
+            return Poll::Ok(None)
+        }
+        self.stream.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        if let Some(ref mut signal) = self.signal {
+            signal.schedule(task);
+        }
+        self.stream.schedule(task)
+    }
+}
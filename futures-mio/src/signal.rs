@@ -0,0 +1,358 @@
+//! Unix signal handling for the event loop.
+//!
+//! Signals are delivered to a process-wide handler which records which signal
+//! was received and then writes a byte into a global "self pipe". Each
+//! `SignalStream` registers its own duplicate of the read end of that pipe
+//! with its event loop, so every loop in the process is woken up when any
+//! signal arrives. Whichever stream gets woken up first drains the pipe and
+//! passes the news along to every stream interested in the signals which were
+//! received, including those on other event loops.
+
+use std::cell::UnsafeCell;
+use std::io;
+use std::mem;
+use std::sync::{Arc, Mutex, Once};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::os::unix::prelude::*;
+
+use futures::stream::Stream;
+use futures::{Future, IntoFuture, failed, Poll, Task, TaskHandle};
+use futures_io::IoFuture;
+use libc;
+use mio;
+use mio::unix::EventedFd;
+
+use {ReadinessStream, LoopHandle};
+use event_loop::Source;
+
+/// A stream of deliveries of a Unix signal, created by `LoopHandle::signal`.
+///
+/// Each item of the stream is the number of the signal which was received.
+/// Multiple deliveries of a signal which happen before the stream is polled
+/// are coalesced into one item, but a delivery is never lost: after a signal
+/// arrives the stream will always yield at least one more item.
+///
+/// Any number of streams, on any number of event loops, can be created for
+/// the same signal and they're all notified of each delivery.
+pub struct SignalStream {
+    signum: libc::c_int,
+    recipient: Arc<Recipient>,
+    source: Arc<Source<Pipe>>,
+    ready: ReadinessStream,
+}
+
+struct Globals {
+    read: RawFd,
+    write: RawFd,
+    slots: Vec<Slot>,
+}
+
+struct Slot {
+    // Set by the signal handler when the signal is received, and cleared once
+    // the news has been passed on to all recipients.
+    pending: AtomicBool,
+    installed: Mutex<bool>,
+    // The action for the signal before ours was installed. This is only
+    // written, with `installed` locked, before our handler is installed and
+    // only read by our handler.
+    prev: UnsafeCell<libc::sigaction>,
+    recipients: Mutex<Vec<Arc<Recipient>>>,
+}
+
+unsafe impl Sync for Slot {}
+
+struct Recipient {
+    pending: AtomicBool,
+    task: Mutex<Option<TaskHandle>>,
+}
+
+// Our duplicate of the read end of the self pipe.
+struct Pipe(RawFd);
+
+// Covers the real time signals on Linux as well.
+const NSIG: usize = 65;
+
+static INIT: Once = Once::new();
+static mut GLOBALS: *const Globals = 0 as *const Globals;
+
+fn globals() -> io::Result<&'static Globals> {
+    static mut ERROR: Option<i32> = None;
+    unsafe {
+        INIT.call_once(|| {
+            match new_globals() {
+                Ok(g) => GLOBALS = Box::into_raw(Box::new(g)),
+                Err(e) => ERROR = e.raw_os_error().or(Some(libc::EINVAL)),
+            }
+        });
+        if GLOBALS.is_null() {
+            Err(io::Error::from_raw_os_error(ERROR.unwrap_or(libc::EINVAL)))
+        } else {
+            Ok(&*GLOBALS)
+        }
+    }
+}
+
+fn new_globals() -> io::Result<Globals> {
+    let mut fds = [0; 2];
+    unsafe {
+        if libc::pipe(fds.as_mut_ptr()) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+    }
+    for &fd in fds.iter() {
+        try!(set_nonblocking_cloexec(fd));
+    }
+    Ok(Globals {
+        read: fds[0],
+        write: fds[1],
+        slots: (0..NSIG).map(|_| {
+            Slot {
+                pending: AtomicBool::new(false),
+                installed: Mutex::new(false),
+                prev: UnsafeCell::new(unsafe { mem::zeroed() }),
+                recipients: Mutex::new(Vec::new()),
+            }
+        }).collect(),
+    })
+}
+
+fn set_nonblocking_cloexec(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 ||
+           libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        if flags < 0 ||
+           libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+    }
+    Ok(())
+}
+
+// Only async-signal-safe operations are allowed in here, so all we do is set
+// a flag, poke the self pipe, and call any handler which was installed before
+// ours.
+extern fn handler(signum: libc::c_int,
+                  info: *mut libc::siginfo_t,
+                  ctx: *mut libc::c_void) {
+    unsafe {
+        let globals = &*GLOBALS;
+        let slot = &globals.slots[signum as usize];
+        slot.pending.store(true, Ordering::SeqCst);
+
+        // If the pipe is full then a wakeup is already pending, so it's fine
+        // for this write to fail.
+        let byte = 1u8;
+        libc::write(globals.write, &byte as *const u8 as *const libc::c_void, 1);
+
+        let prev = &*slot.prev.get();
+        if prev.sa_sigaction == libc::SIG_DFL ||
+           prev.sa_sigaction == libc::SIG_IGN {
+            return
+        }
+        if prev.sa_flags & libc::SA_SIGINFO != 0 {
+            let f: extern fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) =
+                mem::transmute(prev.sa_sigaction);
+            f(signum, info, ctx);
+        } else {
+            let f: extern fn(libc::c_int) = mem::transmute(prev.sa_sigaction);
+            f(signum);
+        }
+    }
+}
+
+fn install(signum: libc::c_int, globals: &Globals) -> io::Result<()> {
+    if signum <= 0 || signum as usize >= NSIG ||
+       FORBIDDEN.contains(&signum) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  "invalid signal number"))
+    }
+    let slot = &globals.slots[signum as usize];
+    let mut installed = slot.installed.lock().unwrap();
+    if *installed {
+        return Ok(())
+    }
+    unsafe {
+        let mut new: libc::sigaction = mem::zeroed();
+        new.sa_sigaction = handler as usize;
+        new.sa_flags = libc::SA_RESTART | libc::SA_SIGINFO;
+        libc::sigemptyset(&mut new.sa_mask);
+        if libc::sigaction(signum, &new, slot.prev.get()) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+    }
+    *installed = true;
+    Ok(())
+}
+
+const FORBIDDEN: &'static [libc::c_int] = &[
+    libc::SIGILL,
+    libc::SIGFPE,
+    libc::SIGKILL,
+    libc::SIGSEGV,
+    libc::SIGSTOP,
+];
+
+// Drains the self pipe and hands out any signals which have been received to
+// everyone listening for them.
+fn broadcast(globals: &Globals) {
+    let mut buf = [0u8; 128];
+    loop {
+        let r = unsafe {
+            libc::read(globals.read,
+                       buf.as_mut_ptr() as *mut libc::c_void,
+                       buf.len())
+        };
+        if r <= 0 {
+            break
+        }
+    }
+
+    // Tasks are only notified once all locks are released, as notifying may
+    // run the task right here and it'll want to take those locks itself.
+    let mut to_wake = Vec::new();
+    for slot in globals.slots.iter() {
+        if !slot.pending.swap(false, Ordering::SeqCst) {
+            continue
+        }
+        for recipient in slot.recipients.lock().unwrap().iter() {
+            recipient.pending.store(true, Ordering::SeqCst);
+            let task = recipient.task.lock().unwrap().take();
+            if let Some(task) = task {
+                to_wake.push(task);
+            }
+        }
+    }
+    for task in to_wake {
+        task.notify();
+    }
+}
+
+impl LoopHandle {
+    /// Creates a new stream of deliveries of the signal `signum`, such as
+    /// `libc::SIGTERM` or `libc::SIGHUP`.
+    ///
+    /// The first time a stream is created for a signal a process-wide handler
+    /// is installed for it, which stays installed for the rest of the life of
+    /// the process. Handlers which were installed beforehand are still called
+    /// when the signal arrives.
+    ///
+    /// This returns an error for signals which can't be handled (`SIGKILL` and
+    /// `SIGSTOP`) or which indicate a fault in the program (such as
+    /// `SIGSEGV`).
+    pub fn signal(self, signum: libc::c_int) -> Box<IoFuture<SignalStream>> {
+        let globals = match globals() {
+            Ok(g) => g,
+            Err(e) => return failed(e).boxed(),
+        };
+        if let Err(e) = install(signum, globals) {
+            return failed(e).boxed()
+        }
+        let fd = unsafe { libc::dup(globals.read) };
+        if fd < 0 {
+            return failed(io::Error::last_os_error()).boxed()
+        }
+        let pipe = Pipe(fd);
+        set_nonblocking_cloexec(fd).into_future().and_then(move |()| {
+            let source = Arc::new(Source::new(pipe));
+            ReadinessStream::new(self, source.clone()).map(move |ready| {
+                let recipient = Arc::new(Recipient {
+                    pending: AtomicBool::new(false),
+                    task: Mutex::new(None),
+                });
+                globals.slots[signum as usize].recipients.lock().unwrap()
+                       .push(recipient.clone());
+                SignalStream {
+                    signum: signum,
+                    recipient: recipient,
+                    source: source,
+                    ready: ready,
+                }
+            })
+        }).boxed()
+    }
+}
+
+impl SignalStream {
+    /// Returns the number of the signal this stream is for.
+    pub fn signum(&self) -> libc::c_int {
+        self.signum
+    }
+}
+
+impl Stream for SignalStream {
+    type Item = libc::c_int;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<libc::c_int>, io::Error> {
+        // Readiness of the pipe only tells us to go look at what's pending,
+        // and another stream may have already done that for us, so we always
+        // check.
+        if let Poll::Err(e) = self.ready.poll(task) {
+            return Poll::Err(e)
+        }
+        broadcast(unsafe { &*GLOBALS });
+        if self.recipient.pending.swap(false, Ordering::SeqCst) {
+            Poll::Ok(Some(self.signum))
+        } else {
+            Poll::NotReady
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        *self.recipient.task.lock().unwrap() = Some(task.handle().clone());
+        if self.recipient.pending.load(Ordering::SeqCst) {
+            return task.notify()
+        }
+        self.ready.schedule(task)
+    }
+}
+
+impl Drop for SignalStream {
+    fn drop(&mut self) {
+        let globals = unsafe { &*GLOBALS };
+        let slot = &globals.slots[self.signum as usize];
+        slot.recipients.lock().unwrap().retain(|r| {
+            !Arc::ptr_eq(r, &self.recipient)
+        });
+    }
+}
+
+impl AsRawFd for SignalStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.source.io().0
+    }
+}
+
+impl mio::Evented for Pipe {
+    fn register(&self,
+                poll: &mio::Poll,
+                token: mio::Token,
+                interest: mio::EventSet,
+                opts: mio::PollOpt) -> io::Result<()> {
+        EventedFd(&self.0).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self,
+                  poll: &mio::Poll,
+                  token: mio::Token,
+                  interest: mio::EventSet,
+                  opts: mio::PollOpt) -> io::Result<()> {
+        EventedFd(&self.0).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
+        EventedFd(&self.0).deregister(poll)
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
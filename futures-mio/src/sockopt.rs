@@ -0,0 +1,199 @@
+//! Getting and setting socket options which aren't exposed by mio or net2.
+
+pub use self::sys::*;
+
+#[cfg(unix)]
+mod sys {
+    use std::io;
+    use std::mem;
+    use std::os::unix::prelude::*;
+    use std::time::Duration;
+
+    use libc::{self, c_int, c_void, socklen_t};
+
+    fn set<T>(fd: RawFd, level: c_int, name: c_int, val: T) -> io::Result<()> {
+        let r = unsafe {
+            libc::setsockopt(fd,
+                             level,
+                             name,
+                             &val as *const T as *const c_void,
+                             mem::size_of::<T>() as socklen_t)
+        };
+        if r < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn get<T: Copy>(fd: RawFd, level: c_int, name: c_int) -> io::Result<T> {
+        unsafe {
+            let mut val: T = mem::zeroed();
+            let mut len = mem::size_of::<T>() as socklen_t;
+            let r = libc::getsockopt(fd,
+                                     level,
+                                     name,
+                                     &mut val as *mut T as *mut c_void,
+                                     &mut len);
+            if r < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(val)
+            }
+        }
+    }
+
+    fn clamp(n: u64) -> c_int {
+        if n > c_int::max_value() as u64 {
+            c_int::max_value()
+        } else {
+            n as c_int
+        }
+    }
+
+    pub fn set_send_buffer_size(fd: &AsRawFd, size: usize) -> io::Result<()> {
+        set(fd.as_raw_fd(), libc::SOL_SOCKET, libc::SO_SNDBUF,
+            clamp(size as u64))
+    }
+
+    pub fn send_buffer_size(fd: &AsRawFd) -> io::Result<usize> {
+        get::<c_int>(fd.as_raw_fd(), libc::SOL_SOCKET, libc::SO_SNDBUF)
+            .map(|s| s as usize)
+    }
+
+    pub fn set_recv_buffer_size(fd: &AsRawFd, size: usize) -> io::Result<()> {
+        set(fd.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF,
+            clamp(size as u64))
+    }
+
+    pub fn recv_buffer_size(fd: &AsRawFd) -> io::Result<usize> {
+        get::<c_int>(fd.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF)
+            .map(|s| s as usize)
+    }
+
+    pub fn set_linger(fd: &AsRawFd, dur: Option<Duration>) -> io::Result<()> {
+        let linger = libc::linger {
+            l_onoff: dur.is_some() as c_int,
+            // A linger time of 0 means to reset the connection rather than
+            // close it gracefully, so round partial seconds up.
+            l_linger: dur.map(|d| {
+                if d.subsec_nanos() > 0 {
+                    clamp(d.as_secs().saturating_add(1))
+                } else {
+                    clamp(d.as_secs())
+                }
+            }).unwrap_or(0),
+        };
+        set(fd.as_raw_fd(), libc::SOL_SOCKET, libc::SO_LINGER, linger)
+    }
+
+    pub fn linger(fd: &AsRawFd) -> io::Result<Option<Duration>> {
+        let linger = try!(get::<libc::linger>(fd.as_raw_fd(),
+                                              libc::SOL_SOCKET,
+                                              libc::SO_LINGER));
+        if linger.l_onoff == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(Duration::from_secs(linger.l_linger as u64)))
+        }
+    }
+
+    pub fn set_ttl(fd: &AsRawFd, ttl: u32) -> io::Result<()> {
+        set(fd.as_raw_fd(), libc::IPPROTO_IP, libc::IP_TTL, ttl as c_int)
+    }
+
+    pub fn ttl(fd: &AsRawFd) -> io::Result<u32> {
+        get::<c_int>(fd.as_raw_fd(), libc::IPPROTO_IP, libc::IP_TTL)
+            .map(|t| t as u32)
+    }
+
+    pub fn only_v6(fd: &AsRawFd) -> io::Result<bool> {
+        get::<c_int>(fd.as_raw_fd(), libc::IPPROTO_IPV6, libc::IPV6_V6ONLY)
+            .map(|v| v != 0)
+    }
+
+    pub fn nodelay(fd: &AsRawFd) -> io::Result<bool> {
+        get::<c_int>(fd.as_raw_fd(), libc::IPPROTO_TCP, libc::TCP_NODELAY)
+            .map(|v| v != 0)
+    }
+
+    pub fn set_reuse_port(fd: &AsRawFd, reuse: bool) -> io::Result<()> {
+        set(fd.as_raw_fd(), libc::SOL_SOCKET, libc::SO_REUSEPORT,
+            reuse as c_int)
+    }
+
+    pub fn reuse_port(fd: &AsRawFd) -> io::Result<bool> {
+        get::<c_int>(fd.as_raw_fd(), libc::SOL_SOCKET, libc::SO_REUSEPORT)
+            .map(|v| v != 0)
+    }
+
+    pub fn reuse_address(fd: &AsRawFd) -> io::Result<bool> {
+        get::<c_int>(fd.as_raw_fd(), libc::SOL_SOCKET, libc::SO_REUSEADDR)
+            .map(|v| v != 0)
+    }
+}
+
+// TODO: mio doesn't expose the raw sockets on Windows yet, so these options
+//       can't be reached there.
+#[cfg(windows)]
+mod sys {
+    use std::io;
+    use std::time::Duration;
+
+    fn unsupported<T>() -> io::Result<T> {
+        Err(io::Error::new(io::ErrorKind::Other,
+                           "socket option not supported on this platform"))
+    }
+
+    pub fn set_send_buffer_size<T>(_: &T, _: usize) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn send_buffer_size<T>(_: &T) -> io::Result<usize> {
+        unsupported()
+    }
+
+    pub fn set_recv_buffer_size<T>(_: &T, _: usize) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn recv_buffer_size<T>(_: &T) -> io::Result<usize> {
+        unsupported()
+    }
+
+    pub fn set_linger<T>(_: &T, _: Option<Duration>) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn linger<T>(_: &T) -> io::Result<Option<Duration>> {
+        unsupported()
+    }
+
+    pub fn set_ttl<T>(_: &T, _: u32) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn ttl<T>(_: &T) -> io::Result<u32> {
+        unsupported()
+    }
+
+    pub fn only_v6<T>(_: &T) -> io::Result<bool> {
+        unsupported()
+    }
+
+    pub fn nodelay<T>(_: &T) -> io::Result<bool> {
+        unsupported()
+    }
+
+    pub fn set_reuse_port<T>(_: &T, _: bool) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn reuse_port<T>(_: &T) -> io::Result<bool> {
+        unsupported()
+    }
+
+    pub fn reuse_address<T>(_: &T) -> io::Result<bool> {
+        unsupported()
+    }
+}
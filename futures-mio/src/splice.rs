@@ -0,0 +1,429 @@
+//! Zero-copy transfers between sockets and files.
+//!
+//! On Linux these are implemented with `splice(2)` and `sendfile(2)` so data
+//! never has to be copied into userspace. On other platforms they fall back to
+//! the buffered `futures_io::copy`.
+
+use std::fs::File;
+use std::io;
+
+use futures::{Future, Poll, Task};
+
+use TcpStream;
+
+/// A future which copies all data from one TCP stream into another.
+///
+/// Created by the `copy_splice` function, this future will resolve to the
+/// number of bytes copied or an error if one happens.
+pub struct CopySplice {
+    inner: imp::CopySplice,
+}
+
+/// A future which writes the contents of a file to a TCP stream.
+///
+/// Created by the `send_file` function, this future will resolve to the
+/// number of bytes written or an error if one happens.
+pub struct SendFile {
+    inner: imp::SendFile,
+}
+
+/// Creates a future which copies all bytes read from `reader` into `writer`.
+///
+/// This is similar to `futures_io::copy` except that on Linux the data is
+/// moved between the two sockets with `splice(2)` through an intermediate
+/// pipe, so it never needs to be copied into userspace. On other platforms
+/// this is the same as `futures_io::copy`.
+///
+/// The returned future only completes once `reader` has hit EOF and all bytes
+/// have been written to `writer`. Both streams are consumed.
+pub fn copy_splice(reader: TcpStream, writer: TcpStream) -> CopySplice {
+    CopySplice { inner: imp::CopySplice::new(reader, writer) }
+}
+
+/// Creates a future which writes the contents of `file`, starting at its
+/// current position, to `writer`.
+///
+/// On Linux this is implemented with `sendfile(2)` so the contents of the file
+/// never need to be copied into userspace. On other platforms the file is
+/// read into a buffer and then written out with `futures_io::copy`, and note
+/// that in that case reading the file may block the event loop.
+///
+/// The returned future completes once the end of the file is reached and all
+/// of its contents have been written to `writer`.
+pub fn send_file(file: File, writer: TcpStream) -> SendFile {
+    SendFile { inner: imp::SendFile::new(file, writer) }
+}
+
+impl Future for CopySplice {
+    type Item = u64;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<u64, io::Error> {
+        self.inner.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.inner.schedule(task)
+    }
+}
+
+impl Future for SendFile {
+    type Item = u64;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<u64, io::Error> {
+        self.inner.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.inner.schedule(task)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::prelude::*;
+    use std::ptr;
+
+    use futures::{Future, Poll, Task};
+    use futures::stream::Stream;
+    use futures_io::Ready;
+    use libc;
+
+    use TcpStream;
+
+    // The default capacity of a pipe on Linux, we never splice more than this
+    // into the pipe so splicing from the reader only blocks when the reader
+    // itself has no data.
+    const PIPE_SIZE: usize = 64 * 1024;
+
+    // The most that Linux will transfer in one call to `sendfile`.
+    const MAX_SENDFILE: usize = 0x7fff_f000;
+
+    pub struct CopySplice {
+        reader: TcpStream,
+        read_ready: bool,
+        read_done: bool,
+        writer: TcpStream,
+        write_ready: bool,
+        pipe: Option<Pipe>,
+        buffered: usize,
+        amt: u64,
+    }
+
+    pub struct SendFile {
+        file: File,
+        writer: TcpStream,
+        write_ready: bool,
+        amt: u64,
+    }
+
+    struct Pipe {
+        rd: RawFd,
+        wr: RawFd,
+    }
+
+    impl Pipe {
+        fn new() -> io::Result<Pipe> {
+            let mut fds = [0; 2];
+            let flags = libc::O_NONBLOCK | libc::O_CLOEXEC;
+            if unsafe { libc::pipe2(fds.as_mut_ptr(), flags) } < 0 {
+                return Err(io::Error::last_os_error())
+            }
+            Ok(Pipe { rd: fds[0], wr: fds[1] })
+        }
+    }
+
+    impl Drop for Pipe {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.rd);
+                libc::close(self.wr);
+            }
+        }
+    }
+
+    fn splice(from: RawFd, to: RawFd, len: usize) -> io::Result<usize> {
+        let flags = libc::SPLICE_F_MOVE | libc::SPLICE_F_NONBLOCK;
+        let r = unsafe {
+            libc::splice(from, ptr::null_mut(), to, ptr::null_mut(), len, flags)
+        };
+        if r < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(r as usize)
+        }
+    }
+
+    fn sendfile(to: RawFd, from: RawFd, len: usize) -> io::Result<usize> {
+        let r = unsafe { libc::sendfile(to, from, ptr::null_mut(), len) };
+        if r < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(r as usize)
+        }
+    }
+
+    // Polls `stream` for readiness, returning whether the readiness `want`ed
+    // was received.
+    fn poll_ready(stream: &mut TcpStream,
+                  task: &mut Task,
+                  want: fn(&Ready) -> bool) -> io::Result<bool> {
+        match stream.poll(task) {
+            Poll::Ok(Some(ref r)) => Ok(want(r)),
+            Poll::Ok(None) => Ok(true),
+            Poll::NotReady => Ok(false),
+            Poll::Err(e) => Err(e),
+        }
+    }
+
+    fn zero_write() -> io::Error {
+        io::Error::new(io::ErrorKind::WriteZero, "zero-length write")
+    }
+
+    impl CopySplice {
+        pub fn new(reader: TcpStream, writer: TcpStream) -> CopySplice {
+            CopySplice {
+                reader: reader,
+                read_ready: true,
+                read_done: false,
+                writer: writer,
+                write_ready: true,
+                pipe: None,
+                buffered: 0,
+                amt: 0,
+            }
+        }
+
+        fn can_read(&self) -> bool {
+            !self.read_done && self.buffered < PIPE_SIZE
+        }
+    }
+
+    impl Future for CopySplice {
+        type Item = u64;
+        type Error = io::Error;
+
+        fn poll(&mut self, task: &mut Task) -> Poll<u64, io::Error> {
+            if self.pipe.is_none() {
+                match Pipe::new() {
+                    Ok(pipe) => self.pipe = Some(pipe),
+                    Err(e) => return Poll::Err(e),
+                }
+            }
+            let (rd, wr) = {
+                let pipe = self.pipe.as_ref().unwrap();
+                (pipe.rd, pipe.wr)
+            };
+
+            loop {
+                let mut progress = false;
+
+                // Move as much as we can from the reader into our pipe.
+                if self.can_read() && !self.read_ready {
+                    match poll_ready(&mut self.reader, task, Ready::is_read) {
+                        Ok(ready) => self.read_ready = ready,
+                        Err(e) => return Poll::Err(e),
+                    }
+                }
+                if self.can_read() && self.read_ready {
+                    let fd = self.reader.as_raw_fd();
+                    match splice(fd, wr, PIPE_SIZE - self.buffered) {
+                        Ok(0) => {
+                            trace!("splice at eof");
+                            self.read_done = true;
+                            progress = true;
+                        }
+                        Ok(n) => {
+                            trace!("spliced {} bytes into pipe", n);
+                            self.buffered += n;
+                            progress = true;
+                        }
+                        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            self.read_ready = false;
+                        }
+                        Err(e) => return Poll::Err(e),
+                    }
+                }
+
+                // And then move everything in our pipe out to the writer.
+                if self.buffered > 0 && !self.write_ready {
+                    match poll_ready(&mut self.writer, task, Ready::is_write) {
+                        Ok(ready) => self.write_ready = ready,
+                        Err(e) => return Poll::Err(e),
+                    }
+                }
+                if self.buffered > 0 && self.write_ready {
+                    let fd = self.writer.as_raw_fd();
+                    match splice(rd, fd, self.buffered) {
+                        Ok(0) => return Poll::Err(zero_write()),
+                        Ok(n) => {
+                            trace!("spliced {} bytes out of pipe", n);
+                            self.buffered -= n;
+                            self.amt += n as u64;
+                            progress = true;
+                        }
+                        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            self.write_ready = false;
+                        }
+                        Err(e) => return Poll::Err(e),
+                    }
+                }
+
+                if self.read_done && self.buffered == 0 {
+                    return Poll::Ok(self.amt)
+                }
+                if !progress {
+                    return Poll::NotReady
+                }
+            }
+        }
+
+        fn schedule(&mut self, task: &mut Task) {
+            let can_read = self.can_read();
+            let can_write = self.buffered > 0;
+            if self.pipe.is_none() ||
+               (can_read && self.read_ready) ||
+               (can_write && self.write_ready) ||
+               (!can_read && !can_write) {
+                return task.notify()
+            }
+            if can_read {
+                self.reader.schedule(task);
+            }
+            if can_write {
+                self.writer.schedule(task);
+            }
+        }
+    }
+
+    impl SendFile {
+        pub fn new(file: File, writer: TcpStream) -> SendFile {
+            SendFile {
+                file: file,
+                writer: writer,
+                write_ready: true,
+                amt: 0,
+            }
+        }
+    }
+
+    impl Future for SendFile {
+        type Item = u64;
+        type Error = io::Error;
+
+        fn poll(&mut self, task: &mut Task) -> Poll<u64, io::Error> {
+            loop {
+                if !self.write_ready {
+                    match poll_ready(&mut self.writer, task, Ready::is_write) {
+                        Ok(true) => self.write_ready = true,
+                        Ok(false) => return Poll::NotReady,
+                        Err(e) => return Poll::Err(e),
+                    }
+                }
+                let fd = self.writer.as_raw_fd();
+                match sendfile(fd, self.file.as_raw_fd(), MAX_SENDFILE) {
+                    Ok(0) => return Poll::Ok(self.amt),
+                    Ok(n) => {
+                        trace!("sent {} bytes of file", n);
+                        self.amt += n as u64;
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        self.write_ready = false;
+                    }
+                    Err(e) => return Poll::Err(e),
+                }
+            }
+        }
+
+        fn schedule(&mut self, task: &mut Task) {
+            if self.write_ready {
+                task.notify()
+            } else {
+                self.writer.schedule(task)
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use std::fs::File;
+    use std::io::{self, Read};
+
+    use futures::{Future, Poll, Task};
+    use futures::stream::Stream;
+    use futures_io::{self, Copy, Ready};
+
+    use TcpStream;
+
+    pub struct CopySplice {
+        inner: Copy<TcpStream, TcpStream>,
+    }
+
+    pub struct SendFile {
+        inner: Copy<BlockingFile, TcpStream>,
+    }
+
+    // Files are always "ready", even though reading them may block.
+    struct BlockingFile(File);
+
+    impl CopySplice {
+        pub fn new(reader: TcpStream, writer: TcpStream) -> CopySplice {
+            CopySplice { inner: futures_io::copy(reader, writer) }
+        }
+    }
+
+    impl Future for CopySplice {
+        type Item = u64;
+        type Error = io::Error;
+
+        fn poll(&mut self, task: &mut Task) -> Poll<u64, io::Error> {
+            self.inner.poll(task)
+        }
+
+        fn schedule(&mut self, task: &mut Task) {
+            self.inner.schedule(task)
+        }
+    }
+
+    impl SendFile {
+        pub fn new(file: File, writer: TcpStream) -> SendFile {
+            SendFile { inner: futures_io::copy(BlockingFile(file), writer) }
+        }
+    }
+
+    impl Future for SendFile {
+        type Item = u64;
+        type Error = io::Error;
+
+        fn poll(&mut self, task: &mut Task) -> Poll<u64, io::Error> {
+            self.inner.poll(task)
+        }
+
+        fn schedule(&mut self, task: &mut Task) {
+            self.inner.schedule(task)
+        }
+    }
+
+    impl Read for BlockingFile {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Stream for BlockingFile {
+        type Item = Ready;
+        type Error = io::Error;
+
+        fn poll(&mut self, _task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+            Poll::Ok(Some(Ready::Read))
+        }
+
+        fn schedule(&mut self, task: &mut Task) {
+            task.notify()
+        }
+    }
+}
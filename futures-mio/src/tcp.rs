@@ -1,16 +1,19 @@
 use std::fmt;
-use std::io::{self, ErrorKind, Read, Write};
+use std::io::{self, ErrorKind, IoSlice, IoSliceMut, Read, Write};
 use std::mem;
 use std::net::{self, SocketAddr, Shutdown};
 use std::sync::Arc;
+use std::time::Duration;
 
 use futures::stream::{self, Stream};
 use futures::{Future, IntoFuture, failed, Task, Poll};
-use futures_io::{Ready, IoFuture, IoStream};
+use futures_io::{Ready, IoFuture, IoStream, ShutdownWrite};
 use mio;
 
 use {ReadinessStream, LoopHandle};
 use event_loop::Source;
+use sockopt;
+use shutdown::until_shutdown;
 
 /// An I/O object representing a TCP socket listening for incoming connections.
 ///
@@ -41,10 +44,11 @@ impl TcpListener {
     /// sufficient because perhaps some more configuration is needed in terms of
     /// before the calls to `bind` and `listen`.
     ///
-    /// This API is typically paired with the `net2` crate and the `TcpBuilder`
-    /// type to build up and customize a listener before it's shipped off to the
-    /// backing event loop. This allows configuration of options like
-    /// `SO_REUSEPORT`, binding to multiple addresses, etc.
+    /// Most options, like `SO_REUSEPORT`, can be configured with the
+    /// `TcpBuilder` returned from `LoopHandle::tcp_builder`. This API can be
+    /// paired with the `net2` crate's `TcpBuilder` type for anything else, to
+    /// build up and customize a listener before it's shipped off to the
+    /// backing event loop.
     ///
     /// The `addr` argument here is one of the addresses that `listener` is
     /// bound to and the listener will only be guaranteed to accept connections
@@ -79,33 +83,72 @@ impl TcpListener {
         self.listener.io().local_addr()
     }
 
+    /// Sets the value of the `IP_TTL` option on this socket.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        sockopt::set_ttl(self.listener.io(), ttl)
+    }
+
+    /// Gets the value of the `IP_TTL` option on this socket.
+    pub fn ttl(&self) -> io::Result<u32> {
+        sockopt::ttl(self.listener.io())
+    }
+
+    /// Gets the value of the `IPV6_V6ONLY` option on this socket.
+    pub fn only_v6(&self) -> io::Result<bool> {
+        sockopt::only_v6(self.listener.io())
+    }
+
+    /// Gets the value of the `SO_REUSEADDR` option on this socket.
+    pub fn reuse_address(&self) -> io::Result<bool> {
+        sockopt::reuse_address(self.listener.io())
+    }
+
+    /// Gets the value of the `SO_REUSEPORT` option on this socket.
+    pub fn reuse_port(&self) -> io::Result<bool> {
+        sockopt::reuse_port(self.listener.io())
+    }
+
     /// Consumes this listener, returning a stream of the sockets this listener
     /// accepts.
     ///
     /// This method returns an implementation of the `Stream` trait which
     /// resolves to the sockets the are accepted on this listener.
+    ///
+    /// The stream ends once the event loop starts shutting down gracefully,
+    /// see `LoopHandle::shutdown_gracefully`.
     pub fn incoming(self) -> Box<IoStream<(TcpStream, SocketAddr)>> {
-        let TcpListener { loop_handle, listener, ready } = self;
-
-        ready
-            .map(move |_| {
-                stream::iter(NonblockingIter { source: listener.clone() }.fuse())
+        let loop_handle = self.loop_handle.clone();
+        accept(self).and_then(move |(tcp, addr)| {
+            register_accepted(tcp, loop_handle.clone()).map(move |stream| {
+                (stream, addr)
             })
-            .flatten()
-            .and_then(move |(tcp, addr)| {
-                let tcp = Arc::new(Source::new(tcp));
-                ReadinessStream::new(loop_handle.clone(),
-                                     tcp.clone()).map(move |ready| {
-                    let stream = TcpStream {
-                        source: tcp,
-                        ready: ready,
-                    };
-                    (stream, addr)
-                })
-            }).boxed()
+        }).boxed()
     }
 }
 
+/// Returns a stream of the sockets accepted by `listener` which, unlike with
+/// `TcpListener::incoming`, haven't been registered with any event loop yet.
+pub fn accept(listener: TcpListener)
+              -> Box<IoStream<(mio::tcp::TcpStream, SocketAddr)>> {
+    let TcpListener { loop_handle, listener, ready } = listener;
+    let accepted = ready.map(move |_| {
+        stream::iter(NonblockingIter { source: listener.clone() }.fuse())
+    }).flatten();
+    until_shutdown(accepted, &loop_handle).boxed()
+}
+
+/// Registers a socket returned by `accept` with the event loop `handle`.
+pub fn register_accepted(tcp: mio::tcp::TcpStream,
+                         handle: LoopHandle) -> Box<IoFuture<TcpStream>> {
+    let tcp = Arc::new(Source::new(tcp));
+    ReadinessStream::new(handle, tcp.clone()).map(move |ready| {
+        TcpStream {
+            source: tcp,
+            ready: ready,
+        }
+    }).boxed()
+}
+
 struct NonblockingIter {
     source: Arc<Source<mio::tcp::TcpListener>>,
 }
@@ -267,10 +310,71 @@ impl TcpStream {
         self.source.io().set_nodelay(nodelay)
     }
 
+    /// Gets the value of the `TCP_NODELAY` option on this socket.
+    pub fn nodelay(&self) -> io::Result<bool> {
+        sockopt::nodelay(self.source.io())
+    }
+
     /// Sets the keepalive time in seconds for this socket.
     pub fn set_keepalive_s(&self, seconds: Option<u32>) -> io::Result<()> {
         self.source.io().set_keepalive(seconds)
     }
+
+    /// Sets the value of the `SO_SNDBUF` option on this socket.
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        sockopt::set_send_buffer_size(self.source.io(), size)
+    }
+
+    /// Gets the value of the `SO_SNDBUF` option on this socket.
+    ///
+    /// Note that the operating system may have adjusted the size that was
+    /// set, for example Linux doubles it.
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        sockopt::send_buffer_size(self.source.io())
+    }
+
+    /// Sets the value of the `SO_RCVBUF` option on this socket.
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        sockopt::set_recv_buffer_size(self.source.io(), size)
+    }
+
+    /// Gets the value of the `SO_RCVBUF` option on this socket.
+    ///
+    /// Note that the operating system may have adjusted the size that was
+    /// set, for example Linux doubles it.
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        sockopt::recv_buffer_size(self.source.io())
+    }
+
+    /// Sets the value of the `SO_LINGER` option on this socket.
+    ///
+    /// If `dur` is `Some` then closing the socket blocks for up to `dur`
+    /// while unsent data is sent, and otherwise closing it returns
+    /// immediately. The operating system only supports whole seconds here, so
+    /// `dur` is rounded up to the next second.
+    pub fn set_linger(&self, dur: Option<Duration>) -> io::Result<()> {
+        sockopt::set_linger(self.source.io(), dur)
+    }
+
+    /// Gets the value of the `SO_LINGER` option on this socket.
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        sockopt::linger(self.source.io())
+    }
+
+    /// Sets the value of the `IP_TTL` option on this socket.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        sockopt::set_ttl(self.source.io(), ttl)
+    }
+
+    /// Gets the value of the `IP_TTL` option on this socket.
+    pub fn ttl(&self) -> io::Result<u32> {
+        sockopt::ttl(self.source.io())
+    }
+
+    /// Gets the value of the `IPV6_V6ONLY` option on this socket.
+    pub fn only_v6(&self) -> io::Result<bool> {
+        sockopt::only_v6(self.source.io())
+    }
 }
 
 impl Future for TcpStreamNew {
@@ -314,6 +418,12 @@ impl Read for TcpStream {
         trace!("read[{:p}] {:?} on {:?}", self, r, self.source.io());
         return r
     }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        let r = sys::read_vectored(self, bufs);
+        trace!("readv[{:p}] {:?} on {:?}", self, r, self.source.io());
+        return r
+    }
 }
 
 impl Write for TcpStream {
@@ -322,6 +432,11 @@ impl Write for TcpStream {
         trace!("write[{:p}] {:?} on {:?}", self, r, self.source.io());
         return r
     }
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        let r = sys::write_vectored(self, bufs);
+        trace!("writev[{:p}] {:?} on {:?}", self, r, self.source.io());
+        return r
+    }
     fn flush(&mut self) -> io::Result<()> {
         self.source.io().flush()
     }
@@ -331,17 +446,30 @@ impl<'a> Read for &'a TcpStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.source.io().read(buf)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        sys::read_vectored(self, bufs)
+    }
 }
 
 impl<'a> Write for &'a TcpStream {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.source.io().write(buf)
     }
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        sys::write_vectored(self, bufs)
+    }
     fn flush(&mut self) -> io::Result<()> {
         self.source.io().flush()
     }
 }
 
+impl ShutdownWrite for TcpStream {
+    fn shutdown_write(&mut self) -> io::Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+}
+
 impl fmt::Debug for TcpStream {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.source.io().fmt(f)
@@ -363,9 +491,53 @@ impl Stream for TcpStream {
 
 #[cfg(unix)]
 mod sys {
+    use std::cmp;
+    use std::io::{self, IoSlice, IoSliceMut};
     use std::os::unix::prelude::*;
+
+    use libc;
+
     use super::{TcpStream, TcpListener};
 
+    // `IoSlice` and `IoSliceMut` are guaranteed to be ABI compatible with
+    // `iovec` on unix, so the slices can be handed to the kernel directly.
+
+    // `readv` and `writev` fail with `EINVAL` if given more than `IOV_MAX`
+    // slices, which is 1024 on Linux and the BSDs, rather than reading or
+    // writing the first `IOV_MAX` of them. Any beyond that are left for the
+    // next call.
+    const MAX_IOV: usize = 1024;
+
+    pub fn read_vectored(stream: &TcpStream,
+                         bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        let cnt = cmp::min(bufs.len(), MAX_IOV);
+        let r = unsafe {
+            libc::readv(stream.as_raw_fd(),
+                        bufs.as_ptr() as *const libc::iovec,
+                        cnt as libc::c_int)
+        };
+        if r < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(r as usize)
+        }
+    }
+
+    pub fn write_vectored(stream: &TcpStream,
+                          bufs: &[IoSlice]) -> io::Result<usize> {
+        let cnt = cmp::min(bufs.len(), MAX_IOV);
+        let r = unsafe {
+            libc::writev(stream.as_raw_fd(),
+                         bufs.as_ptr() as *const libc::iovec,
+                         cnt as libc::c_int)
+        };
+        if r < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(r as usize)
+        }
+    }
+
     impl AsRawFd for TcpStream {
         fn as_raw_fd(&self) -> RawFd {
             self.source.io().as_raw_fd()
@@ -381,6 +553,29 @@ mod sys {
 
 #[cfg(windows)]
 mod sys {
+    use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+
+    use super::TcpStream;
+
+    // TODO: mio doesn't expose `WSASend` and `WSARecv` with multiple buffers,
+    //       so for now only the first nonempty buffer is used.
+
+    pub fn read_vectored(stream: &TcpStream,
+                         bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        match bufs.iter_mut().find(|b| b.len() > 0) {
+            Some(buf) => stream.source.io().read(buf),
+            None => Ok(0),
+        }
+    }
+
+    pub fn write_vectored(stream: &TcpStream,
+                          bufs: &[IoSlice]) -> io::Result<usize> {
+        match bufs.iter().find(|b| b.len() > 0) {
+            Some(buf) => stream.source.io().write(buf),
+            None => Ok(0),
+        }
+    }
+
     // TODO: let's land these upstream with mio and then we can add them here.
     //
     // use std::os::windows::prelude::*;
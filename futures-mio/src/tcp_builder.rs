@@ -0,0 +1,258 @@
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures::{Future, failed};
+use futures_io::IoFuture;
+use net2;
+
+use {LoopHandle, TcpListener, TcpStream};
+use sockopt;
+
+/// A builder for TCP sockets, used to configure a socket before it's bound
+/// and either listens for connections or connects to an address.
+///
+/// Builders are created through the `LoopHandle::tcp_builder` method, and
+/// the sockets they create are associated with that event loop. Options which
+/// aren't set are left as the operating system's defaults.
+#[derive(Clone)]
+pub struct TcpBuilder {
+    handle: LoopHandle,
+    bind: Option<SocketAddr>,
+    reuse_address: bool,
+    reuse_port: bool,
+    backlog: i32,
+    send_buffer_size: Option<usize>,
+    recv_buffer_size: Option<usize>,
+    linger: Option<Option<Duration>>,
+    ttl: Option<u32>,
+    only_v6: Option<bool>,
+}
+
+impl LoopHandle {
+    /// Creates a new builder for TCP sockets associated with this event loop.
+    ///
+    /// This can be used instead of the `tcp_listen` and `tcp_connect` methods
+    /// when a socket needs to be configured before it's bound.
+    pub fn tcp_builder(self) -> TcpBuilder {
+        TcpBuilder {
+            handle: self,
+            bind: None,
+            reuse_address: false,
+            reuse_port: false,
+            backlog: 1024,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+            linger: None,
+            ttl: None,
+            only_v6: None,
+        }
+    }
+}
+
+impl TcpBuilder {
+    /// Sets the local address that sockets created by `connect` are bound to
+    /// before connecting.
+    ///
+    /// By default the operating system picks the local address.
+    pub fn set_bind(&mut self, addr: SocketAddr) -> &mut TcpBuilder {
+        self.bind = Some(addr);
+        self
+    }
+
+    /// Returns the local address that sockets created by `connect` are bound
+    /// to, if one was set.
+    pub fn bind(&self) -> Option<SocketAddr> {
+        self.bind
+    }
+
+    /// Sets the value of the `SO_REUSEADDR` option on sockets.
+    ///
+    /// This defaults to `false`.
+    pub fn set_reuse_address(&mut self, reuse: bool) -> &mut TcpBuilder {
+        self.reuse_address = reuse;
+        self
+    }
+
+    /// Returns the value of the `SO_REUSEADDR` option set on sockets.
+    pub fn reuse_address(&self) -> bool {
+        self.reuse_address
+    }
+
+    /// Sets the value of the `SO_REUSEPORT` option on sockets, which allows
+    /// several listeners to bind to the same address and share its incoming
+    /// connections.
+    ///
+    /// This defaults to `false`, and creating a socket with it set fails on
+    /// platforms other than Unix.
+    pub fn set_reuse_port(&mut self, reuse: bool) -> &mut TcpBuilder {
+        self.reuse_port = reuse;
+        self
+    }
+
+    /// Returns the value of the `SO_REUSEPORT` option set on sockets.
+    pub fn reuse_port(&self) -> bool {
+        self.reuse_port
+    }
+
+    /// Sets the length of the queue of pending connections for listeners
+    /// created by `listen`.
+    ///
+    /// This defaults to 1024.
+    pub fn set_backlog(&mut self, backlog: i32) -> &mut TcpBuilder {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Returns the length of the queue of pending connections for listeners.
+    pub fn backlog(&self) -> i32 {
+        self.backlog
+    }
+
+    /// Sets the value of the `SO_SNDBUF` option on sockets.
+    ///
+    /// For listeners this is inherited by the sockets they accept.
+    pub fn set_send_buffer_size(&mut self, size: usize) -> &mut TcpBuilder {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Returns the value of the `SO_SNDBUF` option set on sockets, if any.
+    pub fn send_buffer_size(&self) -> Option<usize> {
+        self.send_buffer_size
+    }
+
+    /// Sets the value of the `SO_RCVBUF` option on sockets.
+    ///
+    /// For listeners this is inherited by the sockets they accept.
+    pub fn set_recv_buffer_size(&mut self, size: usize) -> &mut TcpBuilder {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Returns the value of the `SO_RCVBUF` option set on sockets, if any.
+    pub fn recv_buffer_size(&self) -> Option<usize> {
+        self.recv_buffer_size
+    }
+
+    /// Sets the value of the `SO_LINGER` option on sockets.
+    ///
+    /// If `dur` is `Some` then closing a socket blocks for up to `dur` while
+    /// unsent data is sent, and otherwise closing a socket returns
+    /// immediately. The operating system only supports whole seconds here, so
+    /// `dur` is rounded up to the next second.
+    pub fn set_linger(&mut self, dur: Option<Duration>) -> &mut TcpBuilder {
+        self.linger = Some(dur);
+        self
+    }
+
+    /// Returns the value of the `SO_LINGER` option set on sockets, if any.
+    ///
+    /// The outer `Option` is `None` if `set_linger` hasn't been called, in
+    /// which case sockets keep the operating system's default.
+    pub fn linger(&self) -> Option<Option<Duration>> {
+        self.linger
+    }
+
+    /// Sets the value of the `IP_TTL` option on sockets, the time-to-live of
+    /// the packets they send.
+    pub fn set_ttl(&mut self, ttl: u32) -> &mut TcpBuilder {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the value of the `IP_TTL` option set on sockets, if any.
+    pub fn ttl(&self) -> Option<u32> {
+        self.ttl
+    }
+
+    /// Sets the value of the `IPV6_V6ONLY` option on IPv6 sockets.
+    ///
+    /// If set, an IPv6 socket only communicates over IPv6, and otherwise it
+    /// can also communicate with IPv4 addresses mapped into IPv6.
+    pub fn set_only_v6(&mut self, only_v6: bool) -> &mut TcpBuilder {
+        self.only_v6 = Some(only_v6);
+        self
+    }
+
+    /// Returns the value of the `IPV6_V6ONLY` option set on IPv6 sockets, if
+    /// any.
+    pub fn only_v6(&self) -> Option<bool> {
+        self.only_v6
+    }
+
+    /// Creates a new TCP listener with this configuration, bound to `addr`.
+    pub fn listen(&self, addr: &SocketAddr) -> Box<IoFuture<TcpListener>> {
+        let listener = (|| {
+            let builder = try!(self.socket(addr));
+            try!(builder.bind(addr));
+            builder.listen(self.backlog)
+        })();
+        match listener {
+            Ok(l) => TcpListener::from_listener(l, addr, self.handle.clone()),
+            Err(e) => failed(e).boxed(),
+        }
+    }
+
+    /// Creates a new TCP stream with this configuration, connected to `addr`.
+    ///
+    /// The socket is first bound to the address set with `set_bind`, if any.
+    pub fn connect(&self, addr: &SocketAddr) -> Box<IoFuture<TcpStream>> {
+        let stream = (|| {
+            let builder = try!(self.socket(addr));
+            try!(self.bind_local(&builder, addr));
+            builder.to_tcp_stream()
+        })();
+        match stream {
+            Ok(s) => TcpStream::connect_stream(s, addr, self.handle.clone()),
+            Err(e) => failed(e).boxed(),
+        }
+    }
+
+    fn socket(&self, addr: &SocketAddr) -> io::Result<net2::TcpBuilder> {
+        let builder = match *addr {
+            SocketAddr::V4(..) => try!(net2::TcpBuilder::new_v4()),
+            SocketAddr::V6(..) => try!(net2::TcpBuilder::new_v6()),
+        };
+        if let Some(only_v6) = self.only_v6 {
+            try!(builder.only_v6(only_v6));
+        }
+        if self.reuse_address {
+            try!(builder.reuse_address(true));
+        }
+        if self.reuse_port {
+            try!(sockopt::set_reuse_port(&builder, true));
+        }
+        if let Some(size) = self.send_buffer_size {
+            try!(sockopt::set_send_buffer_size(&builder, size));
+        }
+        if let Some(size) = self.recv_buffer_size {
+            try!(sockopt::set_recv_buffer_size(&builder, size));
+        }
+        if let Some(linger) = self.linger {
+            try!(sockopt::set_linger(&builder, linger));
+        }
+        if let Some(ttl) = self.ttl {
+            try!(sockopt::set_ttl(&builder, ttl));
+        }
+        Ok(builder)
+    }
+
+    // On Windows a socket has to be bound before it can be connected, so if
+    // no address was given we bind to the unspecified address.
+    fn bind_local(&self, builder: &net2::TcpBuilder, addr: &SocketAddr)
+                  -> io::Result<()> {
+        let local = match self.bind {
+            Some(local) => local,
+            None if cfg!(windows) => {
+                match *addr {
+                    SocketAddr::V4(..) => "0.0.0.0:0".parse().unwrap(),
+                    SocketAddr::V6(..) => "[::]:0".parse().unwrap(),
+                }
+            }
+            None => return Ok(()),
+        };
+        builder.bind(&local).map(|_| ())
+    }
+}
+
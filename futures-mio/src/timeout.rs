@@ -2,7 +2,7 @@ use std::io;
 use std::time::{Duration, Instant};
 
 use futures::{Future, Task, Poll};
-use futures_io::IoFuture;
+use futures_io::{Clock, IoFuture};
 
 use LoopHandle;
 use event_loop::TimeoutToken;
@@ -46,6 +46,16 @@ impl LoopHandle {
     }
 }
 
+impl Clock for LoopHandle {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, dur: Duration) -> Box<Future<Item=(), Error=()>> {
+        self.clone().timeout(dur).flatten().map_err(|_| ()).boxed()
+    }
+}
+
 impl Future for Timeout {
     type Item = ();
     type Error = io::Error;
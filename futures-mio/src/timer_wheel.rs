@@ -1,4 +1,4 @@
-//! A timer wheel implementation
+//! A hierarchical timer wheel implementation
 
 use std::cmp;
 use std::mem;
@@ -14,23 +14,26 @@ use slab::Slab;
 /// and removal of timers, as well as quickly figuring out what needs to get
 /// fired.
 ///
+/// The wheel is hierarchical: it's made up of a number of levels, each of
+/// which has 64 slots. A slot on the finest level covers one tick, a slot on
+/// the next level covers 64 ticks, and so on, with enough levels that every
+/// instant representable in ticks has a place in the wheel. Timeouts far in
+/// the future start out in a coarse level and are moved down a level at a
+/// time as time approaches them, so no timeout is ever looked at more than
+/// once per level no matter how far away it is.
+///
 /// Note, though, that the resolution of a timer wheel means that timeouts will
 /// not arrive promptly when they expire, but rather in certain increments of
-/// each time. The time delta between each slot of a time wheel is of a fixed
-/// length, meaning that if a timeout is scheduled between two slots it'll end
-/// up getting scheduled into the later slot.
+/// each time. The length of a tick is configurable when the wheel is created,
+/// and a timeout scheduled between two ticks is rounded to the nearest one.
 pub struct TimerWheel<T> {
-    // Actual timer wheel itself.
-    //
-    // Each slot represents a fixed duration of time, and this wheel also
-    // behaves like a ring buffer. All timeouts scheduled will correspond to one
-    // slot and therefore each slot has a linked list of timeouts scheduled in
-    // it. Right now linked lists are done through indices into the `slab`
-    // below.
+    // The levels of the wheel, finest first.
     //
-    // Each slot also contains the next timeout associated with it (the minimum
-    // of the entire linked list).
-    wheel: Vec<Slot>,
+    // Each slot of each level has a linked list of the timeouts scheduled in
+    // it, done through indices into the `slab` below. A level also keeps a
+    // bitmask of which of its slots are occupied, so finding the next slot
+    // with anything in it is a quick bit operation.
+    levels: Vec<Level>,
 
     // A slab containing all the timeout entries themselves. This is the memory
     // backing the "linked lists" in the wheel above. Each entry has a prev/next
@@ -38,31 +41,43 @@ pub struct TimerWheel<T> {
     // timeout and the time the timeout will fire.
     slab: Slab<Entry<T>, usize>,
 
+    // The head of a linked list of timeouts which have expired but haven't
+    // been returned from `poll` yet.
+    expired: usize,
+
     // The instant that this timer was created, through which all other timeout
-    // computations are relative to.
+    // computations are relative to, and the length of a tick.
     start: Instant,
+    tick_nanos: u64,
 
-    // State used during `poll`. The `cur_wheel_tick` field is the current tick
-    // we've poll'd to. That is, all events from `cur_wheel_tick` to the
-    // actual current tick in time still need to be processed.
-    //
-    // The `cur_slab_idx` variable is basically just an iterator over the linked
-    // list associated with a wheel slot. This will get incremented as we move
-    // forward in `poll`
-    cur_wheel_tick: u64,
-    cur_slab_idx: usize,
+    // The next tick which `poll` has yet to process. All timeouts still in the
+    // wheel are scheduled for this tick or later, and each one is stored in
+    // the level corresponding to the highest group of bits in which its tick
+    // differs from this one.
+    cur_tick: u64,
+}
+
+struct Level {
+    occupied: u64,
+    slots: Vec<Slot>,
 }
 
 #[derive(Clone)]
 struct Slot {
     head: usize,
+
+    // For slots on the finest level, the instant the earliest timeout in the
+    // slot should fire at. Coarser slots are woken up for at the start of the
+    // span of time that they cover.
     next_timeout: Option<Instant>,
 }
 
 struct Entry<T> {
     data: T,
     when: Instant,
-    wheel_idx: usize,
+    tick: u64,
+    level: usize,
+    slot: usize,
     prev: usize,
     next: usize,
 }
@@ -76,23 +91,45 @@ pub struct Timeout {
 }
 
 const EMPTY: usize = 0;
-const LEN: usize = 256;
-const MASK: usize = LEN - 1;
-const TICK_MS: u64 = 100;
+const SLOT_BITS: usize = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const MASK: u64 = SLOTS as u64 - 1;
+const LEVELS: usize = (64 + SLOT_BITS - 1) / SLOT_BITS;
+// The `level` of entries which are in the `expired` list.
+const EXPIRED: usize = LEVELS;
+const DEFAULT_TICK_MS: u64 = 100;
 
 impl<T> TimerWheel<T> {
     /// Creates a new timer wheel configured with no timeouts and with the
     /// default parameters.
     ///
-    /// Currently this is a timer wheel of length 256 with a 100ms time
-    /// resolution.
+    /// Currently this is a timer wheel with a 100ms time resolution.
     pub fn new() -> TimerWheel<T> {
+        TimerWheel::with_tick(Duration::from_millis(DEFAULT_TICK_MS))
+    }
+
+    /// Creates a new timer wheel with no timeouts and a time resolution of
+    /// `tick`.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `tick` is zero.
+    pub fn with_tick(tick: Duration) -> TimerWheel<T> {
+        let tick_nanos = tick.as_secs() * 1_000_000_000 +
+                         tick.subsec_nanos() as u64;
+        assert!(tick_nanos > 0, "timer wheel tick must be nonzero");
         TimerWheel {
-            wheel: vec![Slot { head: EMPTY, next_timeout: None }; LEN],
+            levels: (0..LEVELS).map(|_| {
+                Level {
+                    occupied: 0,
+                    slots: vec![Slot { head: EMPTY, next_timeout: None }; SLOTS],
+                }
+            }).collect(),
             slab: Slab::new_starting_at(1, 256),
+            expired: EMPTY,
             start: Instant::now(),
-            cur_wheel_tick: 0,
-            cur_slab_idx: EMPTY,
+            tick_nanos: tick_nanos,
+            cur_tick: 0,
         }
     }
 
@@ -111,57 +148,36 @@ impl<T> TimerWheel<T> {
     /// This method will panic if `at` is before the time that this timer wheel
     /// was created.
     pub fn insert(&mut self, at: Instant, data: T) -> Timeout {
-        // First up, figure out where we're gonna go in the wheel. Note that if
-        // we're being scheduled on or before the current wheel tick we just
-        // make sure to defer ourselves to the next tick.
-        let mut tick = self.time_to_ticks(at);
-        if tick <= self.cur_wheel_tick {
-            debug!("moving {} to {}", tick, self.cur_wheel_tick + 1);
-            tick = self.cur_wheel_tick + 1;
-        }
-        let wheel_idx = self.ticks_to_wheel_idx(tick);
-        trace!("inserting timeout at {} for {}", wheel_idx, tick);
+        // If we're being scheduled before the tick we've polled up to then
+        // we'll just fire the next time we're polled.
+        let tick = cmp::max(self.time_to_ticks(at), self.cur_tick);
 
-        // Next, make sure there's enough space in the slab for the timeout.
+        // Make sure there's enough space in the slab for the timeout.
         if self.slab.vacant_entry().is_none() {
             let amt = self.slab.count();
             self.slab.grow(amt);
         }
 
-        // Insert ourselves at the head of the linked list in the wheel.
-        let slot = &mut self.wheel[wheel_idx];
-        let prev_head;
-        {
+        let slab_idx = {
             let entry = self.slab.vacant_entry().unwrap();
-            prev_head = mem::replace(&mut slot.head, entry.index());
-            trace!("timer wheel slab idx: {}", entry.index());
-
+            let slab_idx = entry.index();
             entry.insert(Entry {
                 data: data,
                 when: at,
-                wheel_idx: wheel_idx,
+                tick: tick,
+                level: EXPIRED,
+                slot: 0,
                 prev: EMPTY,
-                next: prev_head,
+                next: EMPTY,
             });
-        }
-        if prev_head != EMPTY {
-            self.slab[prev_head].prev = slot.head;
-        }
-
-        // Update the wheel slot's next timeout field.
-        if at <= slot.next_timeout.unwrap_or(at) {
-            let tick = tick as u32;
-            let actual_tick = self.start + Duration::from_millis(TICK_MS) * tick;
-            trace!("actual_tick: {:?}", actual_tick);
-            trace!("at:          {:?}", at);
-            let at = cmp::max(actual_tick, at);
-            debug!("updating[{}] next timeout: {:?}", wheel_idx, at);
-            slot.next_timeout = Some(at);
-        }
+            slab_idx
+        };
+        trace!("inserting timeout {} for tick {}", slab_idx, tick);
+        self.link(slab_idx);
 
         Timeout {
             when: at,
-            slab_idx: slot.head,
+            slab_idx: slab_idx,
         }
     }
 
@@ -177,68 +193,75 @@ impl<T> TimerWheel<T> {
     /// This method will panic if `at` is before the instant that this timer
     /// wheel was created.
     pub fn poll(&mut self, at: Instant) -> Option<T> {
-        let wheel_tick = self.time_to_ticks(at);
-
-        trace!("polling {} => {}", self.cur_wheel_tick, wheel_tick);
-
-        // Advance forward in time to the `wheel_tick` specified.
-        //
-        // TODO: don't visit slots in the wheel more than once
-        while self.cur_wheel_tick <= wheel_tick {
-            let head = self.cur_slab_idx;
-            let idx = self.ticks_to_wheel_idx(self.cur_wheel_tick);
-            trace!("next head[{} => {}]: {}",
-                   self.cur_wheel_tick, wheel_tick, head);
-
-            // If the current slot has no entries or we're done iterating go to
-            // the next tick.
-            if head == EMPTY {
-                if head == self.wheel[idx].head {
-                    self.wheel[idx].next_timeout = None;
-                }
-                self.cur_wheel_tick += 1;
-                let idx = self.ticks_to_wheel_idx(self.cur_wheel_tick);
-                self.cur_slab_idx = self.wheel[idx].head;
-                continue
-            }
+        let now = self.time_to_ticks(at);
+
+        trace!("polling {} => {}", self.cur_tick, now);
 
-            // If we're starting to iterate over a slot, clear its timeout as
-            // we're probably going to remove entries. As we skip over each
-            // element of this slot we'll restore the `next_timeout` field if
-            // necessary.
-            if head == self.wheel[idx].head {
-                self.wheel[idx].next_timeout = None;
+        loop {
+            if self.expired != EMPTY {
+                let slab_idx = self.expired;
+                self.unlink(slab_idx);
+                return self.slab.remove(slab_idx).map(|e| e.data)
             }
 
-            // Otherwise, continue iterating over the linked list in the wheel
-            // slot we're on and remove anything which has expired.
-            self.cur_slab_idx = self.slab[head].next;
-            let head_timeout = self.slab[head].when;
-            if self.time_to_ticks(head_timeout) <= self.time_to_ticks(at) {
-                return self.remove_slab(head).map(|e| e.data)
+            let (level, slot, tick) = match self.next_expiration() {
+                Some(next) if next.2 <= now => next,
+                _ => break,
+            };
+            trace!("processing level {} slot {} at tick {}", level, slot, tick);
+
+            let mut head = {
+                let level = &mut self.levels[level];
+                level.occupied &= !(1 << slot);
+                level.slots[slot].next_timeout = None;
+                mem::replace(&mut level.slots[slot].head, EMPTY)
+            };
+
+            if level == 0 {
+                // Everything in a slot of the finest level is for this very
+                // tick, so the whole list has expired.
+                self.cur_tick = tick + 1;
+                self.expired = head;
+                while head != EMPTY {
+                    self.slab[head].level = EXPIRED;
+                    head = self.slab[head].next;
+                }
             } else {
-                let next = self.wheel[idx].next_timeout.unwrap_or(head_timeout);
-                if head_timeout <= next {
-                    self.wheel[idx].next_timeout = Some(head_timeout);
+                // Otherwise we've reached the start of the span covered by a
+                // coarser slot, so spread its timeouts out over the finer
+                // levels.
+                self.cur_tick = tick;
+                while head != EMPTY {
+                    let next = self.slab[head].next;
+                    self.link(head);
+                    head = next;
                 }
             }
         }
 
+        // Nothing is scheduled up to `now`, so we can skip straight past it.
+        if self.cur_tick <= now {
+            self.cur_tick = now + 1;
+        }
         None
     }
 
     /// Returns the instant in time that corresponds to the next timeout
     /// scheduled in this wheel.
+    ///
+    /// For timeouts far in the future this may be an earlier instant at which
+    /// the wheel needs to be polled to move them closer.
     pub fn next_timeout(&self) -> Option<Instant> {
-        // TODO: can this be optimized to not look at the whole array?
-        let timeouts = self.wheel.iter().map(|slot| slot.next_timeout);
-        let min = timeouts.fold(None, |prev, cur| {
-            match (prev, cur) {
-                (None, cur) => cur,
-                (Some(time), None) => Some(time),
-                (Some(a), Some(b)) => Some(cmp::min(a, b)),
-            }
-        });
+        let min = if self.expired != EMPTY {
+            Some(self.ticks_to_time(self.cur_tick - 1))
+        } else {
+            self.next_expiration().map(|(level, slot, tick)| {
+                match self.levels[level].slots[slot].next_timeout {
+                    Some(at) => at,
+                    None => self.ticks_to_time(tick),
+                }
+            })
+        };
         if let Some(min) = min {
             debug!("next timeout {:?}", min);
             debug!("now          {:?}", Instant::now());
@@ -265,45 +288,115 @@ impl<T> TimerWheel<T> {
             _ => return None,
         }
 
-        self.remove_slab(timeout.slab_idx).map(|e| e.data)
+        debug!("removing timer slab {}", timeout.slab_idx);
+        self.unlink(timeout.slab_idx);
+        self.slab.remove(timeout.slab_idx).map(|e| e.data)
+    }
+
+    // Finds the earliest occupied slot, returning its level, its index and the
+    // tick at which it needs to be processed.
+    //
+    // Every timeout in a level differs from `cur_tick` in that level's group
+    // of bits and agrees with it in all higher groups, so within a level the
+    // lowest occupied slot comes due first. Once `cur_tick` reaches the start
+    // of a coarse slot, though, new timeouts for the same span go into finer
+    // levels, so on a tie the coarser slot is picked to spread its timeouts
+    // out before anything in that span fires.
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        let mut next = None;
+        for (i, level) in self.levels.iter().enumerate() {
+            if level.occupied == 0 {
+                continue
+            }
+            let slot = level.occupied.trailing_zeros() as usize;
+            let shift = i * SLOT_BITS;
+            let upper = if shift + SLOT_BITS >= 64 {
+                0
+            } else {
+                self.cur_tick >> (shift + SLOT_BITS) << (shift + SLOT_BITS)
+            };
+            let tick = upper | ((slot as u64) << shift);
+            match next {
+                Some((_, _, t)) if t < tick => {}
+                _ => next = Some((i, slot, tick)),
+            }
+        }
+        next
     }
 
-    fn remove_slab(&mut self, slab_idx: usize) -> Option<Entry<T>> {
-        debug!("removing timer slab {}", slab_idx);
-        let entry = match self.slab.remove(slab_idx) {
-            Some(e) => e,
-            None => return None,
+    // Places an entry in the level and slot corresponding to its tick.
+    fn link(&mut self, slab_idx: usize) {
+        let (tick, when) = {
+            let entry = &self.slab[slab_idx];
+            (entry.tick, entry.when)
         };
+        let masked = (self.cur_tick ^ tick) | MASK;
+        let level = (63 - masked.leading_zeros() as usize) / SLOT_BITS;
+        let slot = ((tick >> (level * SLOT_BITS)) & MASK) as usize;
 
-        // Remove the node from the linked list
-        if entry.prev == EMPTY {
-            self.wheel[entry.wheel_idx].head = entry.next;
+        let next_timeout = if level == 0 {
+            Some(cmp::max(self.ticks_to_time(tick), when))
         } else {
-            self.slab[entry.prev].next = entry.next;
-        }
-        if entry.next != EMPTY {
-            self.slab[entry.next].prev = entry.prev;
+            None
+        };
+        let prev_head = {
+            let level = &mut self.levels[level];
+            level.occupied |= 1 << slot;
+            let slot = &mut level.slots[slot];
+            if let Some(at) = next_timeout {
+                if at <= slot.next_timeout.unwrap_or(at) {
+                    slot.next_timeout = Some(at);
+                }
+            }
+            mem::replace(&mut slot.head, slab_idx)
+        };
+        if prev_head != EMPTY {
+            self.slab[prev_head].prev = slab_idx;
         }
 
-        if self.cur_slab_idx == slab_idx {
-            self.cur_slab_idx = entry.next;
-        }
+        let entry = &mut self.slab[slab_idx];
+        entry.level = level;
+        entry.slot = slot;
+        entry.prev = EMPTY;
+        entry.next = prev_head;
+    }
 
-        return Some(entry)
+    // Removes an entry from whichever list it's in.
+    fn unlink(&mut self, slab_idx: usize) {
+        let (level, slot, prev, next) = {
+            let entry = &self.slab[slab_idx];
+            (entry.level, entry.slot, entry.prev, entry.next)
+        };
+        if prev != EMPTY {
+            self.slab[prev].next = next;
+        } else if level == EXPIRED {
+            self.expired = next;
+        } else {
+            let level = &mut self.levels[level];
+            level.slots[slot].head = next;
+            if next == EMPTY {
+                level.occupied &= !(1 << slot);
+                level.slots[slot].next_timeout = None;
+            }
+        }
+        if next != EMPTY {
+            self.slab[next].prev = prev;
+        }
     }
 
     fn time_to_ticks(&self, time: Instant) -> u64 {
         let dur = time - self.start;
-        let ms = dur.subsec_nanos() as u64 / 1_000_000;
-        let ms = dur.as_secs()
-                    .checked_mul(1_000)
-                    .and_then(|m| m.checked_add(ms))
-                    .expect("overflow scheduling timeout");
-        (ms + TICK_MS / 2) / TICK_MS
+        let nanos = dur.as_secs()
+                       .checked_mul(1_000_000_000)
+                       .and_then(|n| n.checked_add(dur.subsec_nanos() as u64))
+                       .expect("overflow scheduling timeout");
+        (nanos + self.tick_nanos / 2) / self.tick_nanos
     }
 
-    fn ticks_to_wheel_idx(&self, ticks: u64) -> usize {
-        (ticks as usize) & MASK
+    fn ticks_to_time(&self, ticks: u64) -> Instant {
+        let nanos = ticks.saturating_mul(self.tick_nanos);
+        self.start + Duration::new(nanos / 1_000_000_000,
+                                   (nanos % 1_000_000_000) as u32)
     }
 }
 
@@ -494,4 +587,40 @@ mod tests {
             assert_eq!(timer.next_timeout(), None);
         }
     }
+
+    #[test]
+    fn far_future() {
+        drop(env_logger::init());
+        let mut timer = TimerWheel::<i32>::with_tick(ms(1));
+        let now = Instant::now();
+        let secs = Duration::from_secs;
+
+        timer.insert(now + secs(3600), 1);
+        timer.insert(now + secs(60), 2);
+        assert_eq!(timer.poll(now + secs(59)), None);
+        assert_eq!(timer.poll(now + secs(60)), Some(2));
+        assert_eq!(timer.poll(now + secs(3599)), None);
+        assert!(timer.next_timeout().unwrap() <= now + secs(3600));
+        assert_eq!(timer.poll(now + secs(3600)), Some(1));
+        assert_eq!(timer.next_timeout(), None);
+    }
+
+    #[test]
+    fn insert_into_span_of_coarse_slot() {
+        drop(env_logger::init());
+        let mut timer = TimerWheel::<i32>::with_tick(ms(1));
+        let now = Instant::now();
+
+        // Polling up to the start of the span of the coarse slot holding 100
+        // means 90 goes into a finer level than it, but 100 must still not be
+        // missed or fired early.
+        timer.insert(now + ms(100), 1);
+        assert_eq!(timer.poll(now + ms(63)), None);
+        timer.insert(now + ms(90), 2);
+        assert!(timer.next_timeout().unwrap() <= now + ms(90));
+        assert_eq!(timer.poll(now + ms(95)), Some(2));
+        assert_eq!(timer.poll(now + ms(95)), None);
+        assert_eq!(timer.next_timeout(), Some(now + ms(100)));
+        assert_eq!(timer.poll(now + ms(100)), Some(1));
+    }
 }
@@ -0,0 +1,802 @@
+use std::fmt;
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::mem;
+use std::net::Shutdown;
+use std::os::unix::net::{self, SocketAddr};
+use std::os::unix::prelude::*;
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+use futures::stream::{self, Stream};
+use futures::{Future, IntoFuture, failed, Task, Poll};
+use futures_io::{Ready, IoFuture, IoStream, ShutdownWrite};
+use libc;
+use mio;
+use mio::unix::EventedFd;
+
+use {ReadinessStream, LoopHandle};
+use event_loop::Source;
+use shutdown::until_shutdown;
+
+/// An I/O object representing a Unix socket listening for incoming
+/// connections.
+///
+/// This object can be converted into a stream of incoming connections for
+/// various forms of processing.
+pub struct UnixListener {
+    loop_handle: LoopHandle,
+    ready: ReadinessStream,
+    listener: Arc<Source<Fd<net::UnixListener>>>,
+}
+
+/// An I/O object representing a Unix stream socket connected to a peer.
+pub struct UnixStream {
+    source: Arc<Source<Fd<net::UnixStream>>>,
+    ready: ReadinessStream,
+}
+
+/// An I/O object representing a Unix datagram socket.
+pub struct UnixDatagram {
+    source: Arc<Source<Fd<net::UnixDatagram>>>,
+    ready: ReadinessStream,
+}
+
+/// Credentials of the process on the other end of a Unix socket, as returned
+/// by `UnixStream::peer_cred`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UCred {
+    /// The user ID of the peer process.
+    pub uid: libc::uid_t,
+    /// The group ID of the peer process.
+    pub gid: libc::gid_t,
+    /// The process ID of the peer process, if the platform reports it.
+    pub pid: Option<libc::pid_t>,
+}
+
+// Objects from the standard library put into nonblocking mode, registered
+// with mio through their file descriptor. This is also used for the pipes of
+// child processes.
+pub struct Fd<T>(pub T);
+
+impl<T: AsRawFd> mio::Evented for Fd<T> {
+    fn register(&self,
+                poll: &mio::Poll,
+                token: mio::Token,
+                interest: mio::EventSet,
+                opts: mio::PollOpt) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self,
+                  poll: &mio::Poll,
+                  token: mio::Token,
+                  interest: mio::EventSet,
+                  opts: mio::PollOpt) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).deregister(poll)
+    }
+}
+
+impl LoopHandle {
+    /// Create a new Unix listener associated with this event loop.
+    ///
+    /// The listener will bind to the provided `path`, which must not already
+    /// exist, and will be returned as a future. The returned future, if
+    /// resolved successfully, can then be used to accept incoming connections.
+    pub fn unix_listen<P>(self, path: P) -> Box<IoFuture<UnixListener>>
+        where P: AsRef<Path>,
+    {
+        match net::UnixListener::bind(path) {
+            Ok(l) => UnixListener::from_listener(l, self),
+            Err(e) => failed(e).boxed(),
+        }
+    }
+
+    /// Create a new Unix stream connected to the socket at `path`.
+    ///
+    /// The connection is started without blocking, and the returned future
+    /// resolves once it's been made. If the listener's backlog is full the
+    /// connection is retried when the socket next becomes writable.
+    pub fn unix_connect<P>(self, path: P) -> Box<IoFuture<UnixStream>>
+        where P: AsRef<Path>,
+    {
+        let addr = match UnixAddr::new(path.as_ref()) {
+            Ok(addr) => addr,
+            Err(e) => return failed(e).boxed(),
+        };
+        let stream = match nonblocking_stream() {
+            Ok(stream) => stream,
+            Err(e) => return failed(e).boxed(),
+        };
+        let connected = match addr.connect(stream.as_raw_fd()) {
+            Ok(connected) => connected,
+            Err(e) => return failed(e).boxed(),
+        };
+        register(stream, self, |source, ready| {
+            UnixStream {
+                source: source,
+                ready: ready,
+            }
+        }).and_then(move |stream| {
+            UnixStreamNew {
+                stream: Some(stream),
+                addr: addr,
+                connected: connected,
+            }
+        }).boxed()
+    }
+
+    /// Create a new Unix datagram socket bound to the specified `path`.
+    ///
+    /// The returned future will be resolved once the socket has been
+    /// registered with this event loop.
+    pub fn unix_datagram_bind<P>(self, path: P) -> Box<IoFuture<UnixDatagram>>
+        where P: AsRef<Path>,
+    {
+        match net::UnixDatagram::bind(path) {
+            Ok(s) => UnixDatagram::from_socket(s, self),
+            Err(e) => failed(e).boxed(),
+        }
+    }
+}
+
+pub fn register<T, F, U>(io: T, handle: LoopHandle, f: F) -> Box<IoFuture<U>>
+    where T: AsRawFd + Send + Sync + 'static,
+          F: FnOnce(Arc<Source<Fd<T>>>, ReadinessStream) -> U + Send + 'static,
+          U: Send + 'static,
+{
+    let source = Arc::new(Source::new(Fd(io)));
+    ReadinessStream::new(handle, source.clone()).map(move |ready| {
+        f(source, ready)
+    }).boxed()
+}
+
+impl UnixListener {
+    /// Create a new Unix listener from the standard library's listener.
+    ///
+    /// The listener is placed into nonblocking mode and registered with the
+    /// event loop that `handle` is associated with.
+    pub fn from_listener(listener: net::UnixListener,
+                         handle: LoopHandle) -> Box<IoFuture<UnixListener>> {
+        let handle2 = handle.clone();
+        listener.set_nonblocking(true).into_future().and_then(move |()| {
+            register(listener, handle, |listener, ready| {
+                UnixListener {
+                    loop_handle: handle2,
+                    ready: ready,
+                    listener: listener,
+                }
+            })
+        }).boxed()
+    }
+
+    /// Returns the local address that this listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.io().0.local_addr()
+    }
+
+    /// Consumes this listener, returning a stream of the sockets this listener
+    /// accepts.
+    ///
+    /// The stream ends once the event loop starts shutting down gracefully,
+    /// see `LoopHandle::shutdown_gracefully`.
+    pub fn incoming(self) -> Box<IoStream<(UnixStream, SocketAddr)>> {
+        let UnixListener { loop_handle, listener, ready } = self;
+
+        let accepted = ready
+            .map(move |_| {
+                stream::iter(NonblockingIter { source: listener.clone() }.fuse())
+            })
+            .flatten();
+        until_shutdown(accepted, &loop_handle)
+            .and_then(move |(stream, addr)| {
+                UnixStream::from_stream(stream, loop_handle.clone()).map(move |s| {
+                    (s, addr)
+                })
+            }).boxed()
+    }
+}
+
+struct NonblockingIter {
+    source: Arc<Source<Fd<net::UnixListener>>>,
+}
+
+impl Iterator for NonblockingIter {
+    type Item = io::Result<(net::UnixStream, SocketAddr)>;
+
+    fn next(&mut self) -> Option<io::Result<(net::UnixStream, SocketAddr)>> {
+        match self.source.io().0.accept() {
+            Ok(e) => {
+                debug!("accepted connection");
+                Some(Ok(e))
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                debug!("no connection ready");
+                None
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl fmt::Debug for UnixListener {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.listener.io().0.fmt(f)
+    }
+}
+
+impl Stream for UnixListener {
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        self.ready.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.ready.schedule(task)
+    }
+}
+
+impl AsRawFd for UnixListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.listener.io().0.as_raw_fd()
+    }
+}
+
+// The address of a socket in the filesystem, in the form `connect` takes it.
+struct UnixAddr {
+    addr: libc::sockaddr_un,
+    len: libc::socklen_t,
+}
+
+impl UnixAddr {
+    fn new(path: &Path) -> io::Result<UnixAddr> {
+        let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        let bytes = path.as_os_str().as_bytes();
+        if bytes.contains(&0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "paths may not contain interior null \
+                                       bytes"))
+        }
+        // There needs to be room left for a terminating null byte.
+        if bytes.len() >= addr.sun_path.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "path must be shorter than SUN_LEN"))
+        }
+        for (dst, src) in addr.sun_path.iter_mut().zip(bytes) {
+            *dst = *src as libc::c_char;
+        }
+        let base = &addr as *const _ as usize;
+        let path = &addr.sun_path as *const _ as usize;
+        let len = path - base + bytes.len() + 1;
+        Ok(UnixAddr { addr: addr, len: len as libc::socklen_t })
+    }
+
+    // Connects `fd` to this address, returning whether the connection has
+    // been made yet.
+    fn connect(&self, fd: RawFd) -> io::Result<bool> {
+        let r = unsafe {
+            libc::connect(fd,
+                          &self.addr as *const _ as *const libc::sockaddr,
+                          self.len)
+        };
+        if r == 0 {
+            return Ok(true)
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            // A previous attempt has since completed.
+            Some(libc::EISCONN) => Ok(true),
+            // Either the connection is in progress, or on Linux the
+            // listener's backlog is full and we need to try again.
+            Some(libc::EINPROGRESS) |
+            Some(libc::EALREADY) |
+            Some(libc::EAGAIN) => Ok(false),
+            _ => Err(err),
+        }
+    }
+}
+
+// Creates a Unix stream socket which isn't connected yet, in nonblocking mode
+// and closed on exec.
+fn nonblocking_stream() -> io::Result<net::UnixStream> {
+    unsafe {
+        let fd = libc::socket(libc::AF_UNIX, libc::SOCK_STREAM, 0);
+        if fd < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        let stream = net::UnixStream::from_raw_fd(fd);
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        if flags < 0 ||
+           libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        try!(stream.set_nonblocking(true));
+        Ok(stream)
+    }
+}
+
+/// A future waiting for a connection started by `LoopHandle::unix_connect`
+/// to be made.
+struct UnixStreamNew {
+    stream: Option<UnixStream>,
+    addr: UnixAddr,
+    connected: bool,
+}
+
+impl Future for UnixStreamNew {
+    type Item = UnixStream;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<UnixStream, io::Error> {
+        if !self.connected {
+            let stream = self.stream.as_mut().expect("can't poll twice");
+            match stream.ready.poll(task) {
+                Poll::Ok(Some(_)) => {}
+                Poll::Ok(None) => panic!("readiness stream ended"),
+                Poll::Err(e) => return Poll::Err(e),
+                Poll::NotReady => return Poll::NotReady,
+            }
+            // Once we're writable, asking to connect again finds out how
+            // the connection went, or tries again if it wasn't started.
+            match self.addr.connect(stream.as_raw_fd()) {
+                Ok(true) => {}
+                Ok(false) => return Poll::NotReady,
+                Err(e) => return Poll::Err(e),
+            }
+        }
+        Poll::Ok(self.stream.take().expect("can't poll twice"))
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        match self.stream {
+            Some(ref mut s) if !self.connected => s.ready.schedule(task),
+            _ => task.notify(),
+        }
+    }
+}
+
+impl UnixStream {
+    /// Create a new Unix stream from the standard library's stream.
+    ///
+    /// The stream is placed into nonblocking mode and registered with the
+    /// event loop that `handle` is associated with.
+    pub fn from_stream(stream: net::UnixStream,
+                       handle: LoopHandle) -> Box<IoFuture<UnixStream>> {
+        stream.set_nonblocking(true).into_future().and_then(move |()| {
+            register(stream, handle, |source, ready| {
+                UnixStream {
+                    source: source,
+                    ready: ready,
+                }
+            })
+        }).boxed()
+    }
+
+    /// Creates an unnamed pair of connected sockets, both associated with the
+    /// event loop that `handle` is associated with.
+    pub fn pair(handle: LoopHandle) -> Box<IoFuture<(UnixStream, UnixStream)>> {
+        match net::UnixStream::pair() {
+            Ok((a, b)) => {
+                let a = UnixStream::from_stream(a, handle.clone());
+                let b = UnixStream::from_stream(b, handle);
+                a.join(b).boxed()
+            }
+            Err(e) => failed(e).boxed(),
+        }
+    }
+
+    /// Returns the local address that this stream is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.source.io().0.local_addr()
+    }
+
+    /// Returns the address of the peer this stream is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.source.io().0.peer_addr()
+    }
+
+    /// Returns the credentials of the process which created the other end of
+    /// this connection.
+    ///
+    /// On Linux the credentials are those from when the peer connected (or
+    /// when the pair was created), and include the peer's process ID. On BSDs
+    /// and macOS the process ID isn't available.
+    pub fn peer_cred(&self) -> io::Result<UCred> {
+        peer_cred(self.as_raw_fd())
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.source.io().0.shutdown(how)
+    }
+
+    /// Sends `buf` along with the file descriptors in `fds` to the peer.
+    ///
+    /// The descriptors are sent as `SCM_RIGHTS` ancillary data, and the peer
+    /// receives duplicates of them with `recv_fds`. The descriptors are
+    /// attached to the first byte sent, so `buf` must not be empty if `fds`
+    /// isn't. Returns the number of bytes of `buf` sent.
+    pub fn send_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        send_fds(self.as_raw_fd(), buf, fds)
+    }
+
+    /// Receives data into `buf` along with any file descriptors sent by the
+    /// peer with `send_fds`.
+    ///
+    /// Up to `fds.len()` received descriptors are stored in `fds`, and the
+    /// caller becomes responsible for closing them. Any descriptors beyond
+    /// that are discarded by the kernel. Returns the number of bytes and
+    /// descriptors received, respectively.
+    pub fn recv_fds(&self, buf: &mut [u8], fds: &mut [RawFd])
+                    -> io::Result<(usize, usize)> {
+        recv_fds(self.as_raw_fd(), buf, fds)
+    }
+}
+
+impl Read for UnixStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&self.source.io().0).read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        (&self.source.io().0).read_vectored(bufs)
+    }
+}
+
+impl Write for UnixStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&self.source.io().0).write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        (&self.source.io().0).write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&self.source.io().0).flush()
+    }
+}
+
+impl<'a> Read for &'a UnixStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&self.source.io().0).read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        (&self.source.io().0).read_vectored(bufs)
+    }
+}
+
+impl<'a> Write for &'a UnixStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&self.source.io().0).write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        (&self.source.io().0).write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&self.source.io().0).flush()
+    }
+}
+
+impl ShutdownWrite for UnixStream {
+    fn shutdown_write(&mut self) -> io::Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+}
+
+impl fmt::Debug for UnixStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.source.io().0.fmt(f)
+    }
+}
+
+impl Stream for UnixStream {
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        self.ready.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.ready.schedule(task)
+    }
+}
+
+impl AsRawFd for UnixStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.source.io().0.as_raw_fd()
+    }
+}
+
+impl UnixDatagram {
+    /// Create a new Unix datagram socket from the standard library's socket.
+    ///
+    /// The socket is placed into nonblocking mode and registered with the
+    /// event loop that `handle` is associated with.
+    pub fn from_socket(socket: net::UnixDatagram,
+                       handle: LoopHandle) -> Box<IoFuture<UnixDatagram>> {
+        socket.set_nonblocking(true).into_future().and_then(move |()| {
+            register(socket, handle, |source, ready| {
+                UnixDatagram {
+                    source: source,
+                    ready: ready,
+                }
+            })
+        }).boxed()
+    }
+
+    /// Creates a socket which isn't bound to any address.
+    pub fn unbound(handle: LoopHandle) -> Box<IoFuture<UnixDatagram>> {
+        match net::UnixDatagram::unbound() {
+            Ok(s) => UnixDatagram::from_socket(s, handle),
+            Err(e) => failed(e).boxed(),
+        }
+    }
+
+    /// Creates an unnamed pair of connected sockets, both associated with the
+    /// event loop that `handle` is associated with.
+    pub fn pair(handle: LoopHandle)
+                -> Box<IoFuture<(UnixDatagram, UnixDatagram)>> {
+        match net::UnixDatagram::pair() {
+            Ok((a, b)) => {
+                let a = UnixDatagram::from_socket(a, handle.clone());
+                let b = UnixDatagram::from_socket(b, handle);
+                a.join(b).boxed()
+            }
+            Err(e) => failed(e).boxed(),
+        }
+    }
+
+    /// Connects the socket to the socket at `path`, so `send` and `recv` can
+    /// be used.
+    pub fn connect<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.source.io().0.connect(path)
+    }
+
+    /// Returns the local address that this socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.source.io().0.local_addr()
+    }
+
+    /// Returns the address of the socket this socket is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.source.io().0.peer_addr()
+    }
+
+    /// Sends data on the socket to the socket at `path`. On success, returns
+    /// the number of bytes written.
+    pub fn send_to<P: AsRef<Path>>(&self, buf: &[u8], path: P)
+                                   -> io::Result<usize> {
+        self.source.io().0.send_to(buf, path)
+    }
+
+    /// Receives data from the socket. On success, returns the number of bytes
+    /// read and the address of the socket it came from.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.source.io().0.recv_from(buf)
+    }
+
+    /// Sends data on the socket to the socket it's connected to.
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.source.io().0.send(buf)
+    }
+
+    /// Receives data from the socket it's connected to.
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.source.io().0.recv(buf)
+    }
+
+    /// Sends a datagram along with the file descriptors in `fds` to the
+    /// socket this socket is connected to.
+    ///
+    /// See `UnixStream::send_fds` for more information.
+    pub fn send_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        send_fds(self.as_raw_fd(), buf, fds)
+    }
+
+    /// Receives a datagram along with any file descriptors sent with it.
+    ///
+    /// See `UnixStream::recv_fds` for more information.
+    pub fn recv_fds(&self, buf: &mut [u8], fds: &mut [RawFd])
+                    -> io::Result<(usize, usize)> {
+        recv_fds(self.as_raw_fd(), buf, fds)
+    }
+
+    /// Shuts down the read, write, or both halves of this socket.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.source.io().0.shutdown(how)
+    }
+}
+
+impl fmt::Debug for UnixDatagram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.source.io().0.fmt(f)
+    }
+}
+
+impl Stream for UnixDatagram {
+    type Item = Ready;
+    type Error = io::Error;
+
+    fn poll(&mut self, task: &mut Task) -> Poll<Option<Ready>, io::Error> {
+        self.ready.poll(task)
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.ready.schedule(task)
+    }
+}
+
+impl AsRawFd for UnixDatagram {
+    fn as_raw_fd(&self) -> RawFd {
+        self.source.io().0.as_raw_fd()
+    }
+}
+
+fn cvt(r: libc::ssize_t) -> io::Result<usize> {
+    if r < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(r as usize)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_cred(fd: RawFd) -> io::Result<UCred> {
+    unsafe {
+        let mut cred: libc::ucred = mem::zeroed();
+        let mut len = mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let r = libc::getsockopt(fd,
+                                 libc::SOL_SOCKET,
+                                 libc::SO_PEERCRED,
+                                 &mut cred as *mut _ as *mut libc::c_void,
+                                 &mut len);
+        if r < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(UCred { uid: cred.uid, gid: cred.gid, pid: Some(cred.pid) })
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd",
+          target_os = "dragonfly", target_os = "openbsd",
+          target_os = "netbsd"))]
+fn peer_cred(fd: RawFd) -> io::Result<UCred> {
+    unsafe {
+        let mut uid = 0;
+        let mut gid = 0;
+        if libc::getpeereid(fd, &mut uid, &mut gid) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(UCred { uid: uid, gid: gid, pid: None })
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios", target_os = "freebsd",
+              target_os = "dragonfly", target_os = "openbsd",
+              target_os = "netbsd")))]
+fn peer_cred(_fd: RawFd) -> io::Result<UCred> {
+    Err(io::Error::new(io::ErrorKind::Other,
+                       "peer credentials not supported on this platform"))
+}
+
+// Returns a buffer suitably sized and aligned to hold a control message
+// with `n` file descriptors.
+fn cmsg_buffer(n: usize) -> Vec<libc::cmsghdr> {
+    let bytes = unsafe {
+        libc::CMSG_SPACE((n * mem::size_of::<RawFd>()) as libc::c_uint)
+    } as usize;
+    let size = mem::size_of::<libc::cmsghdr>();
+    let zero: libc::cmsghdr = unsafe { mem::zeroed() };
+    vec![zero; (bytes + size - 1) / size]
+}
+
+fn send_fds(fd: RawFd, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+    if buf.len() == 0 && fds.len() > 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  "file descriptors must be sent with data"))
+    }
+    let mut iov = libc::iovec {
+        iov_base: buf.as_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    let mut control = cmsg_buffer(fds.len());
+    unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        if fds.len() > 0 {
+            let len = fds.len() * mem::size_of::<RawFd>();
+            msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = libc::CMSG_SPACE(len as libc::c_uint) as _;
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(len as libc::c_uint) as _;
+            ptr::copy_nonoverlapping(fds.as_ptr() as *const u8,
+                                     libc::CMSG_DATA(cmsg),
+                                     len);
+        }
+        cvt(libc::sendmsg(fd, &msg, 0))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const RECV_FLAGS: libc::c_int = libc::MSG_CMSG_CLOEXEC;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const RECV_FLAGS: libc::c_int = 0;
+
+fn recv_fds(fd: RawFd, buf: &mut [u8], fds: &mut [RawFd])
+            -> io::Result<(usize, usize)> {
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    let mut control = cmsg_buffer(fds.len());
+    unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        if fds.len() > 0 {
+            msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen =
+                (control.len() * mem::size_of::<libc::cmsghdr>()) as _;
+        }
+        let n = try!(cvt(libc::recvmsg(fd, &mut msg, RECV_FLAGS)));
+
+        let mut received = 0;
+        let mut cmsg = if fds.len() > 0 {
+            libc::CMSG_FIRSTHDR(&msg)
+        } else {
+            ptr::null_mut()
+        };
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET &&
+               (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg);
+                let len = (*cmsg).cmsg_len as usize -
+                          (data as usize - cmsg as usize);
+                for i in 0..len / mem::size_of::<RawFd>() {
+                    let p = data.offset((i * mem::size_of::<RawFd>()) as isize);
+                    let new = ptr::read_unaligned(p as *const RawFd);
+                    if received < fds.len() {
+                        set_cloexec(new);
+                        fds[received] = new;
+                        received += 1;
+                    } else {
+                        libc::close(new);
+                    }
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+        Ok((n, received))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_cloexec(_fd: RawFd) {
+    // Already done by `MSG_CMSG_CLOEXEC`
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_cloexec(fd: RawFd) {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        if flags >= 0 {
+            libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+extern crate futures;
+extern crate futures_mio;
+
+use std::time::{Duration, Instant};
+
+use futures::Future;
+use futures_mio::LoopBuilder;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn configured_loop() {
+    let mut l = t!(LoopBuilder::new()
+                       .name("configured")
+                       .source_capacity(4)
+                       .events_per_poll(1)
+                       .timer_tick(Duration::from_millis(1))
+                       .timer_capacity(1)
+                       .cpu_affinity(0)
+                       .build());
+    assert_eq!(l.name(), Some("configured"));
+
+    // More sockets and timeouts than there's room for initially.
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let sockets = (0..10).map(|_| l.handle().udp_bind(&addr)).collect::<Vec<_>>();
+    let sockets = t!(l.run(futures::collect(sockets)));
+    assert_eq!(sockets.len(), 10);
+
+    let dur = Duration::from_millis(5);
+    let timeouts = (0..10).map(|_| {
+        l.handle().timeout(dur).and_then(|t| t)
+    }).collect::<Vec<_>>();
+    let start = Instant::now();
+    t!(l.run(futures::collect(timeouts)));
+    assert!(start.elapsed() >= dur);
+}
+
+#[test]
+fn max_sources() {
+    let mut l = t!(LoopBuilder::new().max_sources(2).build());
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let a = l.handle().udp_bind(&addr);
+    let a = t!(l.run(a));
+    let b = l.handle().udp_bind(&addr);
+    let b = t!(l.run(b));
+    let c = l.handle().udp_bind(&addr);
+    assert!(l.run(c).is_err());
+
+    // Dropping a source makes room for another.
+    drop(a);
+    t!(l.run(futures::finished::<(), ()>(())));
+    let c = l.handle().udp_bind(&addr);
+    let c = t!(l.run(c));
+    drop((b, c));
+}
@@ -0,0 +1,211 @@
+extern crate futures;
+extern crate futures_mio;
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, UdpSocket};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use futures_mio::{Loop, ResolverConfig};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+// Builds the response to `query`, with an A record for 1.2.3.4 or an AAAA
+// record for 2001:db8::1 depending on what was asked for.
+fn respond(query: &[u8], truncated: bool) -> Vec<u8> {
+    let mut pos = 12;
+    while query[pos] != 0 {
+        pos += query[pos] as usize + 1;
+    }
+    let qtype = query[pos + 2];
+
+    let mut resp = query.to_vec();
+    resp[2] = if truncated {0x83} else {0x81};
+    resp[3] = 0x80;
+    if truncated {
+        return resp
+    }
+    resp[7] = 1;
+    resp.extend_from_slice(&[0xc0, 12, 0, qtype, 0, 1, 0, 0, 0, 60]);
+    if qtype == 1 {
+        resp.extend_from_slice(&[0, 4, 1, 2, 3, 4]);
+    } else {
+        resp.extend_from_slice(&[0, 16, 0x20, 0x01, 0x0d, 0xb8]);
+        resp.extend_from_slice(&[0; 11]);
+        resp.push(1);
+    }
+    resp
+}
+
+// Starts a name server which answers over UDP, and returns its address along
+// with a count of the queries it has answered.
+fn udp_server(truncated: bool) -> (SocketAddr, Arc<AtomicUsize>) {
+    if truncated {
+        udp_server_with(|q| respond(q, true))
+    } else {
+        udp_server_with(|q| respond(q, false))
+    }
+}
+
+fn udp_server_with(respond: fn(&[u8]) -> Vec<u8>)
+                   -> (SocketAddr, Arc<AtomicUsize>) {
+    let socket = t!(UdpSocket::bind("127.0.0.1:0"));
+    let addr = t!(socket.local_addr());
+    let queries = Arc::new(AtomicUsize::new(0));
+    let queries2 = queries.clone();
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        loop {
+            let (n, peer) = t!(socket.recv_from(&mut buf));
+            queries2.fetch_add(1, Ordering::SeqCst);
+            t!(socket.send_to(&respond(&buf[..n]), &peer));
+        }
+    });
+    (addr, queries)
+}
+
+fn config(server: SocketAddr) -> ResolverConfig {
+    let mut config = ResolverConfig::new();
+    config.nameserver(server).set_timeout(Duration::from_secs(5));
+    config
+}
+
+#[test]
+fn lookup_and_cache() {
+    let (server, queries) = udp_server(false);
+    let mut l = t!(Loop::new());
+    let resolver = l.handle().resolver(config(server));
+
+    let addrs = t!(l.run(resolver.lookup("example.com")));
+    assert_eq!(addrs, vec![ip("1.2.3.4"), ip("2001:db8::1")]);
+    assert_eq!(queries.load(Ordering::SeqCst), 2);
+
+    let addrs = t!(l.run(resolver.lookup("EXAMPLE.com.")));
+    assert_eq!(addrs, vec![ip("1.2.3.4"), ip("2001:db8::1")]);
+    assert_eq!(queries.load(Ordering::SeqCst), 2);
+
+    resolver.clear_cache();
+    t!(l.run(resolver.lookup("example.com")));
+    assert_eq!(queries.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn hosts_and_literals() {
+    let mut l = t!(Loop::new());
+    let mut config = ResolverConfig::new();
+    config.host("myhost", ip("10.0.0.1"));
+    let resolver = l.handle().resolver(config);
+
+    let addrs = t!(l.run(resolver.lookup("MyHost")));
+    assert_eq!(addrs, vec![ip("10.0.0.1")]);
+    let addrs = t!(l.run(resolver.lookup("::1")));
+    assert_eq!(addrs, vec![ip("::1")]);
+    assert!(l.run(resolver.lookup("otherhost")).is_err());
+}
+
+#[test]
+fn truncated_falls_back_to_tcp() {
+    let (server, _) = udp_server(true);
+    let listener = t!(TcpListener::bind(&server));
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = t!(stream);
+            let mut len = [0; 2];
+            t!(stream.read_exact(&mut len));
+            let mut query = vec![0; (len[0] as usize) << 8 | len[1] as usize];
+            t!(stream.read_exact(&mut query));
+            let resp = respond(&query, false);
+            t!(stream.write_all(&[(resp.len() >> 8) as u8, resp.len() as u8]));
+            t!(stream.write_all(&resp));
+        }
+    });
+
+    let mut l = t!(Loop::new());
+    let resolver = l.handle().resolver(config(server));
+    let addrs = t!(l.run(resolver.lookup("example.com")));
+    assert_eq!(addrs, vec![ip("1.2.3.4"), ip("2001:db8::1")]);
+}
+
+#[test]
+fn timeout() {
+    // A socket which never responds.
+    let socket = t!(UdpSocket::bind("127.0.0.1:0"));
+    let mut config = config(t!(socket.local_addr()));
+    config.set_timeout(Duration::from_millis(100)).set_attempts(1);
+
+    let mut l = t!(Loop::new());
+    let resolver = l.handle().resolver(config);
+    match l.run(resolver.lookup("example.com")) {
+        Ok(addrs) => panic!("resolved to {:?}", addrs),
+        Err(e) => assert_eq!(e.kind(), ErrorKind::TimedOut),
+    }
+}
+
+#[test]
+fn mismatched_question_ignored() {
+    // Echo a different name in the question than the one asked about.
+    let (server, queries) = udp_server_with(|query| {
+        let mut resp = respond(query, false);
+        resp[13] = b'x';
+        resp
+    });
+    let mut config = config(server);
+    config.set_timeout(Duration::from_millis(100)).set_attempts(1);
+
+    let mut l = t!(Loop::new());
+    let resolver = l.handle().resolver(config);
+    match l.run(resolver.lookup("example.com")) {
+        Ok(addrs) => panic!("resolved to {:?}", addrs),
+        Err(e) => assert_eq!(e.kind(), ErrorKind::TimedOut),
+    }
+    assert_eq!(queries.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn only_records_for_queried_name() {
+    // Answer with an alias of the queried name, a record for the alias and a
+    // record for some unrelated name, in that order.
+    let (server, _) = udp_server_with(|query| {
+        let resp = respond(query, false);
+        let answer = resp.len() - if query[query.len() - 3] == 1 {16} else {28};
+        let mut out = resp[..answer].to_vec();
+        out[7] = 3;
+        // example.com CNAME www.example.com
+        out.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 6,
+                                3, b'w', b'w', b'w', 0xc0, 12]);
+        // www.example.com <address>, taken from the original answer
+        let alias = answer + 12;
+        out.extend_from_slice(&[0xc0, alias as u8]);
+        out.extend_from_slice(&resp[answer + 2..]);
+        // other <different address>
+        out.extend_from_slice(&[5, b'o', b't', b'h', b'e', b'r', 0]);
+        out.extend_from_slice(&resp[answer + 2..]);
+        *out.last_mut().unwrap() = 9;
+        out
+    });
+
+    let mut l = t!(Loop::new());
+    let resolver = l.handle().resolver(config(server));
+    let addrs = t!(l.run(resolver.lookup("example.com")));
+    assert_eq!(addrs, vec![ip("1.2.3.4"), ip("2001:db8::1")]);
+}
+
+#[test]
+fn system_localhost() {
+    // Found in /etc/hosts on Unix, and by the OS's resolver elsewhere.
+    let mut l = t!(Loop::new());
+    let resolver = l.handle().resolver(t!(ResolverConfig::system()));
+    let addrs = t!(l.run(resolver.lookup("localhost")));
+    assert!(addrs.iter().any(|a| a.is_loopback()), "{:?}", addrs);
+}
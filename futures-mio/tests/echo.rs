@@ -8,7 +8,7 @@ use std::io::{Read, Write};
 
 use futures::Future;
 use futures::stream::Stream;
-use futures_io::{copy, TaskIo};
+use futures_io::{copy, split};
 
 macro_rules! t {
     ($e:expr) => (match $e {
@@ -38,7 +38,7 @@ fn echo_server() {
 
     let clients = srv.incoming();
     let client = clients.into_future().map(|e| e.0.unwrap()).map_err(|e| e.0);
-    let halves = client.and_then(|s| TaskIo::new(s.0)).map(|i| i.split());
+    let halves = client.map(|s| split(s.0));
     let copied = halves.and_then(|(a, b)| copy(a, b));
 
     let amt = t!(l.run(copied));
@@ -0,0 +1,68 @@
+extern crate futures;
+extern crate futures_mio;
+
+use std::io::ErrorKind;
+use std::net::TcpListener;
+use std::thread;
+
+use futures_mio::{Loop, ResolverConfig};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn connect_ip() {
+    let mut l = t!(Loop::new());
+    let srv = t!(TcpListener::bind("127.0.0.1:0"));
+    let addr = t!(srv.local_addr());
+    let t = thread::spawn(move || {
+        t!(srv.accept()).0
+    });
+
+    let stream = l.handle().tcp_connect_host("127.0.0.1", addr.port());
+    let mine = t!(l.run(stream));
+    let theirs = t.join().unwrap();
+    assert_eq!(t!(mine.local_addr()), t!(theirs.peer_addr()));
+}
+
+#[test]
+fn falls_back_to_next_address() {
+    let mut l = t!(Loop::new());
+    let srv = t!(TcpListener::bind("127.0.0.1:0"));
+    let addr = t!(srv.local_addr());
+    let t = thread::spawn(move || {
+        t!(srv.accept()).0
+    });
+
+    // The first address either never answers or fails straight away, and
+    // either way the second one is tried.
+    let mut config = ResolverConfig::new();
+    config.host("example", "10.255.255.1".parse().unwrap())
+          .host("example", "127.0.0.1".parse().unwrap());
+    let resolver = l.handle().resolver(config);
+
+    let stream = resolver.tcp_connect("example", addr.port());
+    let mine = t!(l.run(stream));
+    let theirs = t.join().unwrap();
+    assert_eq!(t!(mine.peer_addr()), addr);
+    assert_eq!(t!(mine.local_addr()), t!(theirs.peer_addr()));
+}
+
+#[test]
+fn all_addresses_fail() {
+    let mut l = t!(Loop::new());
+    let addr = t!(t!(TcpListener::bind("127.0.0.1:0")).local_addr());
+
+    let mut config = ResolverConfig::new();
+    config.host("example", "127.0.0.1".parse().unwrap());
+    let resolver = l.handle().resolver(config);
+
+    match l.run(resolver.tcp_connect("example", addr.port())) {
+        Ok(_) => panic!("connected to a closed port"),
+        Err(e) => assert_eq!(e.kind(), ErrorKind::ConnectionRefused),
+    }
+}
@@ -0,0 +1,66 @@
+extern crate env_logger;
+extern crate futures;
+extern crate futures_mio;
+
+use std::time::{Instant, Duration};
+
+use futures::Future;
+use futures::stream::Stream;
+use futures_mio::MissedTickBehavior;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn interval_ticks() {
+    drop(env_logger::init());
+    let mut l = t!(futures_mio::Loop::new());
+    let dur = Duration::from_millis(10);
+    let start = Instant::now();
+    let ticks = l.handle().interval(dur).and_then(|i| {
+        i.take(3).collect()
+    });
+    let ticks = t!(l.run(ticks));
+    assert_eq!(ticks.len(), 3);
+    assert!(start.elapsed() >= dur * 3);
+    for pair in ticks.windows(2) {
+        assert_eq!(pair[1] - pair[0], dur);
+    }
+}
+
+#[test]
+fn interval_skips_missed_ticks() {
+    drop(env_logger::init());
+    let mut l = t!(futures_mio::Loop::new());
+    let dur = Duration::from_millis(10);
+    let start = Instant::now() + dur;
+    let ticks = l.handle().interval_at(start, dur).and_then(|mut i| {
+        i.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        // Fall a few periods behind before looking at the interval.
+        std::thread::sleep(dur * 4);
+        i.take(2).collect()
+    });
+    let ticks = t!(l.run(ticks));
+    assert_eq!(ticks[0], start);
+    assert!(ticks[1] - ticks[0] >= dur * 4);
+}
+
+#[test]
+fn delay_reset() {
+    drop(env_logger::init());
+    let mut l = t!(futures_mio::Loop::new());
+    let dur = Duration::from_millis(10);
+    let start = Instant::now();
+    let delay = l.handle().delay(Duration::from_secs(60)).and_then(|mut d| {
+        d.reset(Instant::now() + dur);
+        d
+    });
+    t!(l.run(delay));
+    let elapsed = start.elapsed();
+    assert!(elapsed >= dur);
+    assert!(elapsed < Duration::from_secs(60));
+}
@@ -0,0 +1,56 @@
+#![cfg(unix)]
+
+extern crate futures;
+extern crate futures_io;
+extern crate futures_mio;
+
+use std::process::{Command, Stdio};
+
+use futures::Future;
+use futures_io::{read_to_end, write_all};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn exit_status() {
+    let mut l = t!(futures_mio::Loop::new());
+    let child = l.handle().spawn_command(Command::new("sh").arg("-c")
+                                                           .arg("exit 3"));
+    let child = t!(l.run(child));
+    let status = t!(l.run(child));
+    assert_eq!(status.code(), Some(3));
+}
+
+#[test]
+fn piped_stdio() {
+    let mut l = t!(futures_mio::Loop::new());
+    let mut cmd = Command::new("cat");
+    cmd.stdin(Stdio::piped()).stdout(Stdio::piped());
+    let mut child = t!(l.run(l.handle().spawn_command(&mut cmd)));
+    assert!(child.stderr.is_none());
+
+    // Write more than fits in a pipe so both sides have to wait on each other.
+    let data = (0..256 * 1024).map(|i| i as u8).collect::<Vec<_>>();
+    let stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let write = write_all(stdin, data.clone()).map(|_| ());
+    let read = read_to_end(stdout, Vec::new());
+    let ((), output) = t!(l.run(write.join(read)));
+    assert!(output == data);
+
+    assert!(t!(l.run(child)).success());
+}
+
+#[test]
+fn kill() {
+    let mut l = t!(futures_mio::Loop::new());
+    let mut child = t!(l.run(l.handle().spawn_command(&mut Command::new("sleep")
+                                                                   .arg("1000"))));
+    t!(child.kill());
+    assert!(!t!(l.run(child)).success());
+}
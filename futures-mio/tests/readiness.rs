@@ -0,0 +1,52 @@
+extern crate futures;
+extern crate futures_io;
+extern crate futures_mio;
+extern crate mio;
+
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use futures::Future;
+use futures::stream::Stream;
+use futures_io::Ready;
+use futures_mio::{Loop, ReadinessStream, Source, Trigger};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+// Registers a listener with a pending connection which is never accepted, so
+// the listener stays readable, and checks that it's notified twice.
+fn notified_again(trigger: Trigger) {
+    let mut l = t!(Loop::new());
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let listener = t!(mio::tcp::TcpListener::bind(&addr));
+    let addr = t!(listener.local_addr());
+    let source = Source::with_interest(listener, Ready::Read, trigger);
+    assert_eq!(source.interest(), Ready::Read);
+    assert_eq!(source.trigger(), trigger);
+
+    let ready = ReadinessStream::new(l.handle(), Arc::new(source));
+    let ready = t!(l.run(ready));
+    let _socket = t!(TcpStream::connect(&addr));
+
+    let next = ready.into_future().map_err(|(e, _)| e);
+    let (r, ready) = t!(l.run(next));
+    assert_eq!(r, Some(Ready::Read));
+    let next = ready.into_future().map_err(|(e, _)| e);
+    let (r, _ready) = t!(l.run(next));
+    assert_eq!(r, Some(Ready::Read));
+}
+
+#[test]
+fn level() {
+    notified_again(Trigger::Level);
+}
+
+#[test]
+fn oneshot() {
+    notified_again(Trigger::Oneshot);
+}
@@ -0,0 +1,86 @@
+extern crate futures;
+extern crate futures_io;
+extern crate futures_mio;
+
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+use futures::Future;
+use futures_mio::{Balance, Loop, Runtime, RuntimeHandle};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn spawn() {
+    let rt = t!(Runtime::new(2));
+    let a = rt.handle().spawn_on(0, futures::finished::<i32, ()>(1));
+    let b = rt.handle().spawn_on(1, futures::finished::<i32, ()>(2));
+    let c = rt.handle().spawn(futures::finished::<i32, ()>(3));
+
+    let mut l = t!(Loop::new());
+    let (a, (b, c)) = t!(l.run(a.join(b.join(c))));
+    assert_eq!((a, b, c), (Ok(1), Ok(2), Ok(3)));
+    rt.shutdown_gracefully();
+}
+
+// Starts serving on a new listener, where each connection is held open until
+// the client closes it, and returns the listener's address.
+fn serve(rt: &RuntimeHandle, balance: Balance) -> std::net::SocketAddr {
+    let mut l = t!(Loop::new());
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let listener = rt.loop_handle(0).clone().tcp_listen(&addr);
+    let listener = t!(l.run(listener));
+    let addr = t!(listener.local_addr());
+    drop(rt.spawn_on(0, rt.serve(listener, balance, |stream, _, _| {
+        futures_io::read_to_end(stream, Vec::new()).map(|_| ())
+    })));
+    addr
+}
+
+fn wait_for(rt: &RuntimeHandle, connections: &[usize]) {
+    for _ in 0..500 {
+        let now = (0..rt.loops()).map(|i| rt.connections(i))
+                                 .collect::<Vec<_>>();
+        if now == connections {
+            return
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("never reached {:?} connections", connections);
+}
+
+#[test]
+fn round_robin() {
+    let rt = t!(Runtime::new(2));
+    let addr = serve(rt.handle(), Balance::RoundRobin);
+
+    let conns = (0..4).map(|_| t!(TcpStream::connect(&addr)))
+                      .collect::<Vec<_>>();
+    wait_for(rt.handle(), &[2, 2]);
+    drop(conns);
+    wait_for(rt.handle(), &[0, 0]);
+}
+
+#[test]
+fn least_connections() {
+    let rt = t!(Runtime::new(2));
+    let addr = serve(rt.handle(), Balance::LeastConnections);
+
+    let a = t!(TcpStream::connect(&addr));
+    wait_for(rt.handle(), &[1, 0]);
+    let b = t!(TcpStream::connect(&addr));
+    wait_for(rt.handle(), &[1, 1]);
+
+    // Once the first loop is the least busy it gets the next connection.
+    drop(a);
+    wait_for(rt.handle(), &[0, 1]);
+    let c = t!(TcpStream::connect(&addr));
+    wait_for(rt.handle(), &[1, 1]);
+    drop((b, c));
+}
@@ -0,0 +1,70 @@
+#![cfg(unix)]
+
+extern crate futures;
+extern crate futures_mio;
+extern crate libc;
+
+use std::sync::mpsc::channel;
+use std::thread;
+
+use futures::Future;
+use futures::stream::Stream;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+fn kill(signum: libc::c_int) {
+    unsafe {
+        assert_eq!(libc::kill(libc::getpid(), signum), 0);
+    }
+}
+
+#[test]
+fn multiple_streams() {
+    let mut l = t!(futures_mio::Loop::new());
+    let a = l.handle().signal(libc::SIGUSR1);
+    let b = l.handle().signal(libc::SIGUSR1);
+    let (a, b) = t!(l.run(a.join(b)));
+
+    // Several deliveries before anyone looks are coalesced.
+    kill(libc::SIGUSR1);
+    kill(libc::SIGUSR1);
+
+    let a = a.into_future().map_err(|e| e.0);
+    let b = b.into_future().map_err(|e| e.0);
+    let ((sa, _), (sb, _)) = t!(l.run(a.join(b)));
+    assert_eq!(sa, Some(libc::SIGUSR1));
+    assert_eq!(sb, Some(libc::SIGUSR1));
+}
+
+#[test]
+fn multiple_loops() {
+    let (tx, rx) = channel();
+    let t = thread::spawn(move || {
+        let mut l = t!(futures_mio::Loop::new());
+        let s = t!(l.run(l.handle().signal(libc::SIGUSR2)));
+        tx.send(()).unwrap();
+        let (sig, _) = t!(l.run(s.into_future().map_err(|e| e.0)));
+        assert_eq!(sig, Some(libc::SIGUSR2));
+    });
+
+    let mut l = t!(futures_mio::Loop::new());
+    let s = t!(l.run(l.handle().signal(libc::SIGUSR2)));
+    rx.recv().unwrap();
+    kill(libc::SIGUSR2);
+
+    let (sig, _) = t!(l.run(s.into_future().map_err(|e| e.0)));
+    assert_eq!(sig, Some(libc::SIGUSR2));
+    t.join().unwrap();
+}
+
+#[test]
+fn invalid_signals() {
+    let mut l = t!(futures_mio::Loop::new());
+    assert!(l.run(l.handle().signal(libc::SIGKILL)).is_err());
+    assert!(l.run(l.handle().signal(-1)).is_err());
+}
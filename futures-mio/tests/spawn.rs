@@ -0,0 +1,178 @@
+extern crate futures;
+extern crate futures_io;
+extern crate futures_mio;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::Future;
+use futures::stream::Stream;
+use futures_io::{read_exact, write_all};
+use futures_mio::{Loop, LoopBuilder};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn spawn_onto_thread() {
+    let (remote, thread) = t!(LoopBuilder::new().name("remote").spawn_thread());
+    let name = remote.spawn(futures::lazy(|| {
+        let name = thread::current().name().map(|s| s.to_string());
+        futures::finished::<_, ()>(name)
+    }));
+
+    let mut l = t!(Loop::new());
+    let name = t!(l.run(name));
+    assert_eq!(name, Ok(Some("remote".to_string())));
+
+    remote.shutdown();
+    t!(thread.join());
+}
+
+#[test]
+fn spawn_error() {
+    let (remote, thread) = t!(Loop::spawn_thread());
+    let res = remote.spawn(futures::failed::<(), _>(3));
+
+    let mut l = t!(Loop::new());
+    assert_eq!(t!(l.run(res)), Err(3));
+
+    remote.shutdown();
+    t!(thread.join());
+}
+
+#[test]
+fn graceful_shutdown_waits() {
+    let (remote, thread) = t!(Loop::spawn_thread());
+    let (tx, rx) = mpsc::channel();
+    let timeout = remote.clone().timeout(Duration::from_millis(50));
+    drop(remote.spawn(timeout.and_then(|t| t).map(move |()| {
+        tx.send(()).unwrap();
+    })));
+
+    remote.shutdown_gracefully();
+    t!(thread.join());
+    t!(rx.try_recv());
+}
+
+#[test]
+fn graceful_shutdown_deadline() {
+    let (remote, thread) = t!(Loop::spawn_thread());
+    let timeout = remote.clone().timeout(Duration::from_secs(10));
+    drop(remote.spawn(timeout.and_then(|t| t)));
+
+    let start = Instant::now();
+    remote.shutdown_gracefully_timeout(Duration::from_millis(50));
+    t!(thread.join());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn graceful_shutdown_finishes_connections() {
+    let mut l = t!(Loop::new());
+    let handle = l.handle();
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let listener = t!(l.run(handle.clone().tcp_listen(&addr)));
+    let addr = t!(listener.local_addr());
+
+    let client = thread::spawn(move || {
+        let mut s = t!(TcpStream::connect(&addr));
+        t!(s.write_all(b"a"));
+        let mut buf = Vec::new();
+        t!(s.read_to_end(&mut buf));
+        buf
+    });
+
+    // The connection has the loop shut down once it's read its request, and
+    // only then takes a while to respond.
+    let server = listener.incoming().for_each(move |(socket, _)| {
+        let remote = handle.clone();
+        let conn = read_exact(socket, [0; 1]).and_then(move |(socket, _)| {
+            remote.shutdown_gracefully();
+            let timeout = remote.timeout(Duration::from_millis(100));
+            timeout.and_then(|t| t).map(move |()| socket)
+        }).and_then(|socket| write_all(socket, b"b"));
+        drop(handle.spawn(conn));
+        Ok(())
+    });
+    t!(l.run(server));
+
+    assert_eq!(client.join().unwrap(), b"b");
+}
+
+#[test]
+fn graceful_shutdown_waits_for_run() {
+    let mut l = t!(Loop::new());
+    let handle = l.handle();
+    let timeout = handle.clone().timeout(Duration::from_millis(50));
+    handle.shutdown_gracefully();
+    t!(l.run(timeout.and_then(|t| t)));
+}
+
+#[test]
+fn shutdown_signal() {
+    let (remote, thread) = t!(Loop::spawn_thread());
+    let (tx, rx) = mpsc::channel();
+    drop(remote.spawn(remote.shutdown_signal().map(move |()| {
+        tx.send(()).unwrap();
+    })));
+    thread::sleep(Duration::from_millis(50));
+    assert!(rx.try_recv().is_err());
+
+    remote.shutdown_gracefully();
+    t!(thread.join());
+    t!(rx.try_recv());
+}
+
+#[test]
+fn graceful_shutdown_stops_accepting() {
+    let (remote, thread) = t!(Loop::spawn_thread());
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let listener = remote.spawn(remote.clone().tcp_listen(&addr));
+
+    let mut l = t!(Loop::new());
+    let listener = t!(t!(l.run(listener)));
+    let incoming = remote.spawn(listener.incoming().for_each(|_| Ok(())));
+
+    // Keep the loop busy for a little while, so there's a chance to try to
+    // register something new while it's shutting down.
+    let timeout = remote.clone().timeout(Duration::from_millis(200));
+    drop(remote.spawn(timeout.and_then(|t| t)));
+
+    remote.shutdown_gracefully();
+    let rejected = remote.spawn(remote.clone().tcp_listen(&addr));
+    assert!(t!(l.run(rejected)).is_err());
+
+    t!(thread.join());
+    t!(t!(l.run(incoming)));
+}
+
+#[test]
+fn run_again_after_graceful_shutdown() {
+    let mut l = t!(Loop::new());
+    let handle = l.handle();
+
+    // A spawned future which outlives the first run keeps the loop draining
+    // until the deadline passes.
+    let timeout = handle.clone().timeout(Duration::from_secs(10));
+    drop(handle.spawn(timeout.and_then(|t| t)));
+    let remote = handle.clone();
+    let timeout = handle.clone().timeout(Duration::from_millis(10));
+    t!(l.run(timeout.and_then(|t| t).map(move |()| {
+        remote.shutdown_gracefully_timeout(Duration::from_millis(10));
+    })));
+    thread::sleep(Duration::from_millis(50));
+
+    // Running again neither stops straight away nor refuses new I/O objects.
+    let timeout = handle.clone().timeout(Duration::from_millis(50));
+    t!(l.run(timeout.and_then(|t| t)));
+    let addr = "127.0.0.1:0".parse().unwrap();
+    t!(l.run(handle.clone().tcp_listen(&addr)));
+}
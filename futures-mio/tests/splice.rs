@@ -0,0 +1,85 @@
+extern crate futures;
+extern crate futures_mio;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process;
+use std::thread;
+
+use futures::Future;
+use futures::stream::Stream;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+fn data() -> Vec<u8> {
+    (0..256 * 1024).map(|i| i as u8).collect()
+}
+
+#[test]
+fn copy_splice() {
+    let mut l = t!(futures_mio::Loop::new());
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let src = t!(l.run(l.handle().tcp_listen(&addr)));
+    let dst = t!(l.run(l.handle().tcp_listen(&addr)));
+    let src_addr = t!(src.local_addr());
+    let dst_addr = t!(dst.local_addr());
+
+    let writer = thread::spawn(move || {
+        let mut s = t!(TcpStream::connect(&src_addr));
+        t!(s.write_all(&data()));
+    });
+    let reader = thread::spawn(move || {
+        let mut s = t!(TcpStream::connect(&dst_addr));
+        let mut v = Vec::new();
+        t!(s.read_to_end(&mut v));
+        v
+    });
+
+    let src = src.incoming().into_future().map(|e| e.0.unwrap().0);
+    let dst = dst.incoming().into_future().map(|e| e.0.unwrap().0);
+    let copied = src.join(dst).map_err(|e| e.0).and_then(|(a, b)| {
+        futures_mio::copy_splice(a, b)
+    });
+    let amt = t!(l.run(copied));
+
+    writer.join().unwrap();
+    assert!(reader.join().unwrap() == data());
+    assert_eq!(amt, data().len() as u64);
+}
+
+#[test]
+fn send_file() {
+    let path = env::temp_dir().join(format!("futures-mio-send-file-{}",
+                                            process::id()));
+    t!(t!(File::create(&path)).write_all(&data()));
+
+    let mut l = t!(futures_mio::Loop::new());
+    let srv = l.handle().tcp_listen(&"127.0.0.1:0".parse().unwrap());
+    let srv = t!(l.run(srv));
+    let addr = t!(srv.local_addr());
+
+    let reader = thread::spawn(move || {
+        let mut s = t!(TcpStream::connect(&addr));
+        let mut v = Vec::new();
+        t!(s.read_to_end(&mut v));
+        v
+    });
+
+    let file = t!(File::open(&path));
+    let client = srv.incoming().into_future().map_err(|e| e.0);
+    let sent = client.and_then(move |(s, _)| {
+        futures_mio::send_file(file, s.unwrap().0)
+    });
+    let amt = t!(l.run(sent));
+
+    assert!(reader.join().unwrap() == data());
+    assert_eq!(amt, data().len() as u64);
+    t!(fs::remove_file(&path));
+}
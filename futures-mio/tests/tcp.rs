@@ -1,6 +1,7 @@
 extern crate futures;
 extern crate futures_mio;
 
+use std::io::{IoSlice, Write};
 use std::net::{TcpListener, TcpStream};
 use std::sync::mpsc::channel;
 use std::thread;
@@ -79,3 +80,25 @@ fn accept2() {
     mine.unwrap();
     t.join().unwrap();
 }
+
+#[cfg(unix)]
+#[test]
+fn write_vectored_many_slices() {
+    let mut l = t!(futures_mio::Loop::new());
+    let srv = t!(TcpListener::bind("127.0.0.1:0"));
+    let addr = t!(srv.local_addr());
+    let t = thread::spawn(move || {
+        t!(srv.accept()).0
+    });
+
+    let stream = l.handle().tcp_connect(&addr);
+    let mut mine = t!(l.run(stream));
+    let _theirs = t.join().unwrap();
+
+    // More slices than `writev` accepts at once is a partial write rather
+    // than an error.
+    let data = [0u8; 2000];
+    let bufs = data.chunks(1).map(IoSlice::new).collect::<Vec<_>>();
+    let n = t!(mine.write_vectored(&bufs));
+    assert!(n > 0 && n <= 1024);
+}
@@ -0,0 +1,66 @@
+extern crate futures;
+extern crate futures_mio;
+
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn bind_before_connect() {
+    let mut l = t!(futures_mio::Loop::new());
+    let srv = t!(TcpListener::bind("127.0.0.1:0"));
+    let addr = t!(srv.local_addr());
+    let t = thread::spawn(move || {
+        t!(srv.accept()).0
+    });
+
+    let mut builder = l.handle().tcp_builder();
+    builder.set_bind("127.0.0.1:0".parse().unwrap())
+           .set_linger(Some(Duration::from_secs(1)))
+           .set_ttl(42);
+    assert_eq!(builder.ttl(), Some(42));
+    assert_eq!(builder.linger(), Some(Some(Duration::from_secs(1))));
+
+    let stream = builder.connect(&addr);
+    let mine = t!(l.run(stream));
+    let theirs = t.join().unwrap();
+
+    assert_eq!(t!(mine.local_addr()), t!(theirs.peer_addr()));
+    assert_eq!(t!(mine.ttl()), 42);
+    assert_eq!(t!(mine.linger()), Some(Duration::from_secs(1)));
+    t!(mine.set_ttl(43));
+    assert_eq!(t!(mine.ttl()), 43);
+    t!(mine.set_linger(Some(Duration::from_millis(500))));
+    assert_eq!(t!(mine.linger()), Some(Duration::from_secs(1)));
+}
+
+#[cfg(unix)]
+#[test]
+fn reuse_port() {
+    let mut l = t!(futures_mio::Loop::new());
+    let mut builder = l.handle().tcp_builder();
+    builder.set_reuse_address(true).set_reuse_port(true).set_backlog(16);
+    assert!(builder.reuse_port());
+    assert_eq!(builder.backlog(), 16);
+    assert_eq!(builder.linger(), None);
+    builder.set_linger(None);
+    assert_eq!(builder.linger(), Some(None));
+
+    let a = builder.listen(&"127.0.0.1:0".parse().unwrap());
+    let a = t!(l.run(a));
+    let addr = t!(a.local_addr());
+    assert!(t!(a.reuse_address()));
+    assert!(t!(a.reuse_port()));
+
+    // A second listener can bind to the same address.
+    let b = builder.listen(&addr);
+    let b = t!(l.run(b));
+    assert_eq!(t!(b.local_addr()), addr);
+}
@@ -0,0 +1,128 @@
+#![cfg(unix)]
+
+extern crate futures;
+extern crate futures_io;
+extern crate futures_mio;
+extern crate libc;
+
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::net;
+use std::os::unix::prelude::*;
+use std::thread;
+
+use futures::Future;
+use futures::stream::Stream;
+use futures_io::{read_exact, write_all};
+use futures_mio::{UnixDatagram, UnixStream};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn listen_and_connect() {
+    let path = env::temp_dir().join(format!("futures-mio-{}.sock",
+                                            unsafe { libc::getpid() }));
+    drop(fs::remove_file(&path));
+
+    let mut l = t!(futures_mio::Loop::new());
+    let srv = t!(l.run(l.handle().unix_listen(&path)));
+
+    let path2 = path.clone();
+    let t = thread::spawn(move || {
+        let mut s = t!(net::UnixStream::connect(&path2));
+        t!(s.write_all(b"ping"));
+        let mut buf = [0; 4];
+        t!(s.read_exact(&mut buf));
+        assert_eq!(&buf, b"pong");
+    });
+
+    let client = srv.incoming().into_future().map_err(|e| e.0);
+    let client = client.and_then(|(c, _)| {
+        let (c, _addr) = c.unwrap();
+        read_exact(c, [0; 4])
+    }).and_then(|(c, buf)| {
+        assert_eq!(&buf, b"ping");
+        write_all(c, b"pong")
+    });
+    t!(l.run(client));
+    t.join().unwrap();
+    t!(fs::remove_file(&path));
+}
+
+#[test]
+fn connect() {
+    let path = env::temp_dir().join(format!("futures-mio-connect-{}.sock",
+                                            unsafe { libc::getpid() }));
+    drop(fs::remove_file(&path));
+    let srv = t!(net::UnixListener::bind(&path));
+
+    let mut l = t!(futures_mio::Loop::new());
+    let client = l.handle().unix_connect(&path).and_then(|c| {
+        write_all(c, b"ping")
+    });
+    t!(l.run(client));
+    let (mut theirs, _) = t!(srv.accept());
+    let mut buf = [0; 4];
+    t!(theirs.read_exact(&mut buf));
+    assert_eq!(&buf, b"ping");
+
+    t!(fs::remove_file(&path));
+    assert!(l.run(l.handle().unix_connect(&path)).is_err());
+}
+
+#[test]
+fn pair_and_peer_cred() {
+    let mut l = t!(futures_mio::Loop::new());
+    let (a, b) = t!(l.run(UnixStream::pair(l.handle())));
+
+    let cred = t!(a.peer_cred());
+    assert_eq!(cred.uid, unsafe { libc::getuid() });
+    assert_eq!(cred.gid, unsafe { libc::getgid() });
+
+    let write = write_all(a, b"hello");
+    let read = read_exact(b, [0; 5]);
+    let (_, (_, buf)) = t!(l.run(write.join(read)));
+    assert_eq!(&buf, b"hello");
+}
+
+#[test]
+fn pass_fds() {
+    let mut l = t!(futures_mio::Loop::new());
+    let (a, b) = t!(l.run(UnixStream::pair(l.handle())));
+    let (mut c, d) = t!(net::UnixStream::pair());
+
+    assert_eq!(t!(a.send_fds(b"x", &[d.as_raw_fd()])), 1);
+    drop(d);
+
+    let (_, b) = t!(l.run(b.into_future().map_err(|e| e.0)));
+    let mut buf = [0; 4];
+    let mut fds = [0; 2];
+    assert_eq!(t!(b.recv_fds(&mut buf, &mut fds)), (1, 1));
+    assert_eq!(buf[0], b'x');
+
+    let mut d = unsafe { net::UnixStream::from_raw_fd(fds[0]) };
+    t!(d.write_all(b"hi"));
+    let mut buf = [0; 2];
+    t!(c.read_exact(&mut buf));
+    assert_eq!(&buf, b"hi");
+}
+
+#[test]
+fn datagrams() {
+    let mut l = t!(futures_mio::Loop::new());
+    let (a, b) = t!(l.run(UnixDatagram::pair(l.handle())));
+
+    assert_eq!(t!(a.send(b"1234")), 4);
+    let (br, b) = t!(l.run(b.into_future().map_err(|e| e.0)));
+    assert!(br.unwrap().is_read());
+
+    let mut buf = [0; 32];
+    assert_eq!(t!(b.recv(&mut buf)), 4);
+    assert_eq!(&buf[..4], b"1234");
+}
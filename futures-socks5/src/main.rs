@@ -21,11 +21,11 @@
 //!   through it. This is achieved by waiting for both ends of the proxy to be
 //!   ready, and then the transfer is done.
 //!
-//! * Initiating a SOCKS proxy connection may involve a DNS lookup, and
-//!   currently this is done using the standard library, which does blocking
-//!   I/O. To prevent the event loop from blocking a worker thread pool is used
-//!   to execute DNS lookups and the results are communicated back to the main
-//!   event loop thread.
+//! * Initiating a SOCKS proxy connection may involve a DNS lookup, which is
+//!   done with the asynchronous resolver from `futures_mio`, so the lookups
+//!   happen on the event loop alongside all the other I/O without blocking it.
+//!   (On platforms other than Unix the resolver defers to the operating
+//!   system's resolver instead, on a separate thread.)
 //!
 //! * The entire SOCKS handshake is implemented using the various combinators in
 //!   the `futures` crate as well as the `futures_io` crate. The actual proxying
@@ -45,7 +45,6 @@ extern crate env_logger;
 extern crate futures;
 extern crate futures_io;
 extern crate futures_mio;
-extern crate futures_cpupool;
 
 use std::cell::RefCell;
 use std::env;
@@ -57,10 +56,10 @@ use std::time::Duration;
 
 use futures::{Future, Task, Poll};
 use futures::stream::Stream;
-use futures_cpupool::CpuPool;
-use futures_io::{IoFuture, read_exact, write_all, Window};
-use futures_io::{TaskIo, TaskIoRead, TaskIoWrite, ReadTask, WriteTask};
-use futures_mio::{Loop, LoopData, LoopHandle, TcpStream};
+use futures_io::{IoFuture, IoTimeout, read_exact, write_all, Window};
+use futures_io::{split, ReadHalf, WriteHalf, ReadTask, WriteTask};
+use futures_mio::{Loop, LoopData, LoopHandle, Resolver, ResolverConfig};
+use futures_mio::TcpStream;
 
 fn main() {
     drop(env_logger::init());
@@ -71,11 +70,12 @@ fn main() {
     let addr = addr.parse::<SocketAddr>().unwrap();
 
     // Initialize the various data structures we're going to use in our server.
-    // Here we create the global event loop, our worker thread pool to perform
-    // DNS name resolution, the global buffer that all threads will read/write
-    // into, and finally binding the TCP listener itself.
+    // Here we create the global event loop, a resolver to perform DNS name
+    // resolution configured like the system's own, the global buffer that all
+    // threads will read/write into, and finally binding the TCP listener
+    // itself.
     let mut lp = Loop::new().unwrap();
-    let pool = CpuPool::new(4);
+    let resolver = lp.handle().resolver(ResolverConfig::system().unwrap());
     let buffer = GlobalBuffer::new(&lp, 64 * 1024);
     let listener = lp.handle().tcp_listen(&addr);
     let handle = lp.handle();
@@ -92,25 +92,28 @@ fn main() {
     // with references to the resources we created above.
     //
     // After the clients are created, we register a completion callback with
-    // them to print out what happened, and then we crucially call the
-    // `.forget()` function which allows the client to progress concurrently to
-    // the main server itself.
+    // them to print out what happened, and then we crucially spawn them onto
+    // the event loop with `spawn`, which allows the client to progress
+    // concurrently to the main server itself. The loop keeps track of spawned
+    // futures, so if it's shut down gracefully it waits for the clients to
+    // finish.
     //
-    // Note that the usage of `then` and `.forget()` also disconnects errors in
+    // Note that the usage of `then` and `spawn` also disconnects errors in
     // the clients from errors in the server itself. If any client hits an I/O
     // error it'll cancel that one client, but all others will be unaffected.
     let server = buffer.join(listener).and_then(move |(buffer, listener)| {
         println!("Listening for socks5 proxy connections on {}", addr);
+        let spawner = handle.clone();
         let clients = listener.incoming().map(move |(socket, addr)| {
             (Client {
                 buffer: buffer.clone(),
-                pool: pool.clone(),
+                resolver: resolver.clone(),
                 handle: handle.clone(),
             }.serve(socket), addr)
         });
 
-        clients.for_each(|(client, addr)| {
-            client.then(move |res| {
+        clients.for_each(move |(client, addr)| {
+            drop(spawner.spawn(client.then(move |res| {
                 match res {
                     Ok((a, b)) => {
                         println!("proxied {}/{} bytes for {}", a, b, addr)
@@ -118,7 +121,7 @@ fn main() {
                     Err(e) => println!("error for {}: {}", addr, e),
                 }
                 futures::finished::<_, io::Error>(())
-            }).forget();
+            })));
             Ok(())
         })
     });
@@ -131,6 +134,10 @@ fn main() {
     lp.run(server).unwrap();
 }
 
+// The number of seconds a proxied connection may go without transferring any
+// data before it's closed.
+const IDLE_TIMEOUT: u64 = 5 * 60;
+
 // A simple global buffer abstraction to mostly just avoid writing the `inner`
 // type in multiple places!
 //
@@ -158,7 +165,7 @@ impl GlobalBuffer {
 // lifetime.
 struct Client {
     buffer: GlobalBuffer,
-    pool: CpuPool,
+    resolver: Resolver,
     handle: LoopHandle,
 }
 
@@ -278,7 +285,6 @@ impl Client {
         // to implement that particular address format.
         let resv = command.and_then(|c| read_exact(c, [0u8]).map(|c| c.0));
         let atyp = resv.and_then(|c| read_exact(c, [0u8]));
-        let pool = self.pool.clone();
         let addr = atyp.and_then(|(c, buf)| {
             match buf[0] {
                 // For IPv4 addresses, we read the 4 bytes for the address as
@@ -288,7 +294,7 @@ impl Client {
                         let addr = Ipv4Addr::new(buf[0], buf[1], buf[2], buf[3]);
                         let port = ((buf[4] as u16) << 8) | (buf[5] as u16);
                         let addr = SocketAddrV4::new(addr, port);
-                        (c, SocketAddr::V4(addr))
+                        (c, Target::Addr(SocketAddr::V4(addr)))
                     }).boxed()
                 }
 
@@ -307,7 +313,7 @@ impl Client {
                         let addr = Ipv6Addr::new(a, b, c, d, e, f, g, h);
                         let port = ((buf[16] as u16) << 8) | (buf[17] as u16);
                         let addr = SocketAddrV6::new(addr, port, 0, 0);
-                        (conn, SocketAddr::V6(addr))
+                        (conn, Target::Addr(SocketAddr::V6(addr)))
                     }).boxed()
                 }
 
@@ -316,38 +322,25 @@ impl Client {
                 // clients to perform hostname lookups within the context of the
                 // proxy server rather than the client itself.
                 //
-                // As of the time of this writing there's not a DNS library
-                // based on futures, but we can take this opportunity to show
-                // how to execute otherwise-blocking computations! The basic
-                // idea is that we'll farm out the DNS resolution work to the
-                // standard library, which performs blocking I/O, onto dedicated
-                // threads for performing these blocking syscalls.
-                //
-                // This'll incur some overhead as we're communicating with
-                // another thread, so this would of course be better to
-                // implement with just pure futures, but that may not always be
-                // possible!
+                // Resolving the name is done with our `resolver`, which speaks
+                // DNS with name servers over the same event loop as all our
+                // other I/O, so no thread is blocked while we wait for the
+                // answer. A host may have several addresses though, so rather
+                // than picking one here we hold on to the name and let the
+                // resolver pick below, when connecting.
                 //
                 // In any case, though, the protocol here is to have the next
                 // byte indicate how many bytes the hostname contains, followed
                 // by the hostname and two bytes for the port. To read this
                 // data, we execute two respective `read_exact` operations to
-                // fill up a buffer for the hostname.
-                //
-                // Finally, to perform the "interesting" part, we use our handle
-                // to the thread pool (the `pool` variable), to execute an
-                // arbitrary computation on a separate thread. This for now is
-                // just the `resolve` function returning an
-                // `io::Result<SocketAddr>`, and then we transform the future
-                // type back to match what's above as well.
+                // fill up a buffer for the hostname, and then decode it.
                 v5::ATYP_DOMAIN => {
                     read_exact(c, [0u8]).and_then(|(conn, buf)| {
                         read_exact(conn, vec![0u8; buf[0] as usize + 2])
-                    }).and_then(move |(conn, buf)| {
-                        pool.execute(move || resolve(&buf))
-                            .map_err(|e| other(&format!("panic: {:?}", e)))
-                            .and_then(|e| e)
-                            .map(|addr| (conn, addr))
+                    }).and_then(|(conn, buf)| {
+                        domain(&buf).map(|(host, port)| {
+                            (conn, Target::Host(host, port))
+                        })
                     }).boxed()
                 }
                 n => {
@@ -357,22 +350,33 @@ impl Client {
             }
         }).boxed();
 
-        // Now that we've got a socket address to connect to, let's actually
-        // create a connection to that socket!
+        // Now that we've got a socket address or host name to connect to, let's
+        // actually create a connection to it!
         //
-        // To do this, we use our `handle` field, a handle to the event loop, to
-        // issue a connection to the address we've figured out we're going to
-        // connect to. Note that this `tcp_connect` method itself returns a
-        // future resolving to a `TcpStream`, representing how long it takes to
-        // initiate a TCP connection to the remote.
+        // For an address, we use our `handle` field, a handle to the event
+        // loop, to issue a connection to the address we've figured out we're
+        // going to connect to. Note that this `tcp_connect` method itself
+        // returns a future resolving to a `TcpStream`, representing how long it
+        // takes to initiate a TCP connection to the remote. For a host name,
+        // our resolver looks up its addresses and races connections to them,
+        // resolving to whichever connects first.
         //
         // We wait for the TCP connect to get fully resolved before progressing
         // to the next stage of the SOCKSv5 handshake, but we keep ahold of any
-        // possible error in the connection phase to handle it in a moment.
+        // possible error in the connection phase to handle it in a moment,
+        // along with an address to report if the connection failed.
         let handle = self.handle.clone();
-        let connected = addr.and_then(|(c, addr)| {
-            debug!("proxying to {}", addr);
-            handle.tcp_connect(&addr).then(move |c2| Ok((c, c2, addr)))
+        let resolver = self.resolver.clone();
+        let connected = addr.and_then(|(c, target)| {
+            debug!("proxying to {:?}", target);
+            let (c2, addr) = match target {
+                Target::Addr(addr) => (handle.tcp_connect(&addr), addr),
+                Target::Host(host, port) => {
+                    let unspecified = "0.0.0.0:0".parse().unwrap();
+                    (resolver.tcp_connect(&host, port), unspecified)
+                }
+            };
+            c2.then(move |c2| Ok((c, c2, addr)))
         }).boxed();
 
         // Once we've gotten to this point, we're ready for the final part of
@@ -499,27 +503,37 @@ impl Client {
         // and for between the two connections. That is, data is read from `c1`
         // and written to `c2`, and vice versa.
         //
-        // To accomplish this, we use the `TaskIo` abstraction so "split" both
-        // of these connections into their read/write halves. This
-        // simultaneously associates the I/O with whatever task performed the
-        // split, and also gives separately owned pieces that can be moved into
-        // independent futures.
+        // To accomplish this, we use the `split` function to split both of
+        // these connections into their read/write halves. This gives
+        // separately owned pieces that can be moved into independent futures,
+        // which could even run on different tasks if we wanted.
         //
         // After the split, we create two of our custom `Transfer` futures
         // (defined below) which will be responsible for one half of the
         // read/write connection. The final step of this method is to then join
         // the completion of these two futures together. That is, the proxied
         // connection isn't done until both halves have complete their transfer.
+        //
+        // Before splitting, though, each connection is wrapped in an
+        // `IoTimeout`. The handshake timeout above doesn't help us once data
+        // is flowing, and a peer which simply stops sending (or stops reading)
+        // would otherwise keep the proxied connection open forever. The
+        // `IoTimeout` fails any read or write with `TimedOut` once the
+        // connection has been idle for too long, using our `LoopHandle` as
+        // its clock.
         let buffer = self.buffer.clone();
-        pair.and_then(|(c1, c2)| {
-            TaskIo::new(c1).join(TaskIo::new(c2)).and_then(move |(c1, c2)| {
-                let (c1r, c1w) = c1.split();
-                let (c2r, c2w) = c2.split();
-
-                let half1 = Transfer::new(c1r, c2w, buffer.clone());
-                let half2 = Transfer::new(c2r, c1w, buffer.clone());
-                half1.join(half2)
-            })
+        let handle = self.handle.clone();
+        pair.and_then(move |(c1, c2)| {
+            let mut c1 = IoTimeout::new(c1, handle.clone());
+            let mut c2 = IoTimeout::new(c2, handle);
+            c1.set_idle_timeout(Some(Duration::new(IDLE_TIMEOUT, 0)));
+            c2.set_idle_timeout(Some(Duration::new(IDLE_TIMEOUT, 0)));
+            let (c1r, c1w) = split(c1);
+            let (c2r, c2w) = split(c2);
+
+            let half1 = Transfer::new(c1r, c2w, buffer.clone());
+            let half2 = Transfer::new(c2r, c1w, buffer);
+            half1.join(half2)
         }).boxed()
     }
 }
@@ -534,8 +548,8 @@ impl Client {
 /// be implemented with just a trait impl!
 struct Transfer {
     // The two I/O objects we'll be reading.
-    reader: TaskIoRead<TcpStream>,
-    writer: TaskIoWrite<TcpStream>,
+    reader: ReadHalf<IoTimeout<TcpStream>>,
+    writer: WriteHalf<IoTimeout<TcpStream>>,
 
     // The shared global buffer that all connections on our server are using.
     buf: GlobalBuffer,
@@ -548,8 +562,8 @@ struct Transfer {
 }
 
 impl Transfer {
-    fn new(reader: TaskIoRead<TcpStream>,
-           writer: TaskIoWrite<TcpStream>,
+    fn new(reader: ReadHalf<IoTimeout<TcpStream>>,
+           writer: WriteHalf<IoTimeout<TcpStream>>,
            buffer: GlobalBuffer) -> Transfer {
         Transfer {
             reader: reader,
@@ -716,18 +730,16 @@ fn other(desc: &str) -> io::Error {
     io::Error::new(io::ErrorKind::Other, desc)
 }
 
-/// Performs *blocking* name resolution of the DNS name in `addr_buf`, returning
-/// the first `SocketAddr` it corresponds to.
-///
-/// This method is called for resolving a proxy address of type `ATYP_DOMAIN`
-/// above, and to prevent from blocking the event loop this code is executed on
-/// a separate thread pool.
-///
-/// This method just uses the standard library's `to_socket_addrs` method to
-/// perform name resolution, and is otherwise relatively straightforward.
-fn resolve(addr_buf: &[u8]) -> io::Result<SocketAddr> {
-    use std::net::ToSocketAddrs;
+/// Where a client asked to be proxied to.
+#[derive(Debug)]
+enum Target {
+    Addr(SocketAddr),
+    Host(String, u16),
+}
 
+/// Decodes the DNS name and port in `addr_buf`, sent by a client for an
+/// address of type `ATYP_DOMAIN` above.
+fn domain(addr_buf: &[u8]) -> io::Result<(String, u16)> {
     // The last two bytes of the buffer are the port, and the other parts of it
     // are the hostname.
     let hostname = &addr_buf[..addr_buf.len() - 2];
@@ -736,13 +748,7 @@ fn resolve(addr_buf: &[u8]) -> io::Result<SocketAddr> {
     }));
     let pos = addr_buf.len() - 2;
     let port = ((addr_buf[pos] as u16) << 8) | (addr_buf[pos + 1] as u16);
-
-    let hostname = format!("{}:{}", hostname, port);
-
-    let mut addrs = try!(hostname.to_socket_addrs());
-    addrs.next().ok_or_else(|| {
-        other("no addresses found during name resolution")
-    })
+    Ok((hostname.to_string(), port))
 }
 
 
@@ -4,7 +4,12 @@
 //! is not currently used much by futures beyond `DEFAULT`.
 
 use std::cell::{Cell, RefCell};
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use {Future, Task, TaskHandle, Poll};
+use util::Collapsed;
 
 /// Encapsulation of a value which has the ability to execute arbitrary code.
 ///
@@ -125,6 +130,290 @@ impl LimitState {
     }
 }
 
+/// A single-threaded executor which runs tasks in a reproducible order and
+/// keeps track of a virtual clock.
+///
+/// This executor is primarily intended for tests. Tasks spawned onto it are
+/// never polled until one of the `run*` methods is called, at which point
+/// they're polled on the current thread in the order that they were woken up.
+/// Notifications delivered from other threads (or from the middle of another
+/// task's `poll`) are queued up rather than running the task inline, so the
+/// same test will always poll tasks in the same order.
+///
+/// Each wake-up of a task is also recorded and can be inspected through the
+/// `notified` method, and timeouts created through the `timeout` method only
+/// fire when the virtual clock is moved forward with `advance`, so no test has
+/// to actually sleep.
+///
+/// Handles to a `Deterministic` executor are cloneable and all clones refer to
+/// the same executor.
+#[derive(Clone)]
+pub struct Deterministic {
+    inner: Arc<DetInner>,
+}
+
+struct DetInner {
+    state: Mutex<DetState>,
+}
+
+struct DetState {
+    queue: VecDeque<(TaskHandle, Box<ExecuteCallback>)>,
+    notified: Vec<TaskHandle>,
+    now: Duration,
+    next_timer: usize,
+    timers: HashMap<usize, (Duration, Option<TaskHandle>)>,
+}
+
+thread_local!(static DET_CURRENT: RefCell<Option<TaskHandle>> = RefCell::new(None));
+
+/// The root future of each task spawned onto a `Deterministic` executor.
+///
+/// This future will only poll its contents if the executor is currently
+/// running this particular task, and otherwise it requests to get polled on the
+/// executor via `Task::poll_on`.
+struct DetRoot<F: Future> {
+    inner: Collapsed<F>,
+    exec: Deterministic,
+    task_exec: Option<Arc<Executor>>,
+}
+
+/// Executor handed to `Task::poll_on` for one task of a `Deterministic`
+/// executor, remembering which task the enqueued callback belongs to.
+struct DetTaskExecutor {
+    exec: Deterministic,
+    handle: TaskHandle,
+}
+
+/// A future which resolves once the virtual clock of a `Deterministic`
+/// executor has reached a particular point in time.
+///
+/// Created by the `Deterministic::timeout` method.
+pub struct VirtualTimeout {
+    at: Duration,
+    id: usize,
+    exec: Deterministic,
+}
+
+impl Deterministic {
+    /// Creates a new executor with no tasks and with the virtual clock set to
+    /// zero.
+    pub fn new() -> Deterministic {
+        Deterministic {
+            inner: Arc::new(DetInner {
+                state: Mutex::new(DetState {
+                    queue: VecDeque::new(),
+                    notified: Vec::new(),
+                    now: Duration::new(0, 0),
+                    next_timer: 0,
+                    timers: HashMap::new(),
+                }),
+            }),
+        }
+    }
+
+    /// Spawns a new task onto this executor which will drive `f` to
+    /// completion.
+    ///
+    /// The task is not polled immediately, but rather it's enqueued to get
+    /// polled the next time this executor is run. The handle to the newly
+    /// created task is returned so it can be compared against the handles
+    /// returned from `notified`.
+    pub fn spawn<F>(&self, f: F) -> TaskHandle
+        where F: Future<Item=(), Error=()>,
+    {
+        let task = Task::new();
+        let handle = task.handle().clone();
+        task.run(Box::new(DetRoot {
+            inner: Collapsed::Start(f),
+            exec: self.clone(),
+            task_exec: None,
+        }));
+        handle
+    }
+
+    /// Spawns `f` onto this executor and then runs the executor until no more
+    /// progress can be made.
+    ///
+    /// Returns `None` if the future didn't complete, or otherwise the result of
+    /// the future.
+    pub fn run<F: Future>(&self, f: F) -> Option<Result<F::Item, F::Error>> {
+        let result = Arc::new(Mutex::new(None));
+        let result2 = result.clone();
+        self.spawn(f.then(move |res| {
+            *result2.lock().unwrap() = Some(res);
+            Ok(())
+        }));
+        self.run_until_stalled();
+        let mut result = result.lock().unwrap();
+        result.take()
+    }
+
+    /// Polls all tasks which have been woken up, in the order that they were
+    /// woken up, until there are no more tasks left to poll.
+    ///
+    /// Returns the number of times a task was polled.
+    pub fn run_until_stalled(&self) -> usize {
+        let mut polls = 0;
+        loop {
+            let next = self.inner.state.lock().unwrap().queue.pop_front();
+            let (handle, callback) = match next {
+                Some(pair) => pair,
+                None => return polls,
+            };
+            polls += 1;
+            DET_CURRENT.with(|c| *c.borrow_mut() = Some(handle));
+            callback.call();
+            DET_CURRENT.with(|c| *c.borrow_mut() = None);
+        }
+    }
+
+    /// Returns the current time on this executor's virtual clock, relative to
+    /// when the executor was created.
+    pub fn now(&self) -> Duration {
+        self.inner.state.lock().unwrap().now
+    }
+
+    /// Moves the virtual clock forward by `dur`, firing all timeouts which
+    /// expire along the way.
+    ///
+    /// Timeouts are fired in order of their deadline (ties are broken by the
+    /// order in which they were created) and afterwards the executor is run
+    /// until it's stalled.
+    pub fn advance(&self, dur: Duration) {
+        let mut expired = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.now = state.now + dur;
+            let now = state.now;
+            let ids = state.timers.iter()
+                           .filter(|&(_, &(at, _))| at <= now)
+                           .map(|(id, &(at, _))| (at, *id))
+                           .collect::<Vec<_>>();
+            ids.into_iter().map(|(at, id)| {
+                let handle = state.timers.get_mut(&id).unwrap().1.take();
+                (at, id, handle)
+            }).collect::<Vec<_>>()
+        };
+        expired.sort_by_key(|&(at, id, _)| (at, id));
+        for (_, _, handle) in expired {
+            if let Some(handle) = handle {
+                handle.notify();
+            }
+        }
+        self.run_until_stalled();
+    }
+
+    /// Creates a new future which will resolve once the virtual clock has been
+    /// advanced by at least `dur` from the current time.
+    pub fn timeout(&self, dur: Duration) -> VirtualTimeout {
+        let mut state = self.inner.state.lock().unwrap();
+        let id = state.next_timer;
+        let at = state.now + dur;
+        state.next_timer += 1;
+        state.timers.insert(id, (at, None));
+        VirtualTimeout {
+            at: at,
+            id: id,
+            exec: self.clone(),
+        }
+    }
+
+    /// Returns the list of tasks which have been woken up since the last call
+    /// to this method, in the order that they were woken up.
+    ///
+    /// A task shows up in this list each time it's enqueued to get polled,
+    /// including when it's first spawned.
+    pub fn notified(&self) -> Vec<TaskHandle> {
+        let mut state = self.inner.state.lock().unwrap();
+        state.notified.drain(..).collect()
+    }
+
+    /// Returns whether there are any tasks waiting to get polled.
+    pub fn is_stalled(&self) -> bool {
+        self.inner.state.lock().unwrap().queue.is_empty()
+    }
+}
+
+impl<F: Future> Future for DetRoot<F> {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self, task: &mut Task) -> Poll<(), ()> {
+        // Only poll the inner future if we're being run by the executor for
+        // this particular task, and consume that permission so recursive
+        // notifications don't poll us inline.
+        let allowed = DET_CURRENT.with(|c| {
+            let mut c = c.borrow_mut();
+            let mine = match *c {
+                Some(ref h) => h.equivalent(task.handle()),
+                None => false,
+            };
+            if mine {
+                *c = None;
+            }
+            mine
+        });
+        if allowed {
+            return self.inner.poll(task).map(|_| ()).map_err(|_| ())
+        }
+
+        if self.task_exec.is_none() {
+            self.task_exec = Some(Arc::new(DetTaskExecutor {
+                exec: self.exec.clone(),
+                handle: task.handle().clone(),
+            }));
+        }
+        task.poll_on(self.task_exec.clone().unwrap());
+        Poll::NotReady
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        self.inner.schedule(task)
+    }
+
+    fn tailcall(&mut self) -> Option<Box<Future<Item=(), Error=()>>> {
+        self.inner.collapse();
+        None
+    }
+}
+
+impl Executor for DetTaskExecutor {
+    fn execute_boxed(&self, f: Box<ExecuteCallback>) {
+        let mut state = self.exec.inner.state.lock().unwrap();
+        state.notified.push(self.handle.clone());
+        state.queue.push_back((self.handle.clone(), f));
+    }
+}
+
+impl Future for VirtualTimeout {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self, _task: &mut Task) -> Poll<(), ()> {
+        if self.at <= self.exec.now() {
+            Poll::Ok(())
+        } else {
+            Poll::NotReady
+        }
+    }
+
+    fn schedule(&mut self, task: &mut Task) {
+        let mut state = self.exec.inner.state.lock().unwrap();
+        if self.at <= state.now {
+            drop(state);
+            return task.notify()
+        }
+        if let Some(timer) = state.timers.get_mut(&self.id) {
+            timer.1 = Some(task.handle().clone());
+        }
+    }
+}
+
+impl Drop for VirtualTimeout {
+    fn drop(&mut self) {
+        self.exec.inner.state.lock().unwrap().timers.remove(&self.id);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
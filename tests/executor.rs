@@ -47,3 +47,72 @@ fn simple() {
 
     assert!(EXECUTOR_HIT.with(|p| p.get()));
 }
+
+#[test]
+fn deterministic_order() {
+    use std::sync::Mutex;
+    use futures::executor::Deterministic;
+
+    let exec = Deterministic::new();
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let (c1, p1) = futures::promise::<u32>();
+    let (c2, p2) = futures::promise::<u32>();
+
+    let order1 = order.clone();
+    let t1 = exec.spawn(p1.map(move |i| order1.lock().unwrap().push(i))
+                          .map_err(|_| ()));
+    let order2 = order.clone();
+    let t2 = exec.spawn(p2.map(move |i| order2.lock().unwrap().push(i))
+                          .map_err(|_| ()));
+
+    // Nothing runs until the executor is run
+    assert_eq!(exec.run_until_stalled(), 2);
+    assert!(order.lock().unwrap().is_empty());
+    let notified = exec.notified();
+    assert_eq!(notified.len(), 2);
+    assert!(notified[0].equivalent(&t1));
+    assert!(notified[1].equivalent(&t2));
+
+    // Completing the promises only enqueues the tasks, in order of completion
+    c2.complete(2);
+    c1.complete(1);
+    assert!(order.lock().unwrap().is_empty());
+    let notified = exec.notified();
+    assert_eq!(notified.len(), 2);
+    assert!(notified[0].equivalent(&t2));
+    assert!(notified[1].equivalent(&t1));
+
+    assert_eq!(exec.run_until_stalled(), 2);
+    assert_eq!(*order.lock().unwrap(), [2, 1]);
+    assert!(exec.is_stalled());
+}
+
+#[test]
+fn deterministic_virtual_time() {
+    use std::time::Duration;
+    use futures::executor::Deterministic;
+
+    let exec = Deterministic::new();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let tx2 = tx.clone();
+    exec.spawn(exec.timeout(Duration::from_secs(10)).map(move |()| {
+        tx.send(10).unwrap();
+    }));
+    exec.spawn(exec.timeout(Duration::from_secs(5)).map(move |()| {
+        tx2.send(5).unwrap();
+    }));
+    exec.run_until_stalled();
+    assert!(rx.try_recv().is_err());
+
+    exec.advance(Duration::from_secs(4));
+    assert!(rx.try_recv().is_err());
+    exec.advance(Duration::from_secs(1));
+    assert_eq!(rx.try_recv(), Ok(5));
+    assert!(rx.try_recv().is_err());
+    exec.advance(Duration::from_secs(60));
+    assert_eq!(rx.try_recv(), Ok(10));
+    assert_eq!(exec.now(), Duration::from_secs(65));
+
+    let res = exec.run(exec.timeout(Duration::from_secs(1)));
+    assert_eq!(res, None);
+}